@@ -0,0 +1,259 @@
+//! Generates control tables from the model definition files in `models/`.
+//!
+//! The model files follow the layout of the Robotis Dynamixel Wizard `.model` files.
+//! Columns are tab separated, lines starting with `#` are comments and `-` marks an absent value.
+//!
+//! ```text
+//! [type info]
+//! name            value
+//! model_name      MX-28
+//! model_number    29
+//! protocol        1
+//! value_of_min_radian_position    0
+//! value_of_zero_radian_position   2048
+//! value_of_max_radian_position    4095
+//! min_radian      -3.14159265
+//! max_radian      3.14159265
+//!
+//! [control table]
+//! Address Size    Data Name       Access  Memory  Type    Min     Max     Unit
+//! 30      2       Goal_Position   RW      RAM     u16     0       4095    0.088 deg
+//! ```
+//!
+//! Every model becomes a module in `models`, containing a `control_table1!`/`control_table2!` invocation.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+struct Register {
+    address: u32,
+    size: u32,
+    name: String,
+    access: String,
+    memory: String,
+    rust_type: String,
+    min: Option<String>,
+    max: Option<String>,
+    unit: Option<String>,
+}
+
+struct Model {
+    module: String,
+    name: String,
+    model_number: String,
+    protocol: String,
+    /// Raw value of the min, zero and max radian position followed by the min and max radian
+    position: Option<[String; 5]>,
+    registers: Vec<Register>,
+}
+
+fn optional(value: &str) -> Option<String> {
+    if value == "-" {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn type_size(rust_type: &str) -> u32 {
+    match rust_type {
+        "bool" | "u8" | "i8" => 1,
+        "u16" | "i16" => 2,
+        "u32" | "i32" => 4,
+        x => panic!("unsupported register type: {}", x),
+    }
+}
+
+/// `Goal_Position` becomes `GoalPosition` and `LED_Red` becomes `LedRed`
+fn type_name(data_name: &str) -> String {
+    data_name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars.flat_map(|c| c.to_lowercase())).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// `MX-28` becomes `mx28`
+fn module_name(model_name: &str) -> String {
+    model_name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+fn parse(path: &Path) -> Model {
+    let content = fs::read_to_string(path).unwrap_or_else(|e| panic!("could not read {}: {}", path.display(), e));
+    let error = |line: usize, msg: &str| -> ! { panic!("{}:{}: {}", path.display(), line + 1, msg) };
+
+    let mut section = String::new();
+    let mut header_seen = false;
+    let mut name = None;
+    let mut model_number = None;
+    let mut protocol = None;
+    let mut position: [Option<String>; 5] = Default::default();
+    let mut registers: Vec<Register> = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            section = line.to_string();
+            header_seen = false;
+            continue;
+        }
+        // The first line of every section is a column header
+        if !header_seen {
+            header_seen = true;
+            continue;
+        }
+
+        let columns: Vec<&str> = line.split('\t').map(str::trim).filter(|c| !c.is_empty()).collect();
+        match section.as_str() {
+            "[type info]" => {
+                if columns.len() != 2 {
+                    error(i, "expected two columns");
+                }
+                match columns[0] {
+                    "model_name" => name = Some(columns[1].to_string()),
+                    "model_number" => model_number = Some(columns[1].to_string()),
+                    "protocol" => protocol = Some(columns[1].to_string()),
+                    "value_of_min_radian_position" => position[0] = Some(columns[1].to_string()),
+                    "value_of_zero_radian_position" => position[1] = Some(columns[1].to_string()),
+                    "value_of_max_radian_position" => position[2] = Some(columns[1].to_string()),
+                    "min_radian" => position[3] = Some(columns[1].to_string()),
+                    "max_radian" => position[4] = Some(columns[1].to_string()),
+                    _ => (),
+                }
+            },
+            "[control table]" => {
+                if columns.len() != 9 {
+                    error(i, "expected nine columns");
+                }
+                let register = Register {
+                    address: columns[0].parse().unwrap_or_else(|_| error(i, "invalid address")),
+                    size: columns[1].parse().unwrap_or_else(|_| error(i, "invalid size")),
+                    name: columns[2].to_string(),
+                    access: columns[3].to_string(),
+                    memory: columns[4].to_string(),
+                    rust_type: columns[5].to_string(),
+                    min: optional(columns[6]),
+                    max: optional(columns[7]),
+                    unit: optional(columns[8]),
+                };
+                if register.size != type_size(&register.rust_type) {
+                    error(i, "size does not match type");
+                }
+                if register.access != "R" && register.access != "RW" {
+                    error(i, "access must be R or RW");
+                }
+                if register.memory != "EEPROM" && register.memory != "RAM" {
+                    error(i, "memory must be EEPROM or RAM");
+                }
+                if let Some(previous) = registers.last() {
+                    if previous.address + previous.size > register.address {
+                        error(i, "registers must be ordered by address and not overlap");
+                    }
+                }
+                registers.push(register);
+            },
+            _ => error(i, "line outside of a known section"),
+        }
+    }
+
+    let name = name.unwrap_or_else(|| panic!("{}: missing model_name", path.display()));
+    for register in &registers {
+        if register.min.is_some() != register.max.is_some() {
+            panic!("{}: register {} of {} has only one of min and max, give both or neither", path.display(), register.name, name);
+        }
+    }
+    let position = match position {
+        [Some(a), Some(b), Some(c), Some(d), Some(e)] => Some([a, b, c, d, e]),
+        [None, None, None, None, None] => None,
+        _ => panic!("{}: the radian position values must either all be present or all be absent", path.display()),
+    };
+    Model {
+        module: module_name(&name),
+        name,
+        model_number: model_number.unwrap_or_else(|| panic!("{}: missing model_number", path.display())),
+        protocol: protocol.unwrap_or_else(|| panic!("{}: missing protocol", path.display())),
+        position,
+        registers,
+    }
+}
+
+fn generate(model: &Model, out: &mut String) {
+    let protocol = match model.protocol.as_str() {
+        "1" | "2" => &model.protocol,
+        x => panic!("{}: unsupported protocol {}", model.name, x),
+    };
+
+    out.push_str(&format!("/// Control table of {}\npub mod {} {{\n", model.name, model.module));
+    out.push_str(&format!("    pub trait Register: ::protocol{}::Register {{}}\n", protocol));
+    out.push_str(&format!("    pub trait ReadRegister: ::protocol{}::ReadRegister {{}}\n", protocol));
+    out.push_str(&format!("    pub trait WriteRegister: ::protocol{}::WriteRegister {{}}\n\n", protocol));
+    out.push_str(&format!("    control_table{}!{{\n", protocol));
+    out.push_str(&format!("        {:?}, {}", model.name, model.model_number));
+    if let Some(ref position) = model.position {
+        out.push_str(&format!(", position {}", position.join(", ")));
+    }
+    out.push_str(";\n");
+    for register in &model.registers {
+        out.push_str(&format!(
+            "        {} {}: {} = {}, {}, {:?}",
+            if register.access == "RW" {"rw"} else {"r"},
+            type_name(&register.name),
+            register.rust_type,
+            register.address,
+            if register.memory == "EEPROM" {"Eeprom"} else {"Ram"},
+            register.name.replace('_', " "),
+        ));
+        if let (Some(min), Some(max)) = (&register.min, &register.max) {
+            out.push_str(&format!(", range {}..={}", min, max));
+        }
+        if let Some(ref unit) = register.unit {
+            out.push_str(&format!(", unit {:?}", unit));
+        }
+        out.push_str(";\n");
+    }
+    out.push_str("    }\n}\n\n");
+}
+
+fn main() {
+    let models_dir = Path::new("models");
+    println!("cargo:rerun-if-changed=models");
+
+    let mut paths: Vec<_> = fs::read_dir(models_dir)
+        .expect("could not read models directory")
+        .map(|entry| entry.expect("could not read models directory").path())
+        .filter(|path| path.extension() == Some("model".as_ref()))
+        .collect();
+    paths.sort();
+
+    let mut out = String::from("// Generated by build.rs from the files in models/, do not edit.\n\n");
+    let mut modules = Vec::new();
+    for path in &paths {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let model = parse(path);
+        generate(&model, &mut out);
+        modules.push(model.module);
+    }
+
+    out.push_str("/// Control tables of all models generated from the files in `models/`\n");
+    out.push_str("pub static CONTROL_TABLES: &[&::descriptor::ControlTable] = &[\n");
+    for module in &modules {
+        out.push_str(&format!("    &{}::CONTROL_TABLE,\n", module));
+    }
+    out.push_str("];\n");
+
+    let out_path = Path::new(&env::var("OUT_DIR").unwrap()).join("models.rs");
+    let mut file = fs::File::create(out_path).expect("could not create models.rs");
+    file.write_all(out.as_bytes()).expect("could not write models.rs");
+}
@@ -6,6 +6,7 @@ use std::io::{
     self
 };
 use std::str::FromStr;
+use std::f32::consts::PI;
 
 
 use dynamixel::BaudRate;
@@ -14,21 +15,29 @@ use dynamixel::Interface;
 fn main() {
     badlog::init_from_env("LOG_LEVEL");
     
-    let ports = serialport::available_ports().unwrap();
-
-    println!("Choose a serial device (0 - {})", ports.len() - 1);
-    for (i, port) in ports.iter().enumerate() {
-        println!("({}) {:?}", i, port);
-    }
-    
     let reader = io::stdin();
-    let mut buffer = String::new();
-    reader.read_line(&mut buffer).ok().unwrap();
-    buffer.pop().unwrap(); // remove new-line
-    
-    let index = usize::from_str(&buffer).unwrap();
-    
-    let mut serial = serialport::open(&ports[index].port_name).unwrap();
+
+    // The port may be given as argument, e.g. the pseudo-terminal of dynamixel-emulator
+    let port_name = match std::env::args().nth(1) {
+        Some(port_name) => port_name,
+        None => {
+            let ports = serialport::available_ports().unwrap();
+
+            println!("Choose a serial device (0 - {})", ports.len() - 1);
+            for (i, port) in ports.iter().enumerate() {
+                println!("({}) {:?}", i, port);
+            }
+
+            let mut buffer = String::new();
+            reader.read_line(&mut buffer).ok().unwrap();
+            buffer.pop().unwrap(); // remove new-line
+
+            let index = usize::from_str(&buffer).unwrap();
+            ports[index].port_name.clone()
+        },
+    };
+
+    let mut serial = serialport::open(&port_name).unwrap();
     serial.set_baud_rate(BaudRate::Baud1000000).unwrap();
 
     let interfaces = dynamixel::enumerate(&mut serial).unwrap();
@@ -47,9 +56,9 @@ fn main() {
 
     
     let pos = servo.get_position(&mut serial).unwrap();
-    let mut target_pos = pos + 3.14/2.0;
-    if target_pos >= 3.14 {
-        target_pos -= 6.28;
+    let mut target_pos = pos + PI/2.0;
+    if target_pos >= PI {
+        target_pos -= 2.0*PI;
     }
     
     
@@ -0,0 +1,274 @@
+//! Async communication with servos
+//!
+//! Requires the `async` feature. `AsyncInterface` is the non-blocking counterpart of `Interface`,
+//! and `protocol1::AsyncServo`/`protocol2::AsyncServo` offer `ping`, `read` and `write` returning futures.
+//! Instructions and statuses are serialized and deserialized by the same code as the blocking servos.
+//!
+//! The futures do not depend on an executor. The `tokio` feature adds `TokioInterface`,
+//! an `AsyncInterface` over any `tokio::io::AsyncRead + AsyncWrite` stream.
+
+use lib::fmt;
+use lib::future::Future;
+use lib::pin::Pin;
+use lib::task::{Context, Poll};
+#[cfg(any(test, feature="tokio"))]
+use lib::task::{RawWaker, RawWakerVTable, Waker};
+use lib::time::Duration;
+
+use BaudRate;
+use CommunicationError;
+use transaction::{Decode, Transaction};
+
+pub mod protocol1;
+pub mod protocol2;
+#[cfg(feature="tokio")]
+mod tokio_impl;
+
+#[cfg(feature="tokio")]
+pub use self::tokio_impl::TokioInterface;
+
+/// The non-blocking interface for communicating with dynamixel servos
+pub trait AsyncInterface {
+    /// The error type of the interface, converted into a `CommunicationError` when a transaction fails
+    type Error: Into<CommunicationError> + fmt::Debug;
+
+    /// Set the baud rate of the interface
+    ///
+    /// `BaudRate` must not be matched against exhaustively.
+    fn set_baud_rate(&mut self, b: BaudRate) -> Result<(), Self::Error>;
+
+    /// Set the time `poll_read` waits for bytes before timing out
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), Self::Error>;
+
+    /// Discard received bytes that are not read yet, without waiting for more
+    fn flush(&mut self);
+
+    /// Attempt to read bytes into `data`, returning the number of bytes read
+    ///
+    /// Reading no bytes for the timeout since the last byte was written or read must
+    /// resolve to an error that converts into `CommunicationError::TimedOut`.
+    fn poll_read(&mut self, cx: &mut Context, data: &mut [u8]) -> Poll<Result<usize, Self::Error>>;
+
+    /// Attempt to write bytes from `data`, returning the number of bytes written
+    ///
+    /// The bytes must be on their way to the servos when the write resolves, and not held back in a buffer.
+    fn poll_write(&mut self, cx: &mut Context, data: &[u8]) -> Poll<Result<usize, Self::Error>>;
+}
+
+/// Prepare the interface for a transaction with a servo
+fn begin_transaction<A: AsyncInterface>(interface: &mut A, baud_rate: BaudRate, timeout: Duration) -> Result<(), CommunicationError> {
+    interface.set_baud_rate(baud_rate).map_err(::communication_error)?;
+    interface.set_timeout(timeout).map_err(::communication_error)?;
+    interface.flush();
+    Ok(())
+}
+
+impl<D: Decode> Transaction<D> {
+    /// Write the instruction and decode the status from an async interface
+    fn poll<A: AsyncInterface>(&mut self, interface: &mut A, cx: &mut Context) -> Poll<Result<Option<D::Output>, D::Error>> {
+        loop {
+            if !self.unwritten().is_empty() {
+                match interface.poll_write(cx, self.unwritten()) {
+                    Poll::Ready(Ok(0)) => return Poll::Ready(Err(D::Error::from(CommunicationError::Other))),
+                    Poll::Ready(Ok(n)) => self.wrote(n),
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(D::Error::from(::communication_error(e)))),
+                    Poll::Pending => return Poll::Pending,
+                }
+                continue;
+            }
+
+            let wanted = self.wanted();
+            if wanted == 0 {
+                return Poll::Ready(self.finish());
+            }
+
+            let mut data = [0u8; 16];
+            let data = &mut data[..::lib::cmp::min(wanted, 16)];
+            match interface.poll_read(cx, data) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(D::Error::from(CommunicationError::Other))),
+                Poll::Ready(Ok(n)) => {
+                    if let Err(e) = self.feed(&data[..n]) {
+                        return Poll::Ready(Err(e));
+                    }
+                },
+                Poll::Ready(Err(e)) => return Poll::Ready(match ::communication_error(e) {
+                    CommunicationError::TimedOut => self.timed_out(),
+                    e => Err(D::Error::from(e)),
+                }),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// A `Transaction` on an async interface
+struct TransactionFuture<'a, A: 'a, D: Decode> {
+    interface: &'a mut A,
+    transaction: Transaction<D>,
+    error: Option<D::Error>,
+}
+
+impl<'a, A: AsyncInterface, D: Decode> TransactionFuture<'a, A, D> {
+    /// Run `transaction`, unless beginning it failed with `begun`
+    fn new(interface: &'a mut A, transaction: Transaction<D>, begun: Result<(), D::Error>) -> Self {
+        TransactionFuture {
+            interface: interface,
+            transaction: transaction,
+            error: begun.err(),
+        }
+    }
+}
+
+// The transaction is never pinned, it only holds bytes
+impl<'a, A, D: Decode> Unpin for TransactionFuture<'a, A, D> {}
+
+impl<'a, A: AsyncInterface, D: Decode> Future for TransactionFuture<'a, A, D> {
+    type Output = Result<Option<D::Output>, D::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Some(e) = this.error.take() {
+            return Poll::Ready(Err(e));
+        }
+        this.transaction.poll(this.interface, cx)
+    }
+}
+
+/// Apply `f` to the output of `future`
+struct Map<F, G> {
+    future: F,
+    f: Option<G>,
+}
+
+impl<F, G> Map<F, G> {
+    fn new(future: F, f: G) -> Self {
+        Map {
+            future: future,
+            f: Some(f),
+        }
+    }
+}
+
+impl<F: Future + Unpin, G: FnOnce(F::Output) -> U + Unpin, U> Future for Map<F, G> {
+    type Output = U;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<U> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.future).poll(cx) {
+            Poll::Ready(output) => Poll::Ready((this.f.take().expect("Map polled after completion"))(output)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Enumerate all servos connected to the interface
+///
+/// This function returns a Vec and thus requires the `std` feature.
+#[cfg(feature="std")]
+pub fn enumerate<'a, A: AsyncInterface>(interface: &'a mut A) -> impl Future<Output=Result<Vec<::ServoInfo>, CommunicationError>> + 'a {
+    Enumerate {
+        interface: interface,
+        protocol1: Some(protocol1::Enumeration::new()),
+        protocol2: protocol2::Enumeration::new(),
+        servos: Vec::new(),
+    }
+}
+
+#[cfg(feature="std")]
+struct Enumerate<'a, A: 'a> {
+    interface: &'a mut A,
+    protocol1: Option<protocol1::Enumeration>,
+    protocol2: protocol2::Enumeration,
+    servos: Vec<::ServoInfo>,
+}
+
+#[cfg(feature="std")]
+impl<'a, A: AsyncInterface> Future for Enumerate<'a, A> {
+    type Output = Result<Vec<::ServoInfo>, CommunicationError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        if let Some(ref mut enumeration) = this.protocol1 {
+            match enumeration.poll(this.interface, cx) {
+                Poll::Ready(Ok(servos)) => this.servos.extend(servos.into_iter().map(::ServoInfo::Protocol1)),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        this.protocol1 = None;
+        match this.protocol2.poll(this.interface, cx) {
+            Poll::Ready(Ok(servos)) => {
+                this.servos.extend(servos.into_iter().map(::ServoInfo::Protocol2));
+                Poll::Ready(Ok(::lib::mem::take(&mut this.servos)))
+            },
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// A waker that does nothing, for polling without an executor when nothing waits on the wake up
+#[cfg(any(test, feature="tokio"))]
+pub(crate) fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(::lib::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+    // The vtable functions ignore the data pointer, so any pointer upholds the contract of `RawWaker`
+    unsafe { Waker::from_raw(RawWaker::new(::lib::ptr::null(), &VTABLE)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replies with `response` to everything written
+    pub(crate) struct ScriptedInterface {
+        pub written: [u8; 32],
+        pub written_len: usize,
+        pub response: &'static [u8],
+        pub pos: usize,
+    }
+
+    impl ScriptedInterface {
+        pub fn new(response: &'static [u8]) -> Self {
+            ScriptedInterface{written: [0; 32], written_len: 0, response: response, pos: 0}
+        }
+    }
+
+    impl AsyncInterface for ScriptedInterface {
+        type Error = CommunicationError;
+
+        fn set_baud_rate(&mut self, _b: BaudRate) -> Result<(), CommunicationError> {Ok(())}
+        fn set_timeout(&mut self, _timeout: Duration) -> Result<(), CommunicationError> {Ok(())}
+        fn flush(&mut self) {}
+
+        fn poll_read(&mut self, _cx: &mut Context, data: &mut [u8]) -> Poll<Result<usize, CommunicationError>> {
+            // Hand out one byte at a time, to exercise the incremental decoding
+            if self.pos == self.response.len() {
+                return Poll::Ready(Err(CommunicationError::TimedOut));
+            }
+            data[0] = self.response[self.pos];
+            self.pos += 1;
+            Poll::Ready(Ok(1))
+        }
+
+        fn poll_write(&mut self, _cx: &mut Context, data: &[u8]) -> Poll<Result<usize, CommunicationError>> {
+            self.written[self.written_len..self.written_len + data.len()].copy_from_slice(data);
+            self.written_len += data.len();
+            Poll::Ready(Ok(data.len()))
+        }
+    }
+
+    /// Poll a future that never returns `Pending`
+    pub(crate) fn ready<F: Future + Unpin>(mut future: F) -> F::Output {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        match Pin::new(&mut future).poll(&mut cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future is pending"),
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//! Async protocol 1 servos
+
+use lib::future::Future;
+use lib::time::Duration;
+#[cfg(feature="std")]
+use lib::mem;
+#[cfg(feature="std")]
+use lib::task::{Context, Poll};
+
+use BaudRate;
+use Reply;
+use StatusReturnLevel;
+#[cfg(feature="std")]
+use CommunicationError;
+use protocol1::{instruction, Error, PacketID, ReadRegister, ServoID, StatusDecoder, WriteRegister};
+use transaction::Transaction;
+use super::{begin_transaction, AsyncInterface, Map, TransactionFuture};
+
+/// A protocol 1 servo on an `AsyncInterface`
+///
+/// Reads and writes any protocol 1 register, it is up to the user to only use the registers of the servo model.
+#[derive(Debug, Clone)]
+pub struct AsyncServo {
+    id: ServoID,
+    baud_rate: BaudRate,
+    timeout: Duration,
+    status_return_level: StatusReturnLevel,
+}
+
+impl AsyncServo {
+    pub fn new(id: ServoID, baud_rate: BaudRate) -> Self {
+        AsyncServo {
+            id: id,
+            baud_rate: baud_rate,
+            timeout: ::DEFAULT_TIMEOUT,
+            status_return_level: StatusReturnLevel::default(),
+        }
+    }
+
+    pub fn id(&self) -> ServoID {
+        self.id
+    }
+
+    pub fn baud_rate(&self) -> BaudRate {
+        self.baud_rate
+    }
+
+    /// Set how long to wait for the status of an instruction
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// The status return level the servo is assumed to be configured with
+    pub fn status_return_level(&self) -> StatusReturnLevel {
+        self.status_return_level
+    }
+
+    /// Assume the servo is configured with `status_return_level`, without writing it
+    ///
+    /// Reads fail with `Error::NotAnswered` right away when the servo only answers pings.
+    pub fn assume_status_return_level(&mut self, status_return_level: StatusReturnLevel) {
+        self.status_return_level = status_return_level;
+    }
+
+    /// Ping the servo, resolving to `Ok(())` if it exists.
+    pub fn ping<'a, A: AsyncInterface>(&self, interface: &'a mut A) -> impl Future<Output=Result<(), Error>> + 'a {
+        let ping = instruction::Ping::new(PacketID::from(self.id));
+        let transaction = Transaction::new(::protocol1::packet(&ping), StatusDecoder::<instruction::Pong>::new(PacketID::from(self.id)), Reply::Always);
+        let begun = begin_transaction(interface, self.baud_rate, self.timeout).map_err(Error::from);
+        Map::new(TransactionFuture::new(interface, transaction, begun), |status: Result<Option<instruction::Pong>, Error>| status.map(|_| ()))
+    }
+
+    /// Write the given data `register` to the servo.
+    pub fn write<'a, W: WriteRegister, A: AsyncInterface>(&self, interface: &'a mut A, register: W) -> impl Future<Output=Result<(), Error>> + 'a {
+        let write = instruction::WriteData::new(PacketID::from(self.id), register);
+        let transaction = Transaction::new(::protocol1::packet(&write), StatusDecoder::<instruction::WriteDataResponse>::new(PacketID::from(self.id)), self.status_return_level.write_reply());
+        let begun = begin_transaction(interface, self.baud_rate, self.timeout).map_err(Error::from);
+        Map::new(TransactionFuture::new(interface, transaction, begun), |status: Result<Option<instruction::WriteDataResponse>, Error>| status.map(|_| ()))
+    }
+
+    /// Read data from a register
+    pub fn read<'a, R: ReadRegister + 'a, A: AsyncInterface>(&self, interface: &'a mut A) -> impl Future<Output=Result<R, Error>> + 'a {
+        let read = instruction::ReadData::<R>::new(PacketID::from(self.id));
+        let transaction = Transaction::new(::protocol1::packet(&read), StatusDecoder::<instruction::ReadDataResponse<R>>::new(PacketID::from(self.id)), Reply::Always);
+        // Fail right away if the servo does not answer reads, rather than waiting for the timeout
+        let begun = if self.status_return_level.replies_to_read() {
+            begin_transaction(interface, self.baud_rate, self.timeout).map_err(Error::from)
+        } else {
+            Err(Error::NotAnswered)
+        };
+        Map::new(TransactionFuture::new(interface, transaction, begun), |status: Result<Option<instruction::ReadDataResponse<R>>, Error>| {
+            status.map(|response| response.expect("a status is always expected for reads").data)
+        })
+    }
+}
+
+/// Enumeration of protocol 1 servos, baud rate by baud rate
+///
+/// Protocol 1 servos do not answer a broadcast ping reliably, so every ID is pinged on its own,
+/// and the model number and firmware version of every servo that answered are read right away.
+#[cfg(feature="std")]
+pub(crate) struct Enumeration {
+    state: State,
+    servos: Vec<::protocol1::ServoInfo>,
+}
+
+#[cfg(feature="std")]
+enum State {
+    Baud(usize),
+    Pinging(usize, u8, Transaction<StatusDecoder<instruction::Pong>>),
+    Resolving(usize, u8, Transaction<StatusDecoder<instruction::ReadDataRawResponse>>),
+    Done,
+}
+
+#[cfg(feature="std")]
+impl Enumeration {
+    pub fn new() -> Self {
+        Enumeration {
+            state: State::Baud(0),
+            servos: Vec::new(),
+        }
+    }
+
+    /// Ping the servo with the given `id`, or go on with the next baud rate after the last ID
+    fn ping<A: AsyncInterface>(interface: &mut A, baud: usize, id: u8) -> State {
+        if id > 253 {
+            return State::Baud(baud + 1);
+        }
+        interface.flush();
+        let ping = instruction::Ping::new(PacketID::unicast(id));
+        let transaction = Transaction::new(::protocol1::packet(&ping), StatusDecoder::new(PacketID::unicast(id)), Reply::Always);
+        State::Pinging(baud, id, transaction)
+    }
+
+    /// Read the model number and the firmware version of the servo with the given `id`
+    fn resolve<A: AsyncInterface>(interface: &mut A, baud: usize, id: u8) -> State {
+        interface.flush();
+        let read = instruction::ReadDataRaw::new(PacketID::unicast(id), ::protocol1::MODEL_NUMBER_ADDRESS, 3);
+        let transaction = Transaction::new(::protocol1::packet(&read), StatusDecoder::new(PacketID::unicast(id)), Reply::Always);
+        State::Resolving(baud, id, transaction)
+    }
+
+    pub fn poll<A: AsyncInterface>(&mut self, interface: &mut A, cx: &mut Context) -> Poll<Result<Vec<::protocol1::ServoInfo>, CommunicationError>> {
+        loop {
+            self.state = match mem::replace(&mut self.state, State::Done) {
+                State::Baud(baud) => {
+                    let b = match BaudRate::variants().get(baud) {
+                        Some(b) => *b,
+                        None => return Poll::Ready(Ok(mem::take(&mut self.servos))),
+                    };
+                    if interface.set_baud_rate(b).is_err() {
+                        warn!(target: "protocol1", "not able to enumerate devices on baudrate: {}", u32::from(b));
+                        State::Baud(baud + 1)
+                    } else if let Err(e) = interface.set_timeout(::enumeration::probe_timeout(b)) {
+                        return Poll::Ready(Err(::communication_error(e)));
+                    } else {
+                        Enumeration::ping(interface, baud, 0)
+                    }
+                },
+                State::Pinging(baud, id, mut transaction) => match transaction.poll(interface, cx) {
+                    // A servo reporting an error in the status is still there
+                    Poll::Ready(Ok(_)) | Poll::Ready(Err(Error::Processing(_))) => Enumeration::resolve(interface, baud, id),
+                    Poll::Ready(Err(Error::Communication(CommunicationError::TimedOut))) => Enumeration::ping(interface, baud, id + 1),
+                    Poll::Ready(Err(Error::Communication(e))) => return Poll::Ready(Err(e)),
+                    Poll::Ready(Err(e)) => {
+                        warn!(target: "protocol1", "received error: {:?} when pinging id: {} on baud: {}", e, id, u32::from(BaudRate::variants()[baud]));
+                        Enumeration::ping(interface, baud, id + 1)
+                    },
+                    Poll::Pending => {
+                        self.state = State::Pinging(baud, id, transaction);
+                        return Poll::Pending;
+                    },
+                },
+                State::Resolving(baud, id, mut transaction) => {
+                    let b = BaudRate::variants()[baud];
+                    match transaction.poll(interface, cx) {
+                        Poll::Ready(Ok(Some(ref response))) if response.length == 3 => self.servos.push(
+                            ::protocol1::ServoInfo{
+                                baud_rate: b,
+                                model_number: response.data[0] as u16 | (response.data[1] as u16) << 8,
+                                fw_version: response.data[2],
+                                id: ServoID::new(id),
+                            }),
+                        Poll::Ready(Ok(_)) => {
+                            warn!(target: "protocol1", "Found servo with baud: {} and id: {}. Could not resolve model number due to error: {:?}", u32::from(b), id, Error::Format(::protocol1::FormatError::Length));
+                        },
+                        Poll::Ready(Err(Error::Communication(e))) if e != CommunicationError::TimedOut => return Poll::Ready(Err(e)),
+                        Poll::Ready(Err(e)) => {
+                            warn!(target: "protocol1", "Found servo with baud: {} and id: {}. Could not resolve model number due to error: {:?}", u32::from(b), id, e);
+                        },
+                        Poll::Pending => {
+                            self.state = State::Resolving(baud, id, transaction);
+                            return Poll::Pending;
+                        },
+                    }
+                    Enumeration::ping(interface, baud, id + 1)
+                },
+                State::Done => panic!("enumeration polled after completion"),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::{ready, ScriptedInterface};
+
+    #[test]
+    fn test_ping() {
+        let mut interface = ScriptedInterface::new(&[0xff, 0xff, 0x03, 0x02, 0x00, 0xfa]);
+        let servo = AsyncServo::new(ServoID::new(3), BaudRate::Baud1000000);
+        assert_eq!(ready(servo.ping(&mut interface)), Ok(()));
+        assert_eq!(&interface.written[..interface.written_len], &[0xff, 0xff, 0x03, 0x02, 0x01, 0xf9]);
+
+        let mut interface = ScriptedInterface::new(&[0xff, 0xff, 0x04, 0x02, 0x00, 0xf9]);
+        assert_eq!(ready(servo.ping(&mut interface)), Err(Error::Format(::protocol1::FormatError::ID)));
+    }
+
+    #[test]
+    fn test_read_ping_only() {
+        let mut interface = ScriptedInterface::new(&[]);
+        let mut servo = AsyncServo::new(ServoID::new(3), BaudRate::Baud1000000);
+        servo.assume_status_return_level(StatusReturnLevel::PingOnly);
+        assert_eq!(ready(servo.read::<::dynamixel::mx28::control_table::PresentPosition, _>(&mut interface)).err(), Some(Error::NotAnswered));
+        assert_eq!(interface.written_len, 0);
+    }
+
+    /// A servo with ID 3 at 1 000 000 baud, answering pings and the read of its model number and firmware version
+    #[cfg(feature="std")]
+    struct SingleServo {
+        baud_rate: BaudRate,
+        response: Vec<u8>,
+    }
+
+    #[cfg(feature="std")]
+    impl AsyncInterface for SingleServo {
+        type Error = CommunicationError;
+
+        fn set_baud_rate(&mut self, b: BaudRate) -> Result<(), CommunicationError> {
+            self.baud_rate = b;
+            Ok(())
+        }
+        fn set_timeout(&mut self, _timeout: Duration) -> Result<(), CommunicationError> {Ok(())}
+        fn flush(&mut self) {
+            self.response.clear();
+        }
+
+        fn poll_read(&mut self, _cx: &mut Context, data: &mut [u8]) -> Poll<Result<usize, CommunicationError>> {
+            if self.response.is_empty() {
+                return Poll::Ready(Err(CommunicationError::TimedOut));
+            }
+            data[0] = self.response.remove(0);
+            Poll::Ready(Ok(1))
+        }
+
+        fn poll_write(&mut self, _cx: &mut Context, data: &[u8]) -> Poll<Result<usize, CommunicationError>> {
+            if self.baud_rate == BaudRate::Baud1000000 && data[2] == 3 {
+                match data[4] {
+                    0x01 => self.response.extend_from_slice(&[0xff, 0xff, 0x03, 0x02, 0x00, 0xfa]),
+                    0x02 => self.response.extend_from_slice(&[0xff, 0xff, 0x03, 0x05, 0x00, 0x1d, 0x00, 0x24, 0xb6]),
+                    _ => (),
+                }
+            }
+            Poll::Ready(Ok(data.len()))
+        }
+    }
+
+    #[cfg(feature="std")]
+    #[test]
+    fn test_enumeration() {
+        let mut interface = SingleServo{baud_rate: BaudRate::Baud9600, response: Vec::new()};
+        let mut enumeration = Enumeration::new();
+        let servos = ready(::lib::future::poll_fn(|cx| enumeration.poll(&mut interface, cx))).unwrap();
+        assert_eq!(servos.len(), 1);
+        assert_eq!((servos[0].id, servos[0].model_number, servos[0].fw_version), (ServoID::new(3), 0x001d, 0x24));
+        assert_eq!(servos[0].baud_rate, BaudRate::Baud1000000);
+    }
+}
@@ -0,0 +1,212 @@
+//! Async protocol 2 servos
+
+use lib::future::Future;
+use lib::time::Duration;
+#[cfg(feature="std")]
+use lib::mem;
+#[cfg(feature="std")]
+use lib::task::{Context, Poll};
+
+use BaudRate;
+use Reply;
+use StatusReturnLevel;
+#[cfg(feature="std")]
+use CommunicationError;
+use protocol2::{instruction, Error, PacketID, ReadRegister, Response, ServoID, ServoInfo, StatusDecoder, WriteRegister};
+use transaction::Transaction;
+use super::{begin_transaction, AsyncInterface, Map, TransactionFuture};
+
+/// A protocol 2 servo on an `AsyncInterface`
+///
+/// Reads and writes any protocol 2 register, it is up to the user to only use the registers of the servo model.
+#[derive(Debug, Clone)]
+pub struct AsyncServo {
+    id: ServoID,
+    baud_rate: BaudRate,
+    timeout: Duration,
+    status_return_level: StatusReturnLevel,
+    alert: bool,
+}
+
+impl AsyncServo {
+    pub fn new(id: ServoID, baud_rate: BaudRate) -> Self {
+        AsyncServo {
+            id: id,
+            baud_rate: baud_rate,
+            timeout: ::DEFAULT_TIMEOUT,
+            status_return_level: StatusReturnLevel::default(),
+            alert: false,
+        }
+    }
+
+    pub fn id(&self) -> ServoID {
+        self.id
+    }
+
+    pub fn baud_rate(&self) -> BaudRate {
+        self.baud_rate
+    }
+
+    /// Set how long to wait for the status of an instruction
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// The status return level the servo is assumed to be configured with
+    pub fn status_return_level(&self) -> StatusReturnLevel {
+        self.status_return_level
+    }
+
+    /// Assume the servo is configured with `status_return_level`, without writing it
+    ///
+    /// Reads fail with `Error::NotAnswered` right away when the servo only answers pings.
+    pub fn assume_status_return_level(&mut self, status_return_level: StatusReturnLevel) {
+        self.status_return_level = status_return_level;
+    }
+
+    /// The alert flag of the last status received from the servo
+    pub fn alert(&self) -> bool {
+        self.alert
+    }
+
+    /// Ping the servo, resolving to `Ok(ServoInfo)` if it exists.
+    pub fn ping<'a, A: AsyncInterface>(&'a mut self, interface: &'a mut A) -> impl Future<Output=Result<ServoInfo, Error>> + 'a {
+        let ping = instruction::Ping::new(PacketID::from(self.id));
+        let transaction = Transaction::new(::protocol2::packet(&ping), StatusDecoder::<instruction::Pong>::new(), Reply::Always);
+        let begun = begin_transaction(interface, self.baud_rate, self.timeout).map_err(Error::from);
+        let (baud_rate, alert) = (self.baud_rate, &mut self.alert);
+        Map::new(TransactionFuture::new(interface, transaction, begun), move |status: Result<Option<Response<instruction::Pong>>, Error>| {
+            let response = status?.expect("a status is always expected for pings");
+            *alert = response.alert;
+            Ok(ServoInfo{
+                baud_rate: baud_rate,
+                model_number: response.status.model_number,
+                fw_version: response.status.fw_version,
+                id: response.status.id,
+            })
+        })
+    }
+
+    /// Write the given data `register` to the servo.
+    pub fn write<'a, W: WriteRegister, A: AsyncInterface>(&'a mut self, interface: &'a mut A, register: W) -> impl Future<Output=Result<(), Error>> + 'a {
+        let write = instruction::Write::new(PacketID::from(self.id), register);
+        let transaction = Transaction::new(::protocol2::packet(&write), StatusDecoder::<instruction::WriteResponse>::new(), self.status_return_level.write_reply());
+        let begun = begin_transaction(interface, self.baud_rate, self.timeout).map_err(Error::from);
+        let alert = &mut self.alert;
+        Map::new(TransactionFuture::new(interface, transaction, begun), move |status: Result<Option<Response<instruction::WriteResponse>>, Error>| {
+            if let Some(response) = status? {
+                *alert = response.alert;
+            }
+            Ok(())
+        })
+    }
+
+    /// Read data from a register
+    pub fn read<'a, R: ReadRegister + 'a, A: AsyncInterface>(&'a mut self, interface: &'a mut A) -> impl Future<Output=Result<R, Error>> + 'a {
+        let read = instruction::Read::<R>::new(PacketID::from(self.id));
+        let transaction = Transaction::new(::protocol2::packet(&read), StatusDecoder::<instruction::ReadResponse<R>>::new(), Reply::Always);
+        // Fail right away if the servo does not answer reads, rather than waiting for the timeout
+        let begun = if self.status_return_level.replies_to_read() {
+            begin_transaction(interface, self.baud_rate, self.timeout).map_err(Error::from)
+        } else {
+            Err(Error::NotAnswered)
+        };
+        let alert = &mut self.alert;
+        Map::new(TransactionFuture::new(interface, transaction, begun), move |status: Result<Option<Response<instruction::ReadResponse<R>>>, Error>| {
+            let response = status?.expect("a status is always expected for reads");
+            *alert = response.alert;
+            Ok(response.status.value)
+        })
+    }
+}
+
+/// Enumeration of protocol 2 servos, by listening for the pongs to a broadcast ping on every baud rate
+#[cfg(feature="std")]
+pub(crate) struct Enumeration {
+    baud: usize,
+    listening: Option<Transaction<StatusDecoder<instruction::Pong>>>,
+    servos: Vec<ServoInfo>,
+}
+
+#[cfg(feature="std")]
+impl Enumeration {
+    pub fn new() -> Self {
+        Enumeration {
+            baud: 0,
+            listening: None,
+            servos: Vec::new(),
+        }
+    }
+
+    pub fn poll<A: AsyncInterface>(&mut self, interface: &mut A, cx: &mut Context) -> Poll<Result<Vec<ServoInfo>, CommunicationError>> {
+        loop {
+            let b = match BaudRate::variants().get(self.baud) {
+                Some(b) => *b,
+                None => return Poll::Ready(Ok(mem::take(&mut self.servos))),
+            };
+
+            let transaction = match self.listening {
+                Some(ref mut transaction) => transaction,
+                None => {
+                    if interface.set_baud_rate(b).is_err() {
+                        warn!(target: "protocol2", "not able to enumerate devices on baudrate: {}", u32::from(b));
+                    }
+                    if let Err(e) = interface.set_timeout(::DEFAULT_TIMEOUT) {
+                        return Poll::Ready(Err(::communication_error(e)));
+                    }
+                    interface.flush();
+                    let ping = instruction::Ping::new(PacketID::Broadcast);
+                    self.listening.get_or_insert(Transaction::new(::protocol2::packet(&ping), StatusDecoder::new(), Reply::Always))
+                },
+            };
+
+            match transaction.poll(interface, cx) {
+                Poll::Ready(Ok(Some(response))) => {
+                    self.servos.push(
+                        ServoInfo{
+                            baud_rate: b,
+                            model_number: response.status.model_number,
+                            fw_version: response.status.fw_version,
+                            id: response.status.id,
+                        }
+                    );
+                    *transaction = Transaction::receive(StatusDecoder::new());
+                },
+                Poll::Ready(Ok(None)) | Poll::Ready(Err(Error::Communication(CommunicationError::TimedOut))) => {
+                    self.listening = None;
+                    self.baud += 1;
+                },
+                Poll::Ready(Err(e)) => {
+                    warn!(target: "protocol2", "received error: {:?} when waiting for enumeration on baud: {}", e, u32::from(b));
+                    self.listening = None;
+                    self.baud += 1;
+                },
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::{ready, ScriptedInterface};
+
+    #[test]
+    fn test_ping() {
+        let mut interface = ScriptedInterface::new(&[0xff, 0xff, 0xfd, 0x00, 0x01, 0x07, 0x00, 0x55, 0x00, 0x06, 0x04, 0x26, 0x65, 0x5d]);
+        let mut servo = AsyncServo::new(ServoID::new(1), BaudRate::Baud1000000);
+        let info = ready(servo.ping(&mut interface)).unwrap();
+        assert_eq!(&interface.written[..interface.written_len], &[0xff, 0xff, 0xfd, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4e]);
+        assert_eq!(info.model_number, 0x0406);
+        assert_eq!(info.fw_version, 0x26);
+        assert!(!servo.alert());
+
+        let mut interface = ScriptedInterface::new(&[]);
+        assert_eq!(ready(servo.ping(&mut interface)).unwrap_err(), Error::Communication(::CommunicationError::TimedOut));
+    }
+}
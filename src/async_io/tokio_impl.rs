@@ -0,0 +1,141 @@
+//! An `AsyncInterface` over tokio streams
+//!
+//! Requires the `tokio` feature. Works with any `tokio::io::AsyncRead + AsyncWrite` stream, such as serial ports
+//! from `tokio-serial`, TCP bridges and pipes. The read timeout is measured with the tokio timer.
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::Sleep;
+
+use BaudRate;
+use super::{noop_waker, AsyncInterface};
+
+/// An `AsyncInterface` over a tokio stream
+///
+/// Streams have no baud rate, `set_baud_rate` is accepted and ignored, the stream must be configured with the baud rate of the servos.
+/// The interface must be used from within a tokio runtime with the timer enabled.
+pub struct TokioInterface<S> {
+    stream: S,
+    timeout: Duration,
+    deadline: Option<Pin<Box<Sleep>>>,
+    unflushed: Option<usize>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> TokioInterface<S> {
+    pub fn new(stream: S) -> Self {
+        TokioInterface {
+            stream: stream,
+            timeout: ::DEFAULT_TIMEOUT,
+            deadline: None,
+            unflushed: None,
+        }
+    }
+
+    /// Return the stream
+    pub fn release(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncInterface for TokioInterface<S> {
+    type Error = io::Error;
+
+    fn set_baud_rate(&mut self, _b: BaudRate) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), io::Error> {
+        self.timeout = timeout;
+        self.deadline = None;
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut data = [0u8; 64];
+        loop {
+            let mut buf = ReadBuf::new(&mut data);
+            match Pin::new(&mut self.stream).poll_read(&mut cx, &mut buf) {
+                Poll::Ready(Ok(())) if !buf.filled().is_empty() => (),
+                _ => break,
+            }
+        }
+        self.deadline = None;
+    }
+
+    fn poll_read(&mut self, cx: &mut Context, data: &mut [u8]) -> Poll<Result<usize, io::Error>> {
+        let mut buf = ReadBuf::new(data);
+        match Pin::new(&mut self.stream).poll_read(cx, &mut buf) {
+            Poll::Ready(result) => {
+                self.deadline = None;
+                Poll::Ready(result.map(|()| buf.filled().len()))
+            },
+            Poll::Pending => {
+                // The deadline is armed when the stream first runs dry, and reset by every byte read
+                let timeout = self.timeout;
+                let deadline = self.deadline.get_or_insert_with(|| Box::pin(::tokio::time::sleep(timeout)));
+                match deadline.as_mut().poll(cx) {
+                    Poll::Ready(()) => {
+                        self.deadline = None;
+                        Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, "no data received")))
+                    },
+                    Poll::Pending => Poll::Pending,
+                }
+            },
+        }
+    }
+
+    fn poll_write(&mut self, cx: &mut Context, data: &[u8]) -> Poll<Result<usize, io::Error>> {
+        // Written bytes are only reported once they are flushed out of any buffer of the stream
+        if self.unflushed.is_none() {
+            match Pin::new(&mut self.stream).poll_write(cx, data) {
+                Poll::Ready(Ok(n)) => self.unflushed = Some(n),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        match Pin::new(&mut self.stream).poll_flush(cx) {
+            Poll::Ready(Ok(())) => {
+                self.deadline = None;
+                Poll::Ready(Ok(self.unflushed.take().unwrap_or(0)))
+            },
+            Poll::Ready(Err(e)) => {
+                self.unflushed = None;
+                Poll::Ready(Err(e))
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use async_io::protocol2::AsyncServo;
+    use protocol2::ServoID;
+    use CommunicationError;
+
+    #[test]
+    fn test_tokio() {
+        let runtime = ::tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+        let (stream, mut servo_end) = ::tokio::io::duplex(64);
+        let mut interface = TokioInterface::new(stream);
+        let mut servo = AsyncServo::new(ServoID::new(1), BaudRate::Baud1000000);
+
+        // Beginning the transaction flushes the interface, the pong is sent afterwards
+        let ping = servo.ping(&mut interface);
+        runtime.block_on(servo_end.write_all(&[0xff, 0xff, 0xfd, 0x00, 0x01, 0x07, 0x00, 0x55, 0x00, 0x06, 0x04, 0x26, 0x65, 0x5d])).unwrap();
+        assert_eq!(runtime.block_on(ping).unwrap().model_number, 0x0406);
+
+        servo.set_timeout(Duration::from_millis(10));
+        let error = runtime.block_on(servo.ping(&mut interface)).unwrap_err();
+        assert_eq!(error, ::protocol2::Error::Communication(CommunicationError::TimedOut));
+    }
+}
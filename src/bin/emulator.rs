@@ -0,0 +1,226 @@
+//! Emulate a bus of servos on a pseudo-terminal
+//!
+//! Requires the `emulator` feature and Linux. The servos are simulated with `dynamixel::sim` and configured in a file
+//! with one servo per line, giving the model name, the ID and the baud rate:
+//!
+//! ```text
+//! # model       id  baud rate
+//! XM430-W350    1   57600
+//! MX-28         3   1000000
+//! ```
+//!
+//! Run with `cargo run --features emulator --bin dynamixel-emulator -- servos.conf [link]`.
+//! The pseudo-terminal is opened like any serial port, the baud rate set on it by the client selects the servos that listen.
+//! If `link` is given, a symbolic link to the pseudo-terminal is created at that path.
+//! A symbolic link already there is replaced, any other file is left alone and the emulator refuses to start.
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("the emulator uses Linux specific pseudo-terminal calls and only builds on Linux");
+
+extern crate dynamixel;
+extern crate libc;
+
+use std::env;
+use std::ffi::CStr;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::symlink;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::raw::c_char;
+use std::process;
+use std::time::Instant;
+
+use dynamixel::BaudRate;
+use dynamixel::Interface;
+use dynamixel::descriptor::CONTROL_TABLES;
+use dynamixel::sim::{SimulatedBus, VirtualServo};
+
+fn baud_rate(value: u32) -> Option<BaudRate> {
+    BaudRate::variants().iter().cloned().find(|b| u32::from(*b) == value)
+}
+
+/// Parse the servos of a configuration file
+fn parse_config(config: &str) -> Result<Vec<VirtualServo>, String> {
+    let mut servos = Vec::new();
+    for (number, line) in config.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.len() != 3 {
+            return Err(format!("line {}: expected model, ID and baud rate", number + 1));
+        }
+
+        let table = CONTROL_TABLES.iter().find(|t| t.name.eq_ignore_ascii_case(columns[0]))
+            .ok_or_else(|| {
+                let models: Vec<&str> = CONTROL_TABLES.iter().map(|t| t.name).collect();
+                format!("line {}: unknown model {}, known models are {}", number + 1, columns[0], models.join(", "))
+            })?;
+        let id = columns[1].parse::<u8>().ok().filter(|id| *id < 0xfe)
+            .ok_or_else(|| format!("line {}: invalid ID {}", number + 1, columns[1]))?;
+        let baud = columns[2].parse().ok().and_then(baud_rate)
+            .ok_or_else(|| format!("line {}: unsupported baud rate {}", number + 1, columns[2]))?;
+
+        servos.push(VirtualServo::new(table, id, baud));
+    }
+    Ok(servos)
+}
+
+/// Open a pseudo-terminal in raw mode, returning the master side and the path of the slave side
+fn open_pty() -> io::Result<(File, String)> {
+    unsafe {
+        let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let master = File::from_raw_fd(fd);
+        if libc::grantpt(fd) != 0 || libc::unlockpt(fd) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut termios: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(fd, &mut termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        libc::cfmakeraw(&mut termios);
+        if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut name = [0 as c_char; 64];
+        if libc::ptsname_r(fd, name.as_mut_ptr(), name.len()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let path = CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned();
+        Ok((master, path))
+    }
+}
+
+/// The baud rate the client configured the pseudo-terminal with
+fn client_baud_rate(master: &File) -> Option<BaudRate> {
+    unsafe {
+        let mut termios: libc::termios2 = std::mem::zeroed();
+        if libc::ioctl(master.as_raw_fd(), libc::TCGETS2, &mut termios) != 0 {
+            return None;
+        }
+        baud_rate(termios.c_ospeed)
+    }
+}
+
+/// Wait up to `timeout_ms` for the client to write
+fn wait_readable(master: &File, timeout_ms: i32) -> io::Result<bool> {
+    let mut fd = libc::pollfd{fd: master.as_raw_fd(), events: libc::POLLIN, revents: 0};
+    match unsafe { libc::poll(&mut fd, 1, timeout_ms) } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(false),
+        _ => Ok(fd.revents & libc::POLLIN != 0),
+    }
+}
+
+/// Create a symbolic link to `path` at `link`, replacing a symbolic link but no other kind of file
+fn replace_link(path: &str, link: &str) -> Result<(), String> {
+    match fs::symlink_metadata(link) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            fs::remove_file(link).map_err(|e| format!("failed to remove the old link {}: {}", link, e))?;
+        },
+        Ok(_) => return Err(format!("{} exists and is not a symbolic link, refusing to replace it", link)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => (),
+        Err(e) => return Err(format!("failed to inspect {}: {}", link, e)),
+    }
+    symlink(path, link).map_err(|e| format!("failed to link {} to {}: {}", link, path, e))
+}
+
+fn run(config: &str, link: Option<&str>) -> Result<(), String> {
+    let config = fs::read_to_string(config).map_err(|e| format!("failed to read {}: {}", config, e))?;
+    let mut bus = SimulatedBus::new();
+    for servo in parse_config(&config)? {
+        println!("{} with ID {} at baud rate {}", servo.control_table().name, servo.id(), u32::from(servo.baud_rate()));
+        bus.add(servo);
+    }
+
+    let (mut master, path) = open_pty().map_err(|e| format!("failed to open a pseudo-terminal: {}", e))?;
+    // Reading the master fails while no process has the slave open, keep it open between clients
+    let _slave = OpenOptions::new().read(true).write(true).open(&path).map_err(|e| format!("failed to open {}: {}", path, e))?;
+    if let Some(link) = link {
+        replace_link(&path, link)?;
+    }
+    println!("Emulating {} servos on {}", bus.servos().len(), link.unwrap_or(&path));
+
+    let start = Instant::now();
+    let mut data = [0u8; 256];
+    loop {
+        if wait_readable(&master, 1).map_err(|e| e.to_string())? {
+            let n = master.read(&mut data).map_err(|e| e.to_string())?;
+            if let Some(baud_rate) = client_baud_rate(&master) {
+                bus.set_baud_rate(baud_rate).expect("the simulated bus accepts all baud rates");
+            }
+            bus.write(&data[..n]).expect("the simulated bus accepts all writes");
+        }
+
+        // Keep the simulated time in step with the real time
+        let now = start.elapsed();
+        if now > bus.elapsed() {
+            let behind = now - bus.elapsed();
+            bus.advance(behind);
+        }
+
+        let pending = bus.pending();
+        if pending > 0 {
+            let mut status = vec![0u8; pending];
+            bus.read(&mut status).expect("the pending bytes are available");
+            master.write_all(&status).map_err(|e| e.to_string())?;
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("usage: {} <config> [link]", args[0]);
+        process::exit(2);
+    }
+    if let Err(e) = run(&args[1], args.get(2).map(|s| s.as_str())) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let servos = parse_config("# model id baud\nXM430-W350 1 57600\n\nmx-28\t3\t1000000 # joint 3\n").unwrap();
+        assert_eq!(servos.len(), 2);
+        assert_eq!(servos[0].control_table().name, "XM430-W350");
+        assert_eq!(servos[1].id(), 3);
+        assert_eq!(servos[1].baud_rate(), BaudRate::Baud1000000);
+
+        assert!(parse_config("XM430-W350 1").unwrap_err().starts_with("line 1"));
+        assert!(parse_config("\nXL-320 1 57600").unwrap_err().starts_with("line 2: unknown model"));
+        assert!(parse_config("XM430-W350 254 57600").is_err());
+        assert!(parse_config("XM430-W350 1 12345").is_err());
+    }
+
+    #[test]
+    fn test_replace_link() {
+        let dir = env::temp_dir().join(format!("dynamixel-emulator-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let link = dir.join("link");
+        let file = dir.join("file");
+        let link = link.to_str().unwrap();
+        let file = file.to_str().unwrap();
+
+        replace_link("/dev/null", link).unwrap();
+        replace_link("/dev/zero", link).unwrap();
+        assert_eq!(fs::read_link(link).unwrap().to_str(), Some("/dev/zero"));
+
+        fs::write(file, "keep").unwrap();
+        assert!(replace_link("/dev/null", file).is_err());
+        assert_eq!(fs::read_to_string(file).unwrap(), "keep");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
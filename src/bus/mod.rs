@@ -0,0 +1,234 @@
+//! A bus owning the interface
+//!
+//! Servos are handed the interface for every transaction, and set their baud rate on it every time.
+//! `Bus` owns the interface and only switches the baud rate when it changes, which matters for interfaces that are slow to reconfigure
+//! and for buses with servos on several baud rates.
+//!
+//! With the `std` feature, `SharedBus` shares a bus between threads, queueing the transactions by priority.
+//!
+//! `Bus::servo` borrows the bus for a servo, returning a `Handle` with the methods of the servo without the interface argument:
+//!
+//! ```
+//! use dynamixel::BaudRate;
+//! use dynamixel::bus::Bus;
+//! use dynamixel::dynamixel::mx28::{self, MX28};
+//! use dynamixel::pro::{self, M4210S260R};
+//!
+//! fn pan_and_tilt<I: dynamixel::Interface>(interface: I) -> Result<i32, dynamixel::Error> {
+//!     let mut bus = Bus::new(interface);
+//!     let mut pan = MX28::new(dynamixel::protocol1::ServoID::new(1), BaudRate::Baud1000000);
+//!     let mut tilt = M4210S260R::new(dynamixel::protocol2::ServoID::new(2), BaudRate::Baud57600);
+//!
+//!     bus.servo(&mut pan).write_data(mx28::control_table::GoalPosition::new(2048))?;
+//!     let position = bus.servo(&mut tilt).read::<pro::control_table::PresentPosition>()?;
+//!     Ok(i32::from(position))
+//! }
+//! ```
+
+use lib::ops::{Deref, DerefMut};
+use lib::time::Duration;
+
+use BaudRate;
+use Interface;
+use Servo;
+
+#[cfg(feature="std")]
+mod shared;
+
+#[cfg(feature="std")]
+pub use self::shared::{Priority, SharedBus, SharedBusGuard};
+
+/// An `Interface` that remembers its baud rate and timeout, and only passes changes on to the interface it owns
+#[derive(Debug)]
+pub struct Bus<I> {
+    interface: I,
+    baud_rate: Option<BaudRate>,
+    timeout: Option<Duration>,
+}
+
+impl<I: Interface> Bus<I> {
+    pub fn new(interface: I) -> Self {
+        Bus {
+            interface: interface,
+            baud_rate: None,
+            timeout: None,
+        }
+    }
+
+    /// The baud rate the interface is set to, `None` before the first transaction
+    pub fn baud_rate(&self) -> Option<BaudRate> {
+        self.baud_rate
+    }
+
+    pub fn interface(&self) -> &I {
+        &self.interface
+    }
+
+    /// The owned interface
+    ///
+    /// Changing the baud rate or timeout of the interface directly must be followed by `invalidate`.
+    pub fn interface_mut(&mut self) -> &mut I {
+        &mut self.interface
+    }
+
+    /// Forget the baud rate and timeout, they are set on the interface again by the next transaction
+    pub fn invalidate(&mut self) {
+        self.baud_rate = None;
+        self.timeout = None;
+    }
+
+    /// Return the owned interface
+    pub fn release(self) -> I {
+        self.interface
+    }
+
+    /// Borrow the bus for communicating with `servo`
+    pub fn servo<'a, S: ?Sized>(&'a mut self, servo: &'a mut S) -> Handle<'a, I, S> {
+        Handle {
+            bus: self,
+            servo: servo,
+        }
+    }
+}
+
+impl<I: Interface> Interface for Bus<I> {
+    type Error = I::Error;
+
+    fn set_baud_rate(&mut self, b: BaudRate) -> Result<(), I::Error> {
+        if self.baud_rate != Some(b) {
+            // Forget the baud rate until it is known to be set, the interface might be left in between
+            self.baud_rate = None;
+            self.interface.set_baud_rate(b)?;
+            self.baud_rate = Some(b);
+        }
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), I::Error> {
+        if self.timeout != Some(timeout) {
+            self.timeout = None;
+            self.interface.set_timeout(timeout)?;
+            self.timeout = Some(timeout);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        self.interface.flush()
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), I::Error> {
+        self.interface.read(data)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), I::Error> {
+        self.interface.write(data)
+    }
+}
+
+/// A servo borrowing a `Bus`
+///
+/// Has the methods of the servo that communicate, without the interface argument. The other methods are available through `Deref`.
+pub struct Handle<'a, I: 'a, S: 'a + ?Sized> {
+    pub(crate) bus: &'a mut Bus<I>,
+    pub(crate) servo: &'a mut S,
+}
+
+impl<'a, I: Interface, S: Servo<Bus<I>> + ?Sized> Handle<'a, I, S> {
+    /// Enable/Disable torque on the servo.
+    pub fn set_enable_torque(&mut self, enable_torque: bool) -> Result<(), ::Error> {
+        self.servo.set_enable_torque(self.bus, enable_torque)
+    }
+
+    /// Set the goal position, in radians.
+    pub fn set_position(&mut self, value: f32) -> Result<(), ::Error> {
+        self.servo.set_position(self.bus, value)
+    }
+
+    /// Get the current position, in radians.
+    pub fn get_position(&mut self) -> Result<f32, ::Error> {
+        self.servo.get_position(self.bus)
+    }
+}
+
+impl<'a, I, S: ?Sized> Deref for Handle<'a, I, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        self.servo
+    }
+}
+
+impl<'a, I, S: ?Sized> DerefMut for Handle<'a, I, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        self.servo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CommunicationError;
+    use dynamixel::mx28::MX28;
+    use pro::M4210S260R;
+
+    /// Counts the baud rate switches, and answers every read with a protocol 1 status without parameters
+    struct CountingInterface {
+        switches: usize,
+        baud_rate: Option<BaudRate>,
+        status: &'static [u8],
+        pos: usize,
+    }
+
+    impl Interface for CountingInterface {
+        type Error = CommunicationError;
+
+        fn set_baud_rate(&mut self, b: BaudRate) -> Result<(), CommunicationError> {
+            self.switches += 1;
+            self.baud_rate = Some(b);
+            Ok(())
+        }
+
+        fn flush(&mut self) {
+            self.pos = 0;
+        }
+
+        fn read(&mut self, data: &mut [u8]) -> Result<(), CommunicationError> {
+            if self.baud_rate != Some(BaudRate::Baud1000000) || self.pos + data.len() > self.status.len() {
+                return Err(CommunicationError::TimedOut);
+            }
+            data.copy_from_slice(&self.status[self.pos..self.pos + data.len()]);
+            self.pos += data.len();
+            Ok(())
+        }
+
+        fn write(&mut self, _data: &[u8]) -> Result<(), CommunicationError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_baud_rate_caching() {
+        let interface = CountingInterface{switches: 0, baud_rate: None, status: &[0xff, 0xff, 0x01, 0x02, 0x00, 0xfc], pos: 0};
+        let mut bus = Bus::new(interface);
+        let mut mx28 = MX28::new(::protocol1::ServoID::new(1), BaudRate::Baud1000000);
+        let mut pro = M4210S260R::new(::protocol2::ServoID::new(2), BaudRate::Baud57600);
+        pro.set_timeout(Duration::from_millis(10));
+
+        bus.servo(&mut mx28).ping().unwrap();
+        bus.servo(&mut mx28).ping().unwrap();
+        assert_eq!(bus.interface().switches, 1);
+
+        assert_eq!(bus.servo(&mut pro).ping().unwrap_err(), ::protocol2::Error::Communication(CommunicationError::TimedOut));
+        assert_eq!(bus.baud_rate(), Some(BaudRate::Baud57600));
+        bus.servo(&mut mx28).ping().unwrap();
+        assert_eq!(bus.interface().switches, 3);
+
+        // The servo is reachable through the handle
+        assert_eq!(bus.servo(&mut pro).timeout(), Duration::from_millis(10));
+
+        bus.invalidate();
+        bus.servo(&mut mx28).ping().unwrap();
+        assert_eq!(bus.release().switches, 4);
+    }
+}
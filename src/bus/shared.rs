@@ -0,0 +1,234 @@
+//! A bus shared between threads
+//!
+//! Transactions are queued by priority, and run one at a time while holding the bus.
+
+use std::collections::BinaryHeap;
+use std::cmp::Reverse;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use Interface;
+use super::Bus;
+
+/// The order in which queued transactions get the bus
+///
+/// Transactions of the same priority get the bus in the order they were queued.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Priority {
+    /// Monitoring and diagnostics, e.g. reading temperatures
+    Diagnostic,
+
+    /// Everything else, the default
+    Normal,
+
+    /// Control loops, e.g. writing goal positions
+    Control,
+}
+
+impl Default for Priority {
+    fn default() -> Priority {
+        Priority::Normal
+    }
+}
+
+/// The queue of transactions waiting for the bus
+#[derive(Debug, Default)]
+struct Queue {
+    busy: bool,
+    next_ticket: u64,
+    waiting: BinaryHeap<(Priority, Reverse<u64>)>,
+}
+
+struct Shared<I> {
+    queue: Mutex<Queue>,
+    released: Condvar,
+    bus: Mutex<Bus<I>>,
+}
+
+/// A `Bus` shared between threads
+///
+/// Clones share the same bus, and can be sent to other threads. Every clone has its own priority for its transactions.
+/// A transaction holds the bus from the first byte of the instruction to the last byte of the status,
+/// so pings, reads and writes from several threads are never interleaved on the wire.
+pub struct SharedBus<I> {
+    shared: Arc<Shared<I>>,
+    priority: Priority,
+}
+
+impl<I: Interface> SharedBus<I> {
+    /// Share `interface`, with `Priority::Normal` for the transactions of the returned handle
+    pub fn new(interface: I) -> Self {
+        SharedBus {
+            shared: Arc::new(Shared {
+                queue: Mutex::new(Queue::default()),
+                released: Condvar::new(),
+                bus: Mutex::new(Bus::new(interface)),
+            }),
+            priority: Priority::default(),
+        }
+    }
+
+    /// A handle to the same bus, with another priority for its transactions
+    pub fn with_priority(&self, priority: Priority) -> Self {
+        SharedBus {
+            shared: self.shared.clone(),
+            priority: priority,
+        }
+    }
+
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// The number of transactions waiting for the bus
+    pub fn waiting(&self) -> usize {
+        self.queue().waiting.len()
+    }
+
+    /// Wait for the bus and hold it until the returned guard is dropped
+    ///
+    /// The guard dereferences to the `Bus`, pass it to servos or use `Bus::servo` on it.
+    /// Several instructions can be sent while holding the bus, but holding it for long delays the other threads.
+    pub fn lock(&self) -> SharedBusGuard<'_, I> {
+        let mut queue = self.queue();
+        let ticket = queue.next_ticket;
+        queue.next_ticket += 1;
+        queue.waiting.push((self.priority, Reverse(ticket)));
+
+        while queue.busy || queue.waiting.peek().map(|&(_, Reverse(next))| next) != Some(ticket) {
+            queue = self.shared.released.wait(queue).unwrap_or_else(PoisonError::into_inner);
+        }
+        queue.waiting.pop();
+        queue.busy = true;
+        drop(queue);
+
+        SharedBusGuard {
+            shared: &self.shared,
+            // The bus is only locked by the holder of the queue, a poisoned lock only means that a transaction panicked
+            bus: Some(self.shared.bus.lock().unwrap_or_else(PoisonError::into_inner)),
+        }
+    }
+
+    /// Run `f` while holding the bus
+    pub fn transaction<T, F: FnOnce(&mut Bus<I>) -> T>(&self, f: F) -> T {
+        f(&mut self.lock())
+    }
+
+    fn queue(&self) -> MutexGuard<'_, Queue> {
+        self.shared.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<I> Clone for SharedBus<I> {
+    fn clone(&self) -> Self {
+        SharedBus {
+            shared: self.shared.clone(),
+            priority: self.priority,
+        }
+    }
+}
+
+/// Holds a `SharedBus`, releasing it to the next transaction in the queue when dropped
+pub struct SharedBusGuard<'a, I: 'a> {
+    shared: &'a Shared<I>,
+    bus: Option<MutexGuard<'a, Bus<I>>>,
+}
+
+impl<'a, I> Deref for SharedBusGuard<'a, I> {
+    type Target = Bus<I>;
+
+    fn deref(&self) -> &Bus<I> {
+        self.bus.as_ref().expect("the bus is held until dropped")
+    }
+}
+
+impl<'a, I> DerefMut for SharedBusGuard<'a, I> {
+    fn deref_mut(&mut self) -> &mut Bus<I> {
+        self.bus.as_mut().expect("the bus is held until dropped")
+    }
+}
+
+impl<'a, I> Drop for SharedBusGuard<'a, I> {
+    fn drop(&mut self) {
+        // Unlock the bus before the next transaction is woken up to lock it
+        self.bus = None;
+        self.shared.queue.lock().unwrap_or_else(PoisonError::into_inner).busy = false;
+        self.shared.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use BaudRate;
+    use Protocol;
+    use sim::{SimulatedBus, VirtualServo};
+
+    fn simulated_bus() -> SimulatedBus {
+        let mut bus = SimulatedBus::new();
+        for id in 1..4 {
+            bus.add(VirtualServo::from_model(Protocol::Protocol2, 1020, id, BaudRate::Baud57600).unwrap());
+        }
+        bus
+    }
+
+    /// Wait for `n` transactions to queue up
+    fn wait_for(bus: &SharedBus<SimulatedBus>, n: usize) {
+        while bus.waiting() < n {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_priority() {
+        let bus = SharedBus::new(simulated_bus());
+        let (order, received) = mpsc::channel();
+        let guard = bus.lock();
+
+        let mut threads = Vec::new();
+        for (n, priority) in [Priority::Diagnostic, Priority::Normal, Priority::Control, Priority::Diagnostic].iter().enumerate() {
+            let (handle, order) = (bus.with_priority(*priority), order.clone());
+            threads.push(thread::spawn(move || handle.transaction(|_| order.send(n).unwrap())));
+            wait_for(&bus, n + 1);
+        }
+
+        drop(guard);
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(received.try_iter().collect::<Vec<_>>(), vec![2, 1, 0, 3]);
+        assert_eq!(bus.waiting(), 0);
+    }
+
+    #[test]
+    fn test_concurrent_transactions() {
+        let bus = SharedBus::new(simulated_bus());
+        let threads: Vec<_> = (1..4).map(|id| {
+            let bus = bus.clone();
+            thread::spawn(move || {
+                let mut servo = ::pro::M4210S260R::new(::protocol2::ServoID::new(id), BaudRate::Baud57600);
+                for _ in 0..20 {
+                    let info = bus.transaction(|bus| bus.servo(&mut servo).ping()).unwrap();
+                    assert_eq!(info.id, ::protocol2::ServoID::new(id));
+                }
+            })
+        }).collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_panicking_transaction() {
+        let bus = SharedBus::new(simulated_bus());
+        let panicking = bus.clone();
+        assert!(thread::spawn(move || panicking.transaction(|_| panic!("transaction failed"))).join().is_err());
+
+        let mut servo = ::pro::M4210S260R::new(::protocol2::ServoID::new(1), BaudRate::Baud57600);
+        assert!(bus.transaction(|bus| bus.servo(&mut servo).ping()).is_ok());
+    }
+}
@@ -0,0 +1,314 @@
+//! Run a callback at a fixed rate against a bus
+//!
+//! A `ControlLoop` calls the callback once per period, sleeping between the cycles. Every cycle is timed,
+//! and the statistics tell how much of the period is spent in the callback and how late the cycles start.
+//! This is what limits the rate of a loop, or the number of servos and the baud rate it needs.
+//!
+//! The callback is typically a sync read of the state of the servos followed by a sync write of their commands,
+//! see `protocol2::sync_read` and `protocol2::sync_write`.
+//!
+//! ```
+//! use std::ops::ControlFlow;
+//! use dynamixel::BaudRate;
+//! use dynamixel::Interface;
+//! use dynamixel::Protocol;
+//! use dynamixel::control_loop::ControlLoop;
+//! use dynamixel::protocol2::{self, ServoID};
+//! use dynamixel::sim::{SimulatedBus, VirtualServo};
+//!
+//! let mut bus = SimulatedBus::new();
+//! for id in 1..4 {
+//!     bus.add(VirtualServo::from_model(Protocol::Protocol2, 1020, id, BaudRate::Baud1000000).unwrap());
+//! }
+//! bus.set_baud_rate(BaudRate::Baud1000000).unwrap();
+//! let ids = [ServoID::new(1), ServoID::new(2), ServoID::new(3)];
+//!
+//! let mut control_loop = ControlLoop::with_frequency(200.0);
+//! let positions = control_loop.run(&mut bus, |bus, cycle| {
+//!     // Read the present position of all servos
+//!     let mut positions = [0u8; 12];
+//!     if let Err(e) = protocol2::sync_read(bus, 132, &ids, &mut positions) {
+//!         return ControlFlow::Break(Err(e));
+//!     }
+//!
+//!     if cycle.number == 10 {
+//!         ControlFlow::Break(Ok(positions))
+//!     } else {
+//!         ControlFlow::Continue(())
+//!     }
+//! });
+//!
+//! assert!(positions.is_ok());
+//! let statistics = control_loop.statistics();
+//! println!("cycle time {:?} of {:?}, max jitter {:?}", statistics.cycle_time.max(), control_loop.period(), statistics.jitter.max());
+//! ```
+
+use std::ops::ControlFlow;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use Interface;
+
+/// The timing of a cycle, given to the callback
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Cycle {
+    /// The number of the cycle, counting from zero when the loop started
+    ///
+    /// Cycles skipped after an overrun are counted as well, so the number always tells the scheduled time of the cycle.
+    pub number: u64,
+
+    /// When the cycle was scheduled to start
+    pub scheduled: Instant,
+
+    /// How late the cycle started
+    pub jitter: Duration,
+}
+
+impl Cycle {
+    /// When the cycle must be finished, this is when the next cycle is scheduled to start
+    pub fn deadline(&self, period: Duration) -> Instant {
+        self.scheduled + period
+    }
+}
+
+/// The minimum, maximum and mean of a duration measured every cycle
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Summary {
+    samples: u64,
+    min: Duration,
+    max: Duration,
+    total: Duration,
+}
+
+impl Summary {
+    fn add(&mut self, sample: Duration) {
+        if self.samples == 0 || sample < self.min {
+            self.min = sample;
+        }
+        if sample > self.max {
+            self.max = sample;
+        }
+        self.samples += 1;
+        self.total += sample;
+    }
+
+    /// The number of measured cycles
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// The shortest measurement, zero if no cycles were measured
+    pub fn min(&self) -> Duration {
+        self.min
+    }
+
+    /// The longest measurement, zero if no cycles were measured
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// The mean of the measurements, zero if no cycles were measured
+    pub fn mean(&self) -> Duration {
+        match self.samples {
+            0 => Duration::from_secs(0),
+            n => Duration::from_nanos((self.total.as_nanos() / u128::from(n)) as u64),
+        }
+    }
+}
+
+/// Timing statistics of the cycles run by a `ControlLoop`
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Statistics {
+    /// The number of cycles the callback was run for
+    pub cycles: u64,
+
+    /// Cycles that were not finished before the next cycle was scheduled to start
+    pub missed_deadlines: u64,
+
+    /// Cycles that were not run at all, because an earlier cycle overran past their whole period
+    pub skipped_cycles: u64,
+
+    /// The time spent in the callback
+    ///
+    /// This includes any work the callback does besides communicating, so it is an upper bound for the time spent on the bus.
+    pub cycle_time: Summary,
+
+    /// How late the cycles started
+    pub jitter: Summary,
+}
+
+/// Runs a callback at a fixed rate and collects timing statistics
+#[derive(Debug, Clone)]
+pub struct ControlLoop {
+    period: Duration,
+    statistics: Statistics,
+}
+
+impl ControlLoop {
+    /// A loop running one cycle every `period`
+    ///
+    /// # Panics
+    /// Panics if `period` is zero.
+    pub fn new(period: Duration) -> Self {
+        assert!(period > Duration::from_secs(0));
+        ControlLoop {
+            period: period,
+            statistics: Statistics::default(),
+        }
+    }
+
+    /// A loop running `frequency` cycles per second
+    ///
+    /// # Panics
+    /// Panics if `frequency` is not positive, or so high that the period rounds to zero nanoseconds.
+    pub fn with_frequency(frequency: f64) -> Self {
+        assert!(frequency > 0.0);
+        Self::new(Duration::from_nanos((1e9 / frequency).round() as u64))
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// The statistics of all cycles run since the loop was created, or since the statistics were reset
+    pub fn statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    pub fn reset_statistics(&mut self) {
+        self.statistics = Statistics::default();
+    }
+
+    /// The mean fraction of the period spent in the callback
+    ///
+    /// Close to, or above, one means that the bus is too slow for the rate, the number of servos or the registers transferred.
+    pub fn utilization(&self) -> f64 {
+        self.statistics.cycle_time.mean().as_secs_f64() / self.period.as_secs_f64()
+    }
+
+    /// Run `f` once every period until it breaks, returning the value it breaks with
+    ///
+    /// The first cycle starts immediately. A cycle that overruns its deadline is counted as a missed deadline,
+    /// the next cycle then starts as soon as possible, but skips the cycles that were scheduled to both start and finish during the overrun.
+    /// This keeps the cycles in phase with the start of the loop.
+    pub fn run<I, B, F>(&mut self, interface: &mut I, mut f: F) -> B
+        where I: Interface, F: FnMut(&mut I, &Cycle) -> ControlFlow<B> {
+        let mut scheduled = Instant::now();
+        let mut number = 0;
+        loop {
+            let now = Instant::now();
+            if now < scheduled {
+                thread::sleep(scheduled - now);
+            }
+
+            let started = Instant::now();
+            let cycle = Cycle {
+                number: number,
+                scheduled: scheduled,
+                jitter: started.saturating_duration_since(scheduled),
+            };
+            let flow = f(interface, &cycle);
+            let finished = Instant::now();
+
+            self.statistics.cycles += 1;
+            self.statistics.cycle_time.add(finished - started);
+            self.statistics.jitter.add(cycle.jitter);
+            if finished > cycle.deadline(self.period) {
+                self.statistics.missed_deadlines += 1;
+            }
+
+            if let ControlFlow::Break(value) = flow {
+                return value;
+            }
+
+            number += 1;
+            scheduled += self.period;
+            while scheduled + self.period <= finished {
+                self.statistics.skipped_cycles += 1;
+                number += 1;
+                scheduled += self.period;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use BaudRate;
+    use Protocol;
+    use protocol2::{self, ServoID};
+    use sim::{SimulatedBus, VirtualServo};
+
+    #[test]
+    fn test_with_frequency() {
+        assert_eq!(ControlLoop::with_frequency(500.0).period(), Duration::from_millis(2));
+        assert_eq!(ControlLoop::with_frequency(3.0).period(), Duration::from_nanos(333_333_333));
+    }
+
+    #[test]
+    fn test_sync_loop() {
+        let mut bus = SimulatedBus::new();
+        for id in 1..4 {
+            bus.add(VirtualServo::from_model(Protocol::Protocol2, 1020, id, BaudRate::Baud1000000).unwrap());
+        }
+        bus.set_baud_rate(BaudRate::Baud1000000).unwrap();
+        let ids = [ServoID::new(1), ServoID::new(2), ServoID::new(3)];
+
+        let mut control_loop = ControlLoop::with_frequency(500.0);
+        let result = control_loop.run(&mut bus, |bus, cycle| {
+            let goals: Vec<[u8; 4]> = (0..3).map(|n| (1000 + 100 * n + cycle.number as u32).to_le_bytes()).collect();
+            let commands: Vec<(ServoID, &[u8])> = ids.iter().cloned().zip(goals.iter().map(|goal| &goal[..])).collect();
+            if let Err(e) = protocol2::sync_write(bus, 116, &commands) {
+                return ControlFlow::Break(Err(e));
+            }
+
+            let mut goals = [0u8; 12];
+            match protocol2::sync_read(bus, 116, &ids, &mut goals) {
+                Ok(_) if cycle.number >= 19 => ControlFlow::Break(Ok((cycle.number, goals))),
+                Ok(_) => ControlFlow::Continue(()),
+                Err(e) => ControlFlow::Break(Err(e)),
+            }
+        });
+
+        let (number, goals) = result.unwrap();
+        for (n, goal) in goals.chunks(4).enumerate() {
+            let expected = 1000 + 100 * n as u32 + number as u32;
+            assert_eq!(goal, &expected.to_le_bytes());
+            assert_eq!(bus.servo(Protocol::Protocol2, n as u8 + 1).unwrap().register("Goal Position"), Some(i64::from(expected)));
+        }
+
+        let statistics = control_loop.statistics();
+        assert_eq!(statistics.cycles + statistics.skipped_cycles, number + 1);
+        assert_eq!(statistics.cycle_time.samples(), statistics.cycles);
+        assert!(statistics.cycle_time.min() <= statistics.cycle_time.mean());
+        assert!(statistics.cycle_time.mean() <= statistics.cycle_time.max());
+        assert!(control_loop.utilization() > 0.0);
+
+        control_loop.reset_statistics();
+        assert_eq!(control_loop.statistics(), &Statistics::default());
+    }
+
+    #[test]
+    fn test_missed_deadlines() {
+        let mut bus = SimulatedBus::new();
+        let mut control_loop = ControlLoop::new(Duration::from_millis(2));
+        let mut cycles = Vec::new();
+        control_loop.run(&mut bus, |_, cycle| {
+            cycles.push(*cycle);
+            if cycles.len() == 1 {
+                thread::sleep(Duration::from_millis(5));
+            }
+            if cycles.len() == 3 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+        });
+
+        let statistics = control_loop.statistics();
+        assert_eq!(statistics.cycles, 3);
+        assert!(statistics.missed_deadlines >= 1);
+        assert!(statistics.skipped_cycles >= 1);
+        assert!(cycles[1].number >= 2);
+        assert_eq!(cycles[1].scheduled, cycles[0].scheduled + Duration::from_millis(2) * cycles[1].number as u32);
+        assert!(cycles[1].jitter > Duration::from_secs(0));
+        assert!(statistics.jitter.max() >= cycles[1].jitter);
+    }
+}
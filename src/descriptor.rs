@@ -0,0 +1,307 @@
+//! Runtime descriptions of servo control tables
+//!
+//! Every model in `models/` has a static `ControlTable` generated from the same macros as its typed registers.
+//! It can be used to list, validate and pretty print registers without knowing the register types at compile time.
+
+use lib::fmt;
+
+use Protocol;
+
+macro_rules! descriptor_access {
+    (r) => {::descriptor::Access::Read};
+    (rw) => {::descriptor::Access::ReadWrite};
+}
+
+macro_rules! descriptor_option {
+    () => {None};
+    ($value:expr) => {Some($value)};
+}
+
+macro_rules! descriptor_signed {
+    (i8) => {true};
+    (i16) => {true};
+    (i32) => {true};
+    ($type:ident) => {false};
+}
+
+macro_rules! descriptor_position {
+    () => {None};
+    ($min_value:expr, $zero_value:expr, $max_value:expr, $min_radian:expr, $max_radian:expr) => {
+        Some(::descriptor::PositionConversion {
+            value_of_min_radian_position: $min_value,
+            value_of_zero_radian_position: $zero_value,
+            value_of_max_radian_position: $max_value,
+            min_radian: $min_radian,
+            max_radian: $max_radian,
+        })
+    };
+}
+
+/// Whether a register is read only or can also be written
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Access {
+    Read,
+    ReadWrite,
+}
+
+/// The memory area a register is located in
+///
+/// EEPROM registers are persistent and can usually only be written while torque is disabled.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Area {
+    Eeprom,
+    Ram,
+}
+
+/// Description of a single register in a control table
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RegisterDescriptor {
+    /// Human readable name, as used in the Robotis documentation
+    pub name: &'static str,
+    pub address: u16,
+    /// Size in bytes
+    pub size: u16,
+    /// The raw value is a two's complement signed integer
+    pub signed: bool,
+    pub access: Access,
+    pub area: Area,
+    /// Smallest valid value, if documented
+    pub min: Option<i64>,
+    /// Largest valid value, if documented
+    pub max: Option<i64>,
+    /// Unit of one step of the raw value, if any
+    pub unit: Option<&'static str>,
+}
+
+impl RegisterDescriptor {
+    /// Returns `true` if the register can be written.
+    pub fn is_writable(&self) -> bool {
+        self.access == Access::ReadWrite
+    }
+
+    /// Returns `true` if `value` is inside the documented range of the register.
+    ///
+    /// Registers without a documented range accepts all values.
+    pub fn is_valid(&self, value: i64) -> bool {
+        let above_min = match self.min {
+            Some(min) => value >= min,
+            None => true,
+        };
+        let below_max = match self.max {
+            Some(max) => value <= max,
+            None => true,
+        };
+        above_min && below_max
+    }
+
+    /// Decode a little endian raw value of `size` bytes
+    pub fn decode(&self, data: &[u8]) -> i64 {
+        assert_eq!(data.len(), self.size as usize);
+        let mut value = 0u64;
+        for (i, b) in data.iter().enumerate() {
+            value |= u64::from(*b) << (8*i);
+        }
+        if self.signed {
+            let shift = 64 - 8*u32::from(self.size);
+            ((value << shift) as i64) >> shift
+        } else {
+            value as i64
+        }
+    }
+
+    /// Encode `value` as a little endian raw value of `size` bytes
+    pub fn encode(&self, value: i64, data: &mut [u8]) {
+        assert_eq!(data.len(), self.size as usize);
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = (value >> (8*i)) as u8;
+        }
+    }
+}
+
+/// Conversion between the raw value of the position registers and radians
+///
+/// Follows the `[type info]` section of the Robotis Dynamixel Wizard model files.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PositionConversion {
+    pub value_of_min_radian_position: i64,
+    pub value_of_zero_radian_position: i64,
+    pub value_of_max_radian_position: i64,
+    pub min_radian: f32,
+    pub max_radian: f32,
+}
+
+impl PositionConversion {
+    /// Convert a raw position value to radians
+    pub fn to_radians(&self, value: i64) -> f32 {
+        let offset = (value - self.value_of_zero_radian_position) as f32;
+        if offset >= 0.0 {
+            offset * self.max_radian / (self.value_of_max_radian_position - self.value_of_zero_radian_position) as f32
+        } else {
+            offset * self.min_radian / (self.value_of_min_radian_position - self.value_of_zero_radian_position) as f32
+        }
+    }
+
+    /// Convert radians to a raw position value, saturating at the min and max position
+    pub fn from_radians(&self, radians: f32) -> i64 {
+        let offset = if radians >= 0.0 {
+            radians * (self.value_of_max_radian_position - self.value_of_zero_radian_position) as f32 / self.max_radian
+        } else {
+            radians * (self.value_of_min_radian_position - self.value_of_zero_radian_position) as f32 / self.min_radian
+        };
+        let value = self.value_of_zero_radian_position + offset as i64;
+        if value < self.value_of_min_radian_position {
+            self.value_of_min_radian_position
+        } else if value > self.value_of_max_radian_position {
+            self.value_of_max_radian_position
+        } else {
+            value
+        }
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Access::Read => write!(f, "R"),
+            Access::ReadWrite => write!(f, "RW"),
+        }
+    }
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Area::Eeprom => write!(f, "EEPROM"),
+            Area::Ram => write!(f, "RAM"),
+        }
+    }
+}
+
+impl fmt::Display for RegisterDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (address: {}, size: {}, {}, {})", self.name, self.address, self.size, self.access, self.area)?;
+        if self.min.is_some() || self.max.is_some() {
+            write!(f, " [")?;
+            if let Some(min) = self.min {write!(f, "{}", min)?;}
+            write!(f, ", ")?;
+            if let Some(max) = self.max {write!(f, "{}", max)?;}
+            write!(f, "]")?;
+        }
+        if let Some(unit) = self.unit {
+            write!(f, " {}", unit)?;
+        }
+        Ok(())
+    }
+}
+
+/// The control table of a servo model
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ControlTable {
+    /// Model name, e.g. "MX-28"
+    pub name: &'static str,
+    pub model_number: u16,
+    pub protocol: Protocol,
+    /// Conversion of "Goal Position" and "Present Position" to radians, if known
+    pub position: Option<PositionConversion>,
+    /// The registers, ordered by address
+    pub registers: &'static [RegisterDescriptor],
+}
+
+impl ControlTable {
+    /// Find a register by its human readable name, e.g. "Present Position"
+    pub fn register(&self, name: &str) -> Option<&'static RegisterDescriptor> {
+        self.registers.iter().find(|r| r.name == name)
+    }
+
+    /// Find the register starting at `address`
+    pub fn register_at(&self, address: u16) -> Option<&'static RegisterDescriptor> {
+        self.registers.iter().find(|r| r.address == address)
+    }
+}
+
+pub use models::CONTROL_TABLES;
+
+/// Find the control table for the given protocol and model number
+pub fn find(protocol: Protocol, model_number: u16) -> Option<&'static ControlTable> {
+    CONTROL_TABLES.iter()
+        .find(|t| t.protocol == protocol && t.model_number == model_number)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use descriptor::*;
+    use lib::f32::consts::PI;
+
+    #[test]
+    fn test_find() {
+        let table = find(Protocol::Protocol1, 0x001D).unwrap();
+        assert_eq!(table.name, "MX-28");
+        assert_eq!(find(Protocol::Protocol2, 0x001D), None);
+        assert_eq!(find(Protocol::Protocol2, 0xA918).unwrap().name, "M42-10-S260-R");
+    }
+
+    #[test]
+    fn test_register() {
+        let table = find(Protocol::Protocol1, 0x001D).unwrap();
+        let goal_position = table.register("Goal Position").unwrap();
+        assert_eq!(goal_position.address, 30);
+        assert_eq!(goal_position.size, 2);
+        assert_eq!(goal_position.access, Access::ReadWrite);
+        assert_eq!(goal_position.area, Area::Ram);
+        assert!(goal_position.is_valid(4095));
+        assert!(!goal_position.is_valid(4096));
+        assert_eq!(table.register_at(36).unwrap().name, "Present Position");
+        assert!(!table.register_at(36).unwrap().is_writable());
+    }
+
+    #[test]
+    fn test_generated_models() {
+        let table = find(Protocol::Protocol2, 1020).unwrap();
+        assert_eq!(table.name, "XM430-W350");
+        assert_eq!(table.register("Goal Position").unwrap().address, 116);
+        assert_eq!(table.register("Hardware Error Status").unwrap().access, Access::Read);
+        assert_eq!(find(Protocol::Protocol1, 12).unwrap().name, "AX-12A");
+
+        assert_eq!(<::models::xm430w350::GoalPosition as ::protocol2::Register>::ADDRESS, 116);
+        assert_eq!(<::models::ax12a::CwAngleLimit as ::protocol1::Register>::ADDRESS, 6);
+    }
+
+    #[test]
+    fn test_decode_encode() {
+        let table = find(Protocol::Protocol2, 1020).unwrap();
+        let goal_position = table.register("Goal Position").unwrap();
+        assert!(goal_position.signed);
+        assert_eq!(goal_position.decode(&[0xff, 0xff, 0xff, 0xff]), -1);
+        let mut data = [0u8; 4];
+        goal_position.encode(-2, &mut data);
+        assert_eq!(data, [0xfe, 0xff, 0xff, 0xff]);
+
+        let present_voltage = table.register("Present Input Voltage").unwrap();
+        assert!(!present_voltage.signed);
+        assert_eq!(present_voltage.decode(&[0xff, 0xff]), 0xffff);
+    }
+
+    #[test]
+    fn test_position_conversion() {
+        let position = find(Protocol::Protocol1, 0x001D).unwrap().position.unwrap();
+        assert_eq!(position.from_radians(0.0), 2048);
+        assert_eq!(position.from_radians(10.0), 4095);
+        assert_eq!(position.from_radians(-10.0), 0);
+        assert!((position.to_radians(4095) - PI).abs() < 0.001);
+        assert!((position.to_radians(0) + PI).abs() < 0.001);
+
+        let position = find(Protocol::Protocol2, 0xA918).unwrap().position.unwrap();
+        assert!((position.to_radians(position.from_radians(1.0)) - 1.0).abs() < 0.001);
+        assert!((position.to_radians(position.from_radians(-1.0)) + 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_registers_ordered() {
+        for table in CONTROL_TABLES {
+            for pair in table.registers.windows(2) {
+                assert!(pair[0].address + pair[0].size <= pair[1].address, "{}: {} overlaps {}", table.name, pair[0].name, pair[1].name);
+            }
+        }
+    }
+}
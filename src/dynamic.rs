@@ -0,0 +1,355 @@
+//! Servos driven by runtime control table descriptors
+//!
+//! A `DynamicServo` works with every model that has a `ControlTable` in `descriptor::CONTROL_TABLES`,
+//! even when no typed servo exists for it. Registers are accessed by their name in the control table.
+
+use lib::marker::PhantomData;
+use lib::time::Duration;
+
+use descriptor::{self, ControlTable, RegisterDescriptor};
+use ErrorKind;
+use Instruction;
+use Interface;
+use InterfaceExt;
+use Protocol;
+use Reply;
+use retry::{RetryPolicy, RetryStats};
+use Servo;
+use ServoInfo;
+use StatusReturnLevel;
+
+/// The largest register size in any control table
+const MAX_REGISTER_SIZE: usize = 4;
+
+/// A servo of any model with a known control table
+pub struct DynamicServo<I: Interface> {
+    info: ServoInfo,
+    control_table: &'static ControlTable,
+    alert: bool,
+    timeout: Duration,
+    status_return_level: StatusReturnLevel,
+    retry_policy: RetryPolicy,
+    retry_stats: RetryStats,
+    interface: PhantomData<I>,
+}
+
+impl<I: Interface> DynamicServo<I> {
+    /// Create a new servo from the information returned by `ping` or `enumerate`.
+    ///
+    /// Returns an `ErrorKind::UnknownModel` error if there is no control table for the model number.
+    pub fn new(info: ServoInfo) -> Result<Self, ::Error> {
+        let (protocol, model_number, id) = match info {
+            ServoInfo::Protocol1(ref si) => (Protocol::Protocol1, si.model_number, u8::from(si.id)),
+            ServoInfo::Protocol2(ref si) => (Protocol::Protocol2, si.model_number, u8::from(si.id)),
+        };
+        match descriptor::find(protocol, model_number) {
+            Some(control_table) => Ok(DynamicServo {
+                info: info,
+                control_table: control_table,
+                alert: false,
+                timeout: ::DEFAULT_TIMEOUT,
+                status_return_level: StatusReturnLevel::default(),
+                retry_policy: RetryPolicy::default(),
+                retry_stats: RetryStats::default(),
+                interface: PhantomData{},
+            }),
+            None => Err(::Error::new(ErrorKind::UnknownModel(model_number)).with_id(id)),
+        }
+    }
+
+    /// The ID of the servo
+    pub fn id(&self) -> u8 {
+        match self.info {
+            ServoInfo::Protocol1(ref si) => u8::from(si.id),
+            ServoInfo::Protocol2(ref si) => u8::from(si.id),
+        }
+    }
+
+    /// An error with the ID of this servo
+    fn error(&self, kind: ErrorKind) -> ::Error {
+        ::Error::new(kind).with_id(self.id())
+    }
+
+    /// The control table of the servo model
+    pub fn control_table(&self) -> &'static ControlTable {
+        self.control_table
+    }
+
+    /// Set the time to wait for a status from the servo.
+    ///
+    /// Defaults to `DEFAULT_TIMEOUT`. Use short timeouts for fast polling and longer ones for e.g. EEPROM writes.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// The status return level the servo is assumed to have
+    pub fn status_return_level(&self) -> StatusReturnLevel {
+        self.status_return_level
+    }
+
+    /// Configure the status return level the servo has, without communicating with it.
+    ///
+    /// Writes only wait for a status when the level is `All`.
+    /// Reads fail with `ErrorKind::NotAnswered` right away when the servo only answers pings, except `read_status_return_level`.
+    pub fn assume_status_return_level(&mut self, status_return_level: StatusReturnLevel) {
+        self.status_return_level = status_return_level;
+    }
+
+    /// Read the status return level from the servo and use it from now on.
+    pub fn read_status_return_level(&mut self, interface: &mut I) -> Result<StatusReturnLevel, ::Error> {
+        let register = self.control_table.register("Status Return Level").ok_or_else(|| self.error(ErrorKind::UnknownRegister))?;
+        let mut data = [0u8];
+        // Sent whatever level is assumed, the assumption may be wrong
+        self.read_raw_unchecked(interface, register.address, &mut data)?;
+        self.status_return_level = StatusReturnLevel::from(data[0]);
+        Ok(self.status_return_level)
+    }
+
+    /// Change the status return level of the servo.
+    ///
+    /// A missing status for the write is accepted when either the old or the new level does not answer writes.
+    /// If the new level answers reads, the level is read back from the servo.
+    pub fn set_status_return_level(&mut self, interface: &mut I, status_return_level: StatusReturnLevel) -> Result<(), ::Error> {
+        let register = self.control_table.register("Status Return Level").ok_or_else(|| self.error(ErrorKind::UnknownRegister))?;
+        let reply = self.status_return_level.change_reply(status_return_level);
+        self.write_raw_with_reply(interface, register.address, &[u8::from(status_return_level)], reply)?;
+        self.status_return_level = status_return_level;
+        if status_return_level.replies_to_read() {
+            self.read_status_return_level(interface)?;
+        }
+        Ok(())
+    }
+
+    /// Set the policy used for retrying failed transactions with this servo.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    /// Retry counters for the transactions with this servo
+    pub fn retry_stats(&self) -> RetryStats {
+        self.retry_stats
+    }
+
+    pub fn reset_retry_stats(&mut self) {
+        self.retry_stats = RetryStats::default();
+    }
+
+    /// The alert flag of the last status received from the servo
+    ///
+    /// Only protocol 2 servos have an alert flag, it is always `false` for protocol 1 servos.
+    /// Writes are not answered below `StatusReturnLevel::All`, and leave the flag as it was.
+    pub fn alert(&self) -> bool {
+        self.alert
+    }
+
+    /// Read the Hardware Error Status register, explaining why the alert flag is set.
+    ///
+    /// Returns an `ErrorKind::UnknownRegister` error if the model has no Hardware Error Status register.
+    pub fn hardware_error(&mut self, interface: &mut I) -> Result<::protocol2::HardwareError, ::Error> {
+        let register = self.control_table.register("Hardware Error Status").ok_or_else(|| self.error(ErrorKind::UnknownRegister))?;
+        // The error bits are all in the first byte
+        let mut data = [0u8];
+        self.read_raw(interface, register.address, &mut data)?;
+        Ok(::protocol2::HardwareError::from(data[0]))
+    }
+
+    /// Read `data.len()` bytes starting at `address`
+    ///
+    /// Returns an `ErrorKind::UnknownRegister` error if `address` does not fit in the address space of the protocol.
+    pub fn read_raw(&mut self, interface: &mut I, address: u16, data: &mut [u8]) -> Result<(), ::Error> {
+        if !self.status_return_level.replies_to_read() {
+            return Err(self.error(ErrorKind::NotAnswered).with_instruction(Instruction::Read));
+        }
+        self.read_raw_unchecked(interface, address, data)
+    }
+
+    fn read_raw_unchecked(&mut self, interface: &mut I, address: u16, data: &mut [u8]) -> Result<(), ::Error> {
+        let timeout = self.timeout;
+        match self.info {
+            ServoInfo::Protocol1(ref si) => {
+                // Protocol 1 addresses are 8 bit
+                if address > 0xff {
+                    return Err(::Error::new(ErrorKind::UnknownRegister).with_id(u8::from(si.id)).with_instruction(Instruction::Read));
+                }
+                self.retry_policy.run(&mut self.retry_stats, || {
+                    interface.begin_transaction(si.baud_rate, timeout)?;
+                    ::protocol1::read_raw(interface, si.id, address as u8, &mut *data)
+                }).map_err(|e| ::Error::from(e).with_id(u8::from(si.id)).with_instruction(Instruction::Read))
+            },
+            ServoInfo::Protocol2(ref si) => {
+                self.alert = self.retry_policy.run(&mut self.retry_stats, || {
+                    interface.begin_transaction(si.baud_rate, timeout)?;
+                    ::protocol2::read_raw(interface, si.id, address, &mut *data)
+                }).map_err(|e| ::Error::from(e).with_id(u8::from(si.id)).with_instruction(Instruction::Read))?;
+                Ok(())
+            },
+        }
+    }
+
+    /// Write `data` starting at `address`
+    ///
+    /// Returns an `ErrorKind::UnknownRegister` error if `address` does not fit in the address space of the protocol.
+    pub fn write_raw(&mut self, interface: &mut I, address: u16, data: &[u8]) -> Result<(), ::Error> {
+        let reply = self.status_return_level.write_reply();
+        self.write_raw_with_reply(interface, address, data, reply)
+    }
+
+    fn write_raw_with_reply(&mut self, interface: &mut I, address: u16, data: &[u8], reply: Reply) -> Result<(), ::Error> {
+        let timeout = self.timeout;
+        match self.info {
+            ServoInfo::Protocol1(ref si) => {
+                // Protocol 1 addresses are 8 bit
+                if address > 0xff {
+                    return Err(::Error::new(ErrorKind::UnknownRegister).with_id(u8::from(si.id)).with_instruction(Instruction::Write));
+                }
+                self.retry_policy.run(&mut self.retry_stats, || {
+                    interface.begin_transaction(si.baud_rate, timeout)?;
+                    ::protocol1::write_raw_with_reply(interface, si.id, address as u8, data, reply)
+                }).map_err(|e| ::Error::from(e).with_id(u8::from(si.id)).with_instruction(Instruction::Write))
+            },
+            ServoInfo::Protocol2(ref si) => {
+                let alert = self.retry_policy.run(&mut self.retry_stats, || {
+                    interface.begin_transaction(si.baud_rate, timeout)?;
+                    ::protocol2::write_raw_with_reply(interface, si.id, address, data, reply)
+                }).map_err(|e| ::Error::from(e).with_id(u8::from(si.id)).with_instruction(Instruction::Write))?;
+                self.alert = alert.unwrap_or(self.alert);
+                Ok(())
+            },
+        }
+    }
+
+    /// Read the value of a register
+    pub fn read_register(&mut self, interface: &mut I, register: &RegisterDescriptor) -> Result<i64, ::Error> {
+        let mut data = [0u8; MAX_REGISTER_SIZE];
+        let data = &mut data[..register.size as usize];
+        self.read_raw(interface, register.address, data)?;
+        Ok(register.decode(data))
+    }
+
+    /// Write the value of a register
+    ///
+    /// The value is checked against the access and documented range of the register before anything is sent.
+    pub fn write_register(&mut self, interface: &mut I, register: &RegisterDescriptor, value: i64) -> Result<(), ::Error> {
+        if !register.is_writable() {
+            return Err(self.error(ErrorKind::ReadOnlyRegister));
+        }
+        if !register.is_valid(value) {
+            return Err(self.error(ErrorKind::OutOfRange));
+        }
+        let mut data = [0u8; MAX_REGISTER_SIZE];
+        let data = &mut data[..register.size as usize];
+        register.encode(value, data);
+        self.write_raw(interface, register.address, data)
+    }
+
+    /// Read the value of a register by its name, e.g. "Present Temperature"
+    pub fn read_named(&mut self, interface: &mut I, name: &str) -> Result<i64, ::Error> {
+        let register = self.control_table.register(name).ok_or_else(|| self.error(ErrorKind::UnknownRegister))?;
+        self.read_register(interface, register)
+    }
+
+    /// Write the value of a register by its name, e.g. "Goal Position"
+    pub fn write_named(&mut self, interface: &mut I, name: &str, value: i64) -> Result<(), ::Error> {
+        let register = self.control_table.register(name).ok_or_else(|| self.error(ErrorKind::UnknownRegister))?;
+        self.write_register(interface, register, value)
+    }
+}
+
+impl<I: Interface> Servo<I> for DynamicServo<I> {
+    fn set_enable_torque(&mut self, interface: &mut I, enable_torque: bool) -> Result<(), ::Error> {
+        self.write_named(interface, "Torque Enable", enable_torque as i64)
+    }
+
+    /// Returns an `ErrorKind::UnknownRegister` error if the control table has no radian conversion.
+    fn set_position(&mut self, interface: &mut I, value: f32) -> Result<(), ::Error> {
+        let position = self.control_table.position.ok_or_else(|| self.error(ErrorKind::UnknownRegister))?;
+        self.write_named(interface, "Goal Position", position.from_radians(value))
+    }
+
+    /// Returns an `ErrorKind::UnknownRegister` error if the control table has no radian conversion.
+    fn get_position(&mut self, interface: &mut I) -> Result<f32, ::Error> {
+        let position = self.control_table.position.ok_or_else(|| self.error(ErrorKind::UnknownRegister))?;
+        Ok(position.to_radians(self.read_named(interface, "Present Position")?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoInterface;
+
+    impl Interface for NoInterface {
+        type Error = ::CommunicationError;
+
+        fn set_baud_rate(&mut self, _b: ::BaudRate) -> Result<(), ::CommunicationError> {Ok(())}
+        fn flush(&mut self) {}
+        fn read(&mut self, _data: &mut [u8]) -> Result<(), ::CommunicationError> {Err(::CommunicationError::Other)}
+        fn write(&mut self, _data: &[u8]) -> Result<(), ::CommunicationError> {Err(::CommunicationError::Other)}
+    }
+
+    fn info(model_number: u16) -> ServoInfo {
+        ServoInfo::Protocol2(::protocol2::ServoInfo {
+            baud_rate: ::BaudRate::Baud57600,
+            model_number: model_number,
+            fw_version: 0,
+            id: ::protocol2::ServoID::new(1),
+        })
+    }
+
+    #[test]
+    fn test_new() {
+        let servo = DynamicServo::<NoInterface>::new(info(1020)).unwrap();
+        assert_eq!(servo.control_table().name, "XM430-W350");
+        let error = DynamicServo::<NoInterface>::new(info(0xffff)).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::UnknownModel(0xffff));
+        assert_eq!(error.id(), Some(1));
+    }
+
+    #[test]
+    fn test_write_checked() {
+        let mut servo = DynamicServo::new(info(1020)).unwrap();
+        let mut interface = NoInterface;
+        assert_eq!(servo.write_named(&mut interface, "Present Position", 0).map_err(|e| e.kind()), Err(ErrorKind::ReadOnlyRegister));
+        assert_eq!(servo.write_named(&mut interface, "Torque Enable", 2).map_err(|e| e.kind()), Err(ErrorKind::OutOfRange));
+        assert_eq!(servo.write_named(&mut interface, "Warp Drive", 0).map_err(|e| e.kind()), Err(ErrorKind::UnknownRegister));
+    }
+
+    #[test]
+    fn test_protocol1_address_space() {
+        let mut servo = DynamicServo::new(ServoInfo::Protocol1(::protocol1::ServoInfo {
+            baud_rate: ::BaudRate::Baud57600,
+            model_number: 29,
+            fw_version: 0,
+            id: ::protocol1::ServoID::new(1),
+        })).unwrap();
+        let mut interface = NoInterface;
+        let mut data = [0u8; 2];
+        assert_eq!(servo.read_raw(&mut interface, 0x100, &mut data).map_err(|e| e.kind()), Err(ErrorKind::UnknownRegister));
+        assert_eq!(servo.write_raw(&mut interface, 0x100, &data).map_err(|e| e.kind()), Err(ErrorKind::UnknownRegister));
+        assert_eq!(servo.read_raw(&mut interface, 0, &mut data).map_err(|e| e.kind()), Err(ErrorKind::Communication(::CommunicationError::Other)));
+    }
+
+    #[test]
+    fn test_read_ping_only() {
+        let mut servo = DynamicServo::new(ServoInfo::Protocol2(::protocol2::ServoInfo {
+            baud_rate: ::BaudRate::Baud57600,
+            model_number: 1020,
+            fw_version: 0,
+            id: ::protocol2::ServoID::new(1),
+        })).unwrap();
+        servo.assume_status_return_level(StatusReturnLevel::PingOnly);
+        let mut interface = NoInterface;
+        assert_eq!(servo.read_named(&mut interface, "Present Position").map_err(|e| e.kind()), Err(ErrorKind::NotAnswered));
+        // The level itself is still read from the servo
+        assert_eq!(servo.read_status_return_level(&mut interface).map_err(|e| e.kind()), Err(ErrorKind::Communication(::CommunicationError::Other)));
+    }
+}
@@ -1,8 +1,15 @@
-pub trait Register: ::protocol1::Register {}
-pub trait ReadRegister: ::protocol1::ReadRegister {}
-pub trait WriteRegister: ::protocol1::WriteRegister {}
+pub use models::mx28::*;
 
-rw_reg1!(TorqueEnable, bool, 24);
-rw_reg1!(Led, bool, 25);
-rw_reg1!(GoalPosition, u16, 30);
-r_reg1!(PresentPosition, u16, 36);
+rw_block1!(Goal {
+    goal_position: GoalPosition,
+    moving_speed: MovingSpeed,
+    torque_limit: TorqueLimit,
+});
+
+r_block1!(PresentState {
+    present_position: PresentPosition,
+    present_speed: PresentSpeed,
+    present_load: PresentLoad,
+    present_voltage: PresentVoltage,
+    present_temperature: PresentTemperature,
+});
@@ -0,0 +1,355 @@
+//! Searching a bus for servos
+//!
+//! `enumerate` tries every baud rate with both protocols. An `Enumeration` limits the search to the baud rates,
+//! IDs and protocols that are actually in use, which makes it much faster, and reports its progress while searching.
+//!
+//! Protocol 2 servos are found with a broadcast ping, answered by all servos in turn. When the answers are corrupted,
+//! or broadcast pings are disabled, every ID is pinged on its own. Protocol 1 servos do not answer broadcast pings reliably
+//! and are always pinged one ID at a time.
+
+use std::time::Duration;
+
+use BaudRate;
+use CommunicationError;
+use Interface;
+use Protocol;
+use ServoInfo;
+use protocol1;
+use protocol2;
+
+/// A callback receiving the `Progress` of an `Enumeration`
+type ProgressCallback<'a> = Box<dyn FnMut(&Progress) + 'a>;
+
+/// The state of an `Enumeration`, reported after every probe
+#[derive(Debug)]
+pub struct Progress<'b> {
+    pub protocol: Protocol,
+    pub baud_rate: BaudRate,
+
+    /// The pinged ID, `None` for a broadcast ping
+    pub id: Option<u8>,
+
+    /// The number of probes made so far, including this one
+    pub probes: usize,
+
+    /// The number of probes planned, this grows when a broadcast ping fails and the IDs are pinged one at a time instead
+    pub total: usize,
+
+    /// The servos found so far
+    pub found: &'b [ServoInfo],
+}
+
+/// A search for servos over a limited set of baud rates, IDs and protocols
+///
+/// ```
+/// use dynamixel::BaudRate;
+/// use dynamixel::Protocol;
+/// use dynamixel::enumeration::Enumeration;
+/// use dynamixel::sim::{SimulatedBus, VirtualServo};
+///
+/// let mut bus = SimulatedBus::new();
+/// bus.add(VirtualServo::from_model(Protocol::Protocol2, 1020, 1, BaudRate::Baud1000000).unwrap());
+///
+/// let servos = Enumeration::new()
+///     .baud_rates(&[BaudRate::Baud57600, BaudRate::Baud1000000])
+///     .ids(1..=20)
+///     .protocols(&[Protocol::Protocol2])
+///     .on_progress(|progress| println!("{} of {} probes", progress.probes, progress.total))
+///     .run(&mut bus)
+///     .unwrap();
+/// assert_eq!(servos.len(), 1);
+/// ```
+pub struct Enumeration<'a> {
+    baud_rates: Vec<BaudRate>,
+    ids: Vec<u8>,
+    protocols: Vec<Protocol>,
+    timeout: Option<Duration>,
+    broadcast: bool,
+    broadcast_timeout: Duration,
+    limit: Option<usize>,
+    progress: Option<ProgressCallback<'a>>,
+}
+
+impl<'a> Enumeration<'a> {
+    /// Search all baud rates, all IDs and both protocols, like `enumerate`
+    pub fn new() -> Self {
+        Enumeration {
+            baud_rates: BaudRate::variants().to_vec(),
+            ids: (0..=253).collect(),
+            protocols: vec![Protocol::Protocol1, Protocol::Protocol2],
+            timeout: None,
+            broadcast: true,
+            broadcast_timeout: ::DEFAULT_TIMEOUT,
+            limit: None,
+            progress: None,
+        }
+    }
+
+    /// Search these baud rates, in the given order
+    pub fn baud_rates(mut self, baud_rates: &[BaudRate]) -> Self {
+        self.baud_rates = baud_rates.to_vec();
+        self
+    }
+
+    /// Search these IDs, e.g. `1..=20`
+    ///
+    /// IDs above 253 are ignored, they are not valid servo IDs.
+    pub fn ids<T: IntoIterator<Item=u8>>(mut self, ids: T) -> Self {
+        self.ids = ids.into_iter().filter(|id| *id <= 253).collect();
+        self.ids.sort();
+        self.ids.dedup();
+        self
+    }
+
+    /// Search with these protocols, in the given order
+    pub fn protocols(mut self, protocols: &[Protocol]) -> Self {
+        self.protocols = protocols.to_vec();
+        self
+    }
+
+    /// Wait this long for the answer to a ping of a single ID
+    ///
+    /// By default, the time needed to transfer the ping and the answer at the baud rate, plus 2 ms for the servo to answer.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Find protocol 2 servos with a broadcast ping, enabled by default
+    ///
+    /// Disable it when the servos are not answering in turn, e.g. when several servos share an ID.
+    pub fn broadcast(mut self, broadcast: bool) -> Self {
+        self.broadcast = broadcast;
+        self
+    }
+
+    /// Wait this long for the next answer to a broadcast ping, by default `DEFAULT_TIMEOUT`
+    pub fn broadcast_timeout(mut self, timeout: Duration) -> Self {
+        self.broadcast_timeout = timeout;
+        self
+    }
+
+    /// Stop the search when `limit` servos are found
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Call `f` after every probe
+    pub fn on_progress<F: FnMut(&Progress) + 'a>(mut self, f: F) -> Self {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    /// Search the bus, returning the servos in the order they were found
+    ///
+    /// Baud rates the interface does not support are skipped. The interface is left at the last searched baud rate,
+    /// with the timeout of the last probe.
+    pub fn run<I: Interface>(&mut self, interface: &mut I) -> Result<Vec<ServoInfo>, CommunicationError> {
+        let mut servos = Vec::new();
+        let ids = self.ids.clone();
+        let mut probes = 0;
+        let mut total = self.baud_rates.len() * self.protocols.iter().map(|p| self.planned_probes(*p)).sum::<usize>();
+
+        for &baud_rate in &self.baud_rates.clone() {
+            if interface.set_baud_rate(baud_rate).is_err() {
+                warn!(target: "enumeration", "not able to enumerate devices on baudrate: {}", u32::from(baud_rate));
+                total -= self.protocols.iter().map(|p| self.planned_probes(*p)).sum::<usize>();
+                continue;
+            }
+
+            for &protocol in &self.protocols.clone() {
+                let mut sweep = &ids[..];
+                if self.planned_probes(protocol) == 1 {
+                    interface.set_timeout(self.broadcast_timeout).map_err(::communication_error)?;
+                    let complete = self.broadcast_ping(interface, baud_rate, &mut servos)?;
+                    probes += 1;
+                    if !complete {
+                        total += ids.len();
+                    } else {
+                        sweep = &[];
+                    }
+                    self.report(Progress{protocol: protocol, baud_rate: baud_rate, id: None, probes: probes, total: total, found: &servos});
+                }
+
+                interface.set_timeout(self.timeout.unwrap_or_else(|| probe_timeout(baud_rate))).map_err(::communication_error)?;
+                for &id in sweep {
+                    if self.is_done(&servos) {
+                        return Ok(servos);
+                    }
+                    if servos.iter().any(|servo| found(servo, protocol, baud_rate, id)) {
+                        probes += 1;
+                        continue;
+                    }
+
+                    let servo = match protocol {
+                        Protocol::Protocol1 => protocol1::probe(interface, protocol1::ServoID::new(id), baud_rate)?.map(ServoInfo::Protocol1),
+                        Protocol::Protocol2 => protocol2::probe(interface, protocol2::ServoID::new(id), baud_rate)?.map(ServoInfo::Protocol2),
+                    };
+                    servos.extend(servo);
+                    probes += 1;
+                    self.report(Progress{protocol: protocol, baud_rate: baud_rate, id: Some(id), probes: probes, total: total, found: &servos});
+                }
+
+                if self.is_done(&servos) {
+                    return Ok(servos);
+                }
+            }
+        }
+        Ok(servos)
+    }
+
+    /// The number of probes for one baud rate, a broadcast ping counts as one
+    fn planned_probes(&self, protocol: Protocol) -> usize {
+        match protocol {
+            Protocol::Protocol2 if self.broadcast => 1,
+            _ => self.ids.len(),
+        }
+    }
+
+    fn is_done(&self, servos: &[ServoInfo]) -> bool {
+        self.limit.map_or(false, |limit| servos.len() >= limit)
+    }
+
+    fn report(&mut self, progress: Progress) {
+        if let Some(ref mut f) = self.progress {
+            f(&progress);
+        }
+    }
+
+    /// Ping all protocol 2 servos at once, returns false if the answers were corrupted
+    fn broadcast_ping<I: Interface>(&self, interface: &mut I, baud_rate: BaudRate, servos: &mut Vec<ServoInfo>) -> Result<bool, CommunicationError> {
+        interface.flush();
+        protocol2::write_instruction(interface, &protocol2::instruction::Ping::new(protocol2::PacketID::Broadcast))?;
+
+        loop {
+            if self.is_done(servos) {
+                // Drop the answers of the remaining servos
+                interface.flush();
+                return Ok(true);
+            }
+            match protocol2::read_status::<I, protocol2::instruction::Pong>(interface).map(|r| r.status) {
+                Ok(pong) => if self.ids.contains(&u8::from(pong.id)) {
+                    servos.push(ServoInfo::Protocol2(protocol2::ServoInfo{
+                        baud_rate: baud_rate,
+                        model_number: pong.model_number,
+                        fw_version: pong.fw_version,
+                        id: pong.id,
+                    }));
+                },
+                Err(protocol2::Error::Communication(CommunicationError::TimedOut)) => return Ok(true),
+                Err(protocol2::Error::Communication(e)) => return Err(e),
+                Err(e) => {
+                    warn!(target: "enumeration", "received error: {:?} when waiting for broadcast ping on baud: {}, pinging every ID instead", e, u32::from(baud_rate));
+                    interface.flush();
+                    return Ok(false);
+                },
+            }
+        }
+    }
+}
+
+impl<'a> Default for Enumeration<'a> {
+    fn default() -> Self {
+        Enumeration::new()
+    }
+}
+
+/// Time to transfer a ping and its answer at `baud_rate`, with 2 ms for the servo to answer
+pub(crate) fn probe_timeout(baud_rate: BaudRate) -> Duration {
+    // A protocol 2 ping and its status are 24 bytes, with 10 bits for every byte
+    Duration::from_millis(2) + Duration::from_nanos(240_000_000_000 / u64::from(u32::from(baud_rate)))
+}
+
+fn found(servo: &ServoInfo, protocol: Protocol, baud_rate: BaudRate, id: u8) -> bool {
+    match *servo {
+        ServoInfo::Protocol1(ref info) => protocol == Protocol::Protocol1 && info.baud_rate == baud_rate && u8::from(info.id) == id,
+        ServoInfo::Protocol2(ref info) => protocol == Protocol::Protocol2 && info.baud_rate == baud_rate && u8::from(info.id) == id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sim::{SimulatedBus, VirtualServo};
+
+    fn simulated_bus() -> SimulatedBus {
+        let mut bus = SimulatedBus::new();
+        bus.add(VirtualServo::from_model(Protocol::Protocol2, 1020, 1, BaudRate::Baud57600).unwrap());
+        bus.add(VirtualServo::from_model(Protocol::Protocol2, 1020, 2, BaudRate::Baud57600).unwrap());
+        bus.add(VirtualServo::from_model(Protocol::Protocol1, 29, 3, BaudRate::Baud57600).unwrap());
+        bus.add(VirtualServo::from_model(Protocol::Protocol1, 12, 4, BaudRate::Baud1000000).unwrap());
+        bus.add(VirtualServo::from_model(Protocol::Protocol2, 1020, 5, BaudRate::Baud1000000).unwrap());
+        bus
+    }
+
+    fn summary(servos: &[ServoInfo]) -> Vec<(Protocol, u8, u16, u32)> {
+        servos.iter().map(|servo| match *servo {
+            ServoInfo::Protocol1(ref info) => (Protocol::Protocol1, u8::from(info.id), info.model_number, u32::from(info.baud_rate)),
+            ServoInfo::Protocol2(ref info) => (Protocol::Protocol2, u8::from(info.id), info.model_number, u32::from(info.baud_rate)),
+        }).collect()
+    }
+
+    #[test]
+    fn test_search_space() {
+        let mut bus = simulated_bus();
+        let servos = Enumeration::new()
+            .baud_rates(&[BaudRate::Baud57600, BaudRate::Baud1000000])
+            .ids(1..=10)
+            .run(&mut bus)
+            .unwrap();
+        assert_eq!(summary(&servos), vec![
+            (Protocol::Protocol1, 3, 29, 57600),
+            (Protocol::Protocol2, 1, 1020, 57600),
+            (Protocol::Protocol2, 2, 1020, 57600),
+            (Protocol::Protocol1, 4, 12, 1_000_000),
+            (Protocol::Protocol2, 5, 1020, 1_000_000),
+        ]);
+
+        let servos = Enumeration::new()
+            .baud_rates(&[BaudRate::Baud1000000, BaudRate::Baud57600])
+            .ids(vec![2, 5, 200, 255])
+            .protocols(&[Protocol::Protocol2])
+            .run(&mut bus)
+            .unwrap();
+        assert_eq!(summary(&servos), vec![(Protocol::Protocol2, 5, 1020, 1_000_000), (Protocol::Protocol2, 2, 1020, 57600)]);
+    }
+
+    #[test]
+    fn test_sweep() {
+        let mut bus = simulated_bus();
+        let servos = Enumeration::new()
+            .baud_rates(&[BaudRate::Baud57600])
+            .ids(0..=5)
+            .broadcast(false)
+            .timeout(Duration::from_millis(1))
+            .run(&mut bus)
+            .unwrap();
+        assert_eq!(summary(&servos), vec![
+            (Protocol::Protocol1, 3, 29, 57600),
+            (Protocol::Protocol2, 1, 1020, 57600),
+            (Protocol::Protocol2, 2, 1020, 57600),
+        ]);
+        assert_eq!(bus.timeout(), Some(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn test_limit_and_progress() {
+        let mut bus = simulated_bus();
+        let mut reports = Vec::new();
+        let servos = Enumeration::new()
+            .baud_rates(&[BaudRate::Baud57600, BaudRate::Baud1000000])
+            .ids(1..=10)
+            .limit(2)
+            .on_progress(|progress| reports.push((progress.protocol, progress.id, progress.probes, progress.total, progress.found.len())))
+            .run(&mut bus)
+            .unwrap();
+        assert_eq!(summary(&servos), vec![(Protocol::Protocol1, 3, 29, 57600), (Protocol::Protocol2, 1, 1020, 57600)]);
+
+        // Ten protocol 1 pings and a broadcast ping for both baud rates
+        assert_eq!(reports.len(), 11);
+        assert_eq!(reports[2], (Protocol::Protocol1, Some(3), 3, 22, 1));
+        assert_eq!(reports[10], (Protocol::Protocol2, None, 11, 22, 2));
+        assert_eq!(bus.pending(), 0);
+    }
+}
@@ -0,0 +1,220 @@
+//! An `Interface` over the `embedded-hal` serial traits
+//!
+//! Requires the `embedded-hal` feature. `HalInterface` drives any UART implementing `embedded_hal::serial::Read<u8>`
+//! and `embedded_hal::serial::Write<u8>`, with a `Timer` for the read timeout and a `half_duplex::Direction` hook for half-duplex transceivers.
+
+use lib::fmt;
+use lib::time::Duration;
+
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::serial;
+use nb;
+
+use BaudRate;
+use CommunicationError;
+use Interface;
+use half_duplex::{Direction, NoDirection};
+
+/// A timer measuring the read timeout
+///
+/// Implement this for a hardware timer or a free running counter of the microcontroller.
+pub trait Timer {
+    /// Start the timer, it expires after `timeout`
+    fn start(&mut self, timeout: Duration);
+
+    /// Returns `true` if the timeout has passed since `start`
+    fn expired(&mut self) -> bool;
+}
+
+/// A direction pin that is high while transmitting, as the DE/RE pins of RS-485 transceivers
+#[derive(Debug)]
+pub struct DirectionPin<P: OutputPin>(pub P);
+
+impl<P: OutputPin> Direction for DirectionPin<P> where P::Error: fmt::Debug {
+    type Error = P::Error;
+
+    fn transmit(&mut self) -> Result<(), P::Error> {
+        self.0.set_high()
+    }
+
+    fn receive(&mut self) -> Result<(), P::Error> {
+        self.0.set_low()
+    }
+}
+
+/// An error from a `HalInterface`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error<RE, WE, DE> {
+    /// No byte was received before the timer expired
+    TimedOut,
+
+    /// The baud rate is not the one the UART was configured with
+    UnsupportedBaud(BaudRate),
+
+    Read(RE),
+    Write(WE),
+    Direction(DE),
+}
+
+impl<RE, WE, DE> From<Error<RE, WE, DE>> for CommunicationError {
+    fn from(e: Error<RE, WE, DE>) -> CommunicationError {
+        match e {
+            Error::TimedOut => CommunicationError::TimedOut,
+            Error::UnsupportedBaud(b) => CommunicationError::UnsupportedBaud(b),
+            _ => CommunicationError::Other,
+        }
+    }
+}
+
+/// An `Interface` over an `embedded-hal` UART
+///
+/// The `embedded-hal` serial traits can not change the baud rate,
+/// the UART must be configured with the baud rate given to `new` and other baud rates are rejected.
+pub struct HalInterface<S, T, D = NoDirection> {
+    serial: S,
+    timer: T,
+    direction: D,
+    baud_rate: BaudRate,
+    timeout: Duration,
+}
+
+impl<S, T, D> HalInterface<S, T, D>
+    where S: serial::Read<u8> + serial::Write<u8>,
+          T: Timer,
+          D: Direction {
+    /// Create an interface for a UART configured with `baud_rate`
+    pub fn new(serial: S, timer: T, direction: D, baud_rate: BaudRate) -> Self {
+        HalInterface {
+            serial: serial,
+            timer: timer,
+            direction: direction,
+            baud_rate: baud_rate,
+            timeout: ::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Return the UART, timer and direction hook
+    pub fn release(self) -> (S, T, D) {
+        (self.serial, self.timer, self.direction)
+    }
+}
+
+impl<S, T, D> Interface for HalInterface<S, T, D>
+    where S: serial::Read<u8> + serial::Write<u8>,
+          <S as serial::Read<u8>>::Error: fmt::Debug,
+          <S as serial::Write<u8>>::Error: fmt::Debug,
+          T: Timer,
+          D: Direction {
+    type Error = Error<<S as serial::Read<u8>>::Error, <S as serial::Write<u8>>::Error, D::Error>;
+
+    fn set_baud_rate(&mut self, b: BaudRate) -> Result<(), Self::Error> {
+        if b == self.baud_rate {
+            Ok(())
+        } else {
+            Err(Error::UnsupportedBaud(b))
+        }
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), Self::Error> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        while serial::Read::read(&mut self.serial).is_ok() {}
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), Self::Error> {
+        for byte in data.iter_mut() {
+            self.timer.start(self.timeout);
+            *byte = loop {
+                match serial::Read::read(&mut self.serial) {
+                    Ok(b) => break b,
+                    Err(nb::Error::WouldBlock) if self.timer.expired() => return Err(Error::TimedOut),
+                    Err(nb::Error::WouldBlock) => (),
+                    Err(nb::Error::Other(e)) => return Err(Error::Read(e)),
+                }
+            };
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.direction.transmit().map_err(Error::Direction)?;
+        for b in data {
+            nb::block!(serial::Write::write(&mut self.serial, *b)).map_err(Error::Write)?;
+        }
+        nb::block!(serial::Write::flush(&mut self.serial)).map_err(Error::Write)?;
+        self.direction.receive().map_err(Error::Direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lib::convert::Infallible;
+
+    #[derive(Default)]
+    struct Loopback {
+        buffer: [u8; 8],
+        len: usize,
+        pos: usize,
+    }
+
+    impl serial::Read<u8> for Loopback {
+        type Error = Infallible;
+
+        fn read(&mut self) -> nb::Result<u8, Infallible> {
+            if self.pos < self.len {
+                self.pos += 1;
+                Ok(self.buffer[self.pos - 1])
+            } else {
+                Err(nb::Error::WouldBlock)
+            }
+        }
+    }
+
+    impl serial::Write<u8> for Loopback {
+        type Error = Infallible;
+
+        fn write(&mut self, word: u8) -> nb::Result<(), Infallible> {
+            self.buffer[self.len] = word;
+            self.len += 1;
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    /// Expires after being polled `ticks` times
+    struct TickTimer {
+        ticks: u32,
+        left: u32,
+    }
+
+    impl Timer for TickTimer {
+        fn start(&mut self, _timeout: Duration) {
+            self.left = self.ticks;
+        }
+
+        fn expired(&mut self) -> bool {
+            self.left = self.left.saturating_sub(1);
+            self.left == 0
+        }
+    }
+
+    #[test]
+    fn test_loopback() {
+        let mut interface = HalInterface::new(Loopback::default(), TickTimer{ticks: 3, left: 0}, NoDirection, BaudRate::Baud1000000);
+        assert!(interface.set_baud_rate(BaudRate::Baud1000000).is_ok());
+        assert_eq!(interface.set_baud_rate(BaudRate::Baud57600), Err(Error::UnsupportedBaud(BaudRate::Baud57600)));
+
+        interface.write(&[1, 2, 3]).unwrap();
+        let mut data = [0u8; 2];
+        interface.read(&mut data).unwrap();
+        assert_eq!(data, [1, 2]);
+        assert_eq!(interface.read(&mut data), Err(Error::TimedOut));
+    }
+}
@@ -0,0 +1,227 @@
+//! Half-duplex buses
+//!
+//! Dynamixel servos share a single data line for both directions. Many TTL adapters and RS-485 transceivers echo
+//! the transmitted bytes back into the receive buffer, and some need a direction/enable line toggled around transmissions.
+//! `HalfDuplex` wraps any `Interface` and takes care of both.
+
+use lib::convert::Infallible;
+use lib::fmt;
+use lib::time::Duration;
+
+use BaudRate;
+use CommunicationError;
+use Interface;
+
+/// Switching a half-duplex transceiver between transmitting and receiving
+pub trait Direction {
+    type Error: fmt::Debug;
+
+    /// Called before the first byte of an instruction is written
+    fn transmit(&mut self) -> Result<(), Self::Error>;
+
+    /// Called after the last byte of an instruction is written
+    fn receive(&mut self) -> Result<(), Self::Error>;
+}
+
+/// For transceivers without a direction pin, or with automatic direction control
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoDirection;
+
+impl Direction for NoDirection {
+    type Error = Infallible;
+
+    fn transmit(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// An error from a `HalfDuplex` interface
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error<E, DE> {
+    /// An error from the wrapped interface
+    Interface(E),
+
+    /// The echo did not match the transmitted bytes, usually because of a collision on the bus
+    Echo,
+
+    Direction(DE),
+}
+
+impl<E: Into<CommunicationError>, DE> From<Error<E, DE>> for CommunicationError {
+    fn from(e: Error<E, DE>) -> CommunicationError {
+        match e {
+            Error::Interface(e) => e.into(),
+            Error::Echo | Error::Direction(_) => CommunicationError::Other,
+        }
+    }
+}
+
+/// An `Interface` wrapper for half-duplex buses
+///
+/// Every write is surrounded by the `Direction` hook, and the echo of the written bytes is read and verified afterwards.
+/// The wrapped interface must not return from `write` before the bytes are transmitted, for the direction to be switched in time.
+pub struct HalfDuplex<I, D = NoDirection> {
+    interface: I,
+    direction: D,
+    echo: bool,
+}
+
+impl<I: Interface, D: Direction> HalfDuplex<I, D> {
+    /// Wrap `interface`, with echo cancellation enabled
+    pub fn new(interface: I, direction: D) -> Self {
+        HalfDuplex {
+            interface: interface,
+            direction: direction,
+            echo: true,
+        }
+    }
+
+    /// Enable or disable reading the echo after writes
+    ///
+    /// Disable it for transceivers that do not receive while transmitting.
+    pub fn set_echo_cancellation(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    pub fn echo_cancellation(&self) -> bool {
+        self.echo
+    }
+
+    /// Return the wrapped interface and the direction hook
+    pub fn release(self) -> (I, D) {
+        (self.interface, self.direction)
+    }
+}
+
+impl<I: Interface, D: Direction> Interface for HalfDuplex<I, D> {
+    type Error = Error<I::Error, D::Error>;
+
+    fn set_baud_rate(&mut self, b: BaudRate) -> Result<(), Self::Error> {
+        self.interface.set_baud_rate(b).map_err(Error::Interface)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), Self::Error> {
+        self.interface.set_timeout(timeout).map_err(Error::Interface)
+    }
+
+    fn flush(&mut self) {
+        self.interface.flush()
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.interface.read(data).map_err(Error::Interface)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.direction.transmit().map_err(Error::Direction)?;
+        let written = self.interface.write(data).map_err(Error::Interface);
+        self.direction.receive().map_err(Error::Direction)?;
+        written?;
+
+        if self.echo {
+            let mut echo = [0u8; 16];
+            for chunk in data.chunks(echo.len()) {
+                let echo = &mut echo[..chunk.len()];
+                self.interface.read(echo).map_err(Error::Interface)?;
+                if echo != chunk {
+                    return Err(Error::Echo);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Echoes written bytes, with the byte at `corrupt` flipped
+    struct EchoInterface {
+        buffer: [u8; 32],
+        len: usize,
+        pos: usize,
+        corrupt: Option<usize>,
+    }
+
+    impl Interface for EchoInterface {
+        type Error = CommunicationError;
+
+        fn set_baud_rate(&mut self, _b: BaudRate) -> Result<(), CommunicationError> {Ok(())}
+        fn flush(&mut self) {self.pos = self.len;}
+
+        fn read(&mut self, data: &mut [u8]) -> Result<(), CommunicationError> {
+            if self.len - self.pos < data.len() {
+                return Err(CommunicationError::TimedOut);
+            }
+            data.copy_from_slice(&self.buffer[self.pos..self.pos + data.len()]);
+            self.pos += data.len();
+            Ok(())
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), CommunicationError> {
+            for (i, b) in data.iter().enumerate() {
+                self.buffer[self.len] = if self.corrupt == Some(i) {!b} else {*b};
+                self.len += 1;
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct CountingDirection {
+        transmitting: bool,
+        switches: u32,
+    }
+
+    impl Direction for CountingDirection {
+        type Error = Infallible;
+
+        fn transmit(&mut self) -> Result<(), Infallible> {
+            self.transmitting = true;
+            self.switches += 1;
+            Ok(())
+        }
+
+        fn receive(&mut self) -> Result<(), Infallible> {
+            self.transmitting = false;
+            self.switches += 1;
+            Ok(())
+        }
+    }
+
+    fn echo_interface(corrupt: Option<usize>) -> EchoInterface {
+        EchoInterface{buffer: [0; 32], len: 0, pos: 0, corrupt: corrupt}
+    }
+
+    #[test]
+    fn test_echo_cancellation() {
+        let mut interface = HalfDuplex::new(echo_interface(None), CountingDirection::default());
+        let data: [u8; 20] = [0xaa; 20];
+        interface.write(&data).unwrap();
+        let (inner, direction) = interface.release();
+        assert_eq!(inner.pos, 20);
+        assert!(!direction.transmitting);
+        assert_eq!(direction.switches, 2);
+
+        let mut interface = HalfDuplex::new(echo_interface(Some(17)), NoDirection);
+        assert_eq!(interface.write(&data), Err(Error::Echo));
+
+        let mut interface = HalfDuplex::new(echo_interface(Some(0)), NoDirection);
+        interface.set_echo_cancellation(false);
+        assert_eq!(interface.write(&data), Ok(()));
+    }
+
+    #[test]
+    fn test_protocol2_instruction() {
+        let mut interface = HalfDuplex::new(echo_interface(None), CountingDirection::default());
+        ::protocol2::write_instruction(&mut interface, &::protocol2::instruction::Ping::new(::protocol2::PacketID::unicast(1))).unwrap();
+        let (inner, direction) = interface.release();
+        assert_eq!(&inner.buffer[..inner.len], &[0xff, 0xff, 0xfd, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4e]);
+        assert_eq!(direction.switches, 2);
+    }
+}
@@ -1,4 +1,5 @@
 #![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::redundant_field_names)]
 
 mod lib {
     #[cfg(feature="std")]
@@ -7,7 +8,9 @@ mod lib {
     pub use core::*;
 }
 
-#[macro_use]
+use lib::fmt;
+
+#[cfg_attr(feature="std", macro_use)]
 extern crate log;
 
 extern crate bit_field;
@@ -15,16 +18,46 @@ extern crate bit_field;
 #[cfg(feature="serialport")]
 extern crate serialport;
 
+#[cfg(feature="embedded-hal")]
+extern crate embedded_hal;
+#[cfg(feature="embedded-hal")]
+extern crate nb;
+
+#[cfg(feature="tokio")]
+extern crate tokio;
 
+
+#[macro_use]
+pub mod descriptor;
 #[macro_use]
 pub mod protocol1;
 #[macro_use]
 pub mod protocol2;
+pub mod models;
 pub mod pro;
 pub mod dynamixel;
+pub mod dynamic;
+pub mod retry;
+pub mod half_duplex;
+pub mod bus;
+pub mod transaction;
+#[cfg(feature="async")]
+pub mod async_io;
 
 #[cfg(feature="serialport")]
 mod serial_impl;
+#[cfg(feature="embedded-hal")]
+pub mod hal;
+#[cfg(feature="std")]
+pub mod stream;
+#[cfg(feature="std")]
+pub mod mock;
+#[cfg(feature="std")]
+pub mod sim;
+#[cfg(feature="std")]
+pub mod control_loop;
+#[cfg(feature="std")]
+pub mod enumeration;
 
 /// The generic servo trait
 ///
@@ -46,22 +79,257 @@ pub trait Servo<I: Interface> {
 }
 
 
-/// An Error originating from an `Interface`
+/// The dynamixel communication protocols
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Protocol {
+    Protocol1,
+    Protocol2,
+}
+
+/// An Error originating from an `Interface`
+///
+/// May be extended and must not be matched against exhaustively.
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum CommunicationError {
     TimedOut,
     UnsupportedBaud(BaudRate),
     Other,
+
+    /// Any other failure, keeping the error of the interface
+    #[cfg(feature="std")]
+    Interface(InterfaceError),
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub enum Error {
+/// The error of an `Interface`, kept as the source of a `CommunicationError`
+///
+/// Interfaces keep the details of a failure by converting their error into `CommunicationError::Interface`.
+/// Two `InterfaceError`s are only equal if they are clones of each other.
+#[cfg(feature="std")]
+#[derive(Debug, Clone)]
+pub struct InterfaceError(std::sync::Arc<dyn std::error::Error + Send + Sync>);
+
+#[cfg(feature="std")]
+impl InterfaceError {
+    pub fn new<E: std::error::Error + Send + Sync + 'static>(e: E) -> Self {
+        InterfaceError(std::sync::Arc::new(e))
+    }
+
+    /// The error of the interface
+    pub fn get_ref(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        &*self.0
+    }
+
+    /// The error of the interface, if it is of type `E`
+    pub fn downcast_ref<E: std::error::Error + 'static>(&self) -> Option<&E> {
+        self.0.downcast_ref()
+    }
+}
+
+#[cfg(feature="std")]
+impl PartialEq for InterfaceError {
+    fn eq(&self, other: &InterfaceError) -> bool {
+        std::sync::Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(feature="std")]
+impl Eq for InterfaceError {}
+
+#[cfg(feature="std")]
+impl fmt::Display for InterfaceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// An error from using a servo, together with the servo and instruction it occurred for
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Error {
+    kind: ErrorKind,
+    id: Option<u8>,
+    instruction: Option<Instruction>,
+}
+
+impl Error {
+    /// Create an error without any context
+    pub fn new(kind: ErrorKind) -> Error {
+        Error {
+            kind: kind,
+            id: None,
+            instruction: None,
+        }
+    }
+
+    /// Add the ID of the servo the error occurred for
+    pub fn with_id(self, id: u8) -> Error {
+        Error {
+            id: Some(id),
+            ..self
+        }
+    }
+
+    /// Add the instruction that failed
+    pub fn with_instruction(self, instruction: Instruction) -> Error {
+        Error {
+            instruction: Some(instruction),
+            ..self
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind.clone()
+    }
+
+    /// The ID of the servo the error occurred for, if known
+    pub fn id(&self) -> Option<u8> {
+        self.id
+    }
+
+    /// The instruction that failed, if known
+    pub fn instruction(&self) -> Option<Instruction> {
+        self.instruction
+    }
+}
+
+/// What went wrong
+///
+/// May be extended and must not be matched against exhaustively.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ErrorKind {
+    /// The status was not completely received
     Unfinished,
     
     /// An Error originating from the `Interface`
     Communication(CommunicationError),
-    Format,
-    Processing,
+
+    /// The received status was malformed
+    Format(FormatError),
+
+    /// The servo reported an error while processing the instruction
+    Processing(ProcessingError),
+
+    /// No control table is known for the model number
+    UnknownModel(u16),
+
+    /// The register does not exist in the control table of the servo
+    UnknownRegister,
+
+    /// The register can not be written
+    ReadOnlyRegister,
+
+    /// The value is outside of the documented range of the register
+    OutOfRange,
+
+    /// The servo does not answer the instruction at its status return level, so it was not sent
+    NotAnswered,
+}
+
+/// The protocol specific cause of a malformed status
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FormatError {
+    Protocol1(protocol1::FormatError),
+    Protocol2(protocol2::FormatError),
+}
+
+/// The protocol specific error reported by the servo
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ProcessingError {
+    Protocol1(protocol1::ProcessingError),
+    Protocol2(protocol2::ProcessingError),
+}
+
+/// The instruction an error occurred for
+///
+/// May be extended and must not be matched against exhaustively.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Instruction {
+    Ping,
+    Read,
+    Write,
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Error {
+        Error::new(kind)
+    }
+}
+
+impl From<CommunicationError> for Error {
+    fn from(e: CommunicationError) -> Error {
+        Error::new(ErrorKind::Communication(e))
+    }
+}
+
+impl fmt::Display for CommunicationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CommunicationError::TimedOut => write!(f, "timed out"),
+            CommunicationError::UnsupportedBaud(b) => write!(f, "baud rate {} not supported by the interface", u32::from(b)),
+            CommunicationError::Other => write!(f, "interface error"),
+            #[cfg(feature="std")]
+            CommunicationError::Interface(ref e) => write!(f, "interface error: {}", e),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Ping => write!(f, "ping"),
+            Instruction::Read => write!(f, "read"),
+            Instruction::Write => write!(f, "write"),
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::Unfinished => write!(f, "status not completely received"),
+            ErrorKind::Communication(ref e) => write!(f, "communication error: {}", e),
+            ErrorKind::Format(FormatError::Protocol1(e)) => write!(f, "malformed protocol 1 status: {}", e),
+            ErrorKind::Format(FormatError::Protocol2(e)) => write!(f, "malformed protocol 2 status: {}", e),
+            ErrorKind::Processing(ProcessingError::Protocol1(e)) => write!(f, "servo reported an error: {}", e),
+            ErrorKind::Processing(ProcessingError::Protocol2(e)) => write!(f, "servo reported an error: {}", e),
+            ErrorKind::UnknownModel(model_number) => write!(f, "no control table for model number {}", model_number),
+            ErrorKind::UnknownRegister => write!(f, "register not in the control table"),
+            ErrorKind::ReadOnlyRegister => write!(f, "register is read only"),
+            ErrorKind::OutOfRange => write!(f, "value outside of the register range"),
+            ErrorKind::NotAnswered => write!(f, "not answered at the status return level of the servo"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.instruction, self.id) {
+            (Some(instruction), Some(id)) => write!(f, "{} of servo {} failed: ", instruction, id)?,
+            (Some(instruction), None) => write!(f, "{} failed: ", instruction)?,
+            (None, Some(id)) => write!(f, "servo {}: ", id)?,
+            (None, None) => (),
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+#[cfg(feature="std")]
+impl std::error::Error for CommunicationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            CommunicationError::Interface(ref e) => Some(e.get_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature="std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self.kind {
+            ErrorKind::Communication(ref e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 /// Baud rates the interface should support
@@ -134,7 +402,8 @@ impl From<BaudRate> for u32 {
 }
 
 impl BaudRate {
-    fn variants() -> &'static [Self] {
+    /// All baud rates, from the slowest to the fastest
+    pub fn variants() -> &'static [Self] {
         &[BaudRate::Baud9600,
           BaudRate::Baud19200,
           BaudRate::Baud57600,
@@ -153,12 +422,109 @@ impl BaudRate {
     }
 }
 
+/// Which instructions a servo returns a status packet for
+///
+/// The Status Return Level register is the same for both protocols, `u8::from` gives its register value.
+/// Servos leave the factory with `All`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StatusReturnLevel {
+    /// Only ping instructions are answered
+    PingOnly,
+
+    /// Ping and read instructions are answered
+    Read,
+
+    /// All instructions are answered
+    All,
+}
+
+impl Default for StatusReturnLevel {
+    fn default() -> StatusReturnLevel {
+        StatusReturnLevel::All
+    }
+}
+
+impl StatusReturnLevel {
+    pub fn replies_to_read(&self) -> bool {
+        *self != StatusReturnLevel::PingOnly
+    }
+
+    pub fn replies_to_write(&self) -> bool {
+        *self == StatusReturnLevel::All
+    }
+
+    /// The status to expect after a write instruction
+    pub(crate) fn write_reply(self) -> Reply {
+        if self.replies_to_write() {Reply::Always} else {Reply::Never}
+    }
+
+    /// The status to expect after the write changing the level from `self` to `new`
+    ///
+    /// The firmwares differ in whether they answer the write with the old or new level.
+    pub(crate) fn change_reply(self, new: StatusReturnLevel) -> Reply {
+        match (self.replies_to_write(), new.replies_to_write()) {
+            (true, true) => Reply::Always,
+            (false, false) => Reply::Never,
+            _ => Reply::Maybe,
+        }
+    }
+}
+
+impl From<u8> for StatusReturnLevel {
+    fn from(v: u8) -> StatusReturnLevel {
+        match v {
+            0 => StatusReturnLevel::PingOnly,
+            1 => StatusReturnLevel::Read,
+            _ => StatusReturnLevel::All,
+        }
+    }
+}
+
+impl From<StatusReturnLevel> for u8 {
+    fn from(level: StatusReturnLevel) -> u8 {
+        match level {
+            StatusReturnLevel::PingOnly => 0,
+            StatusReturnLevel::Read => 1,
+            StatusReturnLevel::All => 2,
+        }
+    }
+}
+
+/// Whether a status packet follows an instruction
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Reply {
+    Never,
+
+    /// A status may follow, a timeout is not an error
+    Maybe,
+
+    Always,
+}
+
+/// The time `Interface::read` waits for bytes before timing out, unless a servo is configured with another timeout
+pub const DEFAULT_TIMEOUT: lib::time::Duration = lib::time::Duration::from_millis(100);
+
 /// The interface for communicating with dynamixel servos.
 pub trait Interface {
+    /// The error type of the interface
+    ///
+    /// Implementations can use their own error type to keep the details of a failure.
+    /// When a transaction with a servo fails, the error is converted into a `CommunicationError`.
+    /// With the `std` feature, converting into `CommunicationError::Interface` keeps the error for the caller, as the `std::io::Error` conversion does.
+    type Error: Into<CommunicationError> + fmt::Debug;
+
     /// Set the baud rate of the interface
     ///
     /// `BaudRate` must not be matched against exhaustively.
-    fn set_baud_rate(&mut self, b: BaudRate) -> Result<(), CommunicationError>;
+    fn set_baud_rate(&mut self, b: BaudRate) -> Result<(), Self::Error>;
+
+    /// Set the time `read` waits for bytes before timing out
+    ///
+    /// This is called at the start of every transaction, implementations should only reconfigure the hardware when the timeout changes.
+    /// The default implementation ignores the timeout, leaving it to `read`.
+    fn set_timeout(&mut self, _timeout: lib::time::Duration) -> Result<(), Self::Error> {
+        Ok(())
+    }
 
     /// Flush out the read buffer
     ///
@@ -172,11 +538,10 @@ pub trait Interface {
     /// - read a number of bytes corresponding to `data.len()` into `data` and return `Ok(())`.
     /// - return `Err(_)`.
     ///
-    /// If bytes are not received for a given time, a timeout should occur.
-    /// A timeout is signaled by returning `Err(Error::Timeout)`.
-    /// The time between bytes before a timeout occur should be 100ms or more.
+    /// If bytes are not received for the time given to `set_timeout`, a timeout should occur.
+    /// A timeout is signaled by returning an error that converts into `CommunicationError::TimedOut`.
     /// If the timeout is not implemented, a "dead" servo can cause the code to "freeze".
-    fn read(&mut self, data: &mut [u8]) -> Result<(), CommunicationError>;
+    fn read(&mut self, data: &mut [u8]) -> Result<(), Self::Error>;
 
     /// A blocking/spinning write.
     ///
@@ -187,7 +552,34 @@ pub trait Interface {
     ///
     /// After a transmission is started the time between two consecutive bytes need to be less than 100ms.
     /// This is because the dynamixel actuator recognizes a time of more than 100ms between bytes as a communication problem.
-    fn write(&mut self, data: &[u8]) -> Result<(), CommunicationError>;
+    fn write(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// `Interface` methods with the errors converted into `CommunicationError`, as used by the protocols
+pub(crate) trait InterfaceExt: Interface {
+    /// Prepare the interface for a transaction with a servo
+    fn begin_transaction(&mut self, baud_rate: BaudRate, timeout: lib::time::Duration) -> Result<(), CommunicationError> {
+        self.set_baud_rate(baud_rate).map_err(communication_error)?;
+        self.set_timeout(timeout).map_err(communication_error)?;
+        self.flush();
+        Ok(())
+    }
+
+    fn receive(&mut self, data: &mut [u8]) -> Result<(), CommunicationError> {
+        self.read(data).map_err(communication_error)
+    }
+
+    fn transmit(&mut self, data: &[u8]) -> Result<(), CommunicationError> {
+        self.write(data).map_err(communication_error)
+    }
+}
+
+impl<I: Interface + ?Sized> InterfaceExt for I {}
+
+fn communication_error<E: Into<CommunicationError> + fmt::Debug>(e: E) -> CommunicationError {
+    #[cfg(feature="std")]
+    debug!(target: "interface", "interface error: {:?}", e);
+    e.into()
 }
 
 #[cfg(feature="std")]
@@ -195,7 +587,7 @@ impl From<std::io::Error> for CommunicationError {
     fn from(e: std::io::Error) -> CommunicationError {
         match e.kind() {
             std::io::ErrorKind::TimedOut => CommunicationError::TimedOut,
-            _ => CommunicationError::Other,
+            _ => CommunicationError::Interface(InterfaceError::new(e)),
         }
     }
 }
@@ -209,6 +601,7 @@ pub enum ServoInfo {
 
 /// Enumerate all servos connected to the interface
 ///
+/// Use an `enumeration::Enumeration` to search only some baud rates, IDs or protocols.
 /// This functions returns a Vec and thus requires the `std` feature.
 #[cfg(feature="std")]
 pub fn enumerate<I: ::Interface>(interface: &mut I) -> Result<Vec<ServoInfo>, CommunicationError> {
@@ -217,8 +610,8 @@ pub fn enumerate<I: ::Interface>(interface: &mut I) -> Result<Vec<ServoInfo>, Co
     let servos_protocol1 = protocol1::enumerate(interface)?;
     let servos_protocol2 = protocol2::enumerate(interface)?;
 
-    servos.append(&mut servos_protocol1.into_iter().map(|x| ServoInfo::Protocol1(x)).collect());
-    servos.append(&mut servos_protocol2.into_iter().map(|x| ServoInfo::Protocol2(x)).collect());
+    servos.append(&mut servos_protocol1.into_iter().map(ServoInfo::Protocol1).collect());
+    servos.append(&mut servos_protocol2.into_iter().map(ServoInfo::Protocol2).collect());
 
     Ok(servos)
 }
@@ -226,9 +619,10 @@ pub fn enumerate<I: ::Interface>(interface: &mut I) -> Result<Vec<ServoInfo>, Co
 /// Connect genericly to a servo
 ///
 /// Only offers basic functionality. If you need more functionality use the connect method of the correct servo type instead.
+/// Models without a typed servo are connected as a `dynamic::DynamicServo`, unknown models return an `ErrorKind::UnknownModel` error.
 /// This functions returns a Boxed trait and this requires the `std` feature.
 #[cfg(feature="std")]
-pub fn connect<I: Interface + 'static>(interface: &mut I, info: ServoInfo) -> Result<Box<Servo<I>>, CommunicationError> {
+pub fn connect<I: Interface + 'static>(interface: &mut I, info: ServoInfo) -> Result<Box<dyn Servo<I>>, Error> {
     match info {
         ServoInfo::Protocol1(si) => protocol1::connect(interface, si),
         ServoInfo::Protocol2(si) => protocol2::connect(interface, si),
@@ -237,8 +631,96 @@ pub fn connect<I: Interface + 'static>(interface: &mut I, info: ServoInfo) -> Re
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn test_error_cause() {
+        let error = Error::from(protocol2::Error::Format(protocol2::FormatError::CRC))
+            .with_id(3)
+            .with_instruction(Instruction::Read);
+        assert_eq!(error.kind(), ErrorKind::Format(FormatError::Protocol2(protocol2::FormatError::CRC)));
+        assert_eq!(error.id(), Some(3));
+        assert_eq!(error.instruction(), Some(Instruction::Read));
+    }
+
+    #[derive(Debug)]
+    struct NoiseError(u8);
+
+    impl From<NoiseError> for CommunicationError {
+        fn from(e: NoiseError) -> CommunicationError {
+            if e.0 == 0 {CommunicationError::TimedOut} else {CommunicationError::Other}
+        }
+    }
+
+    struct NoisyInterface {
+        timeout: Option<lib::time::Duration>,
+    }
+
+    impl Interface for NoisyInterface {
+        type Error = NoiseError;
+
+        fn set_baud_rate(&mut self, _b: BaudRate) -> Result<(), NoiseError> {Ok(())}
+        fn set_timeout(&mut self, timeout: lib::time::Duration) -> Result<(), NoiseError> {
+            self.timeout = Some(timeout);
+            Ok(())
+        }
+        fn flush(&mut self) {}
+        fn read(&mut self, _data: &mut [u8]) -> Result<(), NoiseError> {Err(NoiseError(0x55))}
+        fn write(&mut self, _data: &[u8]) -> Result<(), NoiseError> {Ok(())}
+    }
+
+    #[test]
+    fn test_interface_error() {
+        let mut interface = NoisyInterface{timeout: None};
+        let timeout = lib::time::Duration::from_millis(5);
+        assert_eq!(interface.begin_transaction(BaudRate::Baud57600, timeout), Ok(()));
+        assert_eq!(interface.timeout, Some(timeout));
+        assert_eq!(interface.receive(&mut [0u8]), Err(CommunicationError::Other));
+    }
+
+    #[test]
+    fn test_status_return_level() {
+        assert_eq!(StatusReturnLevel::from(1), StatusReturnLevel::Read);
+        assert_eq!(u8::from(StatusReturnLevel::PingOnly), 0);
+        assert!(StatusReturnLevel::Read.replies_to_read());
+        assert!(!StatusReturnLevel::Read.replies_to_write());
+        assert_eq!(StatusReturnLevel::All.change_reply(StatusReturnLevel::Read), Reply::Maybe);
+        assert_eq!(StatusReturnLevel::Read.change_reply(StatusReturnLevel::PingOnly), Reply::Never);
+        assert_eq!(StatusReturnLevel::default().write_reply(), Reply::Always);
+    }
+
+    #[cfg(feature="std")]
+    #[test]
+    fn test_error_display() {
+        let error = Error::from(CommunicationError::TimedOut)
+            .with_id(1)
+            .with_instruction(Instruction::Write);
+        assert_eq!(error.to_string(), "write of servo 1 failed: communication error: timed out");
+        assert_eq!(Error::new(ErrorKind::UnknownModel(42)).to_string(), "no control table for model number 42");
+        let error = Error::from(protocol1::Error::Processing(protocol1::ProcessingError::OVERHEATING)).with_id(2);
+        assert_eq!(error.to_string(), "servo 2: servo reported an error: overheating");
+        let error = Error::from(protocol2::Error::Format(protocol2::FormatError::CRC));
+        assert_eq!(error.to_string(), "malformed protocol 2 status: CRC mismatch");
+    }
+
+    #[cfg(feature="std")]
+    #[test]
+    fn test_interface_error_source() {
+        use std::error::Error as StdError;
+        let io_error = std::io::Error::new(std::io::ErrorKind::BrokenPipe, "unplugged");
+        let error = Error::from(CommunicationError::from(io_error));
+        match error.kind() {
+            ErrorKind::Communication(CommunicationError::Interface(ref e)) => {
+                assert_eq!(e.downcast_ref::<std::io::Error>().map(|e| e.kind()), Some(std::io::ErrorKind::BrokenPipe));
+            },
+            kind => panic!("unexpected error kind {:?}", kind),
+        }
+        let source = error.source().and_then(|e| e.source()).map(|e| e.to_string());
+        assert_eq!(source, Some("unplugged".to_string()));
+    }
 }
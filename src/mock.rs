@@ -0,0 +1,352 @@
+//! A scriptable `Interface` for testing code that talks to servos
+//!
+//! Requires the `std` feature. `MockInterface` checks the written instructions against a script of expectations
+//! and replays the scripted statuses, so servo code can be tested without hardware.
+//!
+//! ```
+//! # extern crate dynamixel;
+//! # use dynamixel::BaudRate;
+//! # use dynamixel::mock::{self, MockInterface};
+//! # use dynamixel::protocol2::ServoID;
+//! # fn main() {
+//! let id = ServoID::new(3);
+//! let mut interface = MockInterface::new();
+//! interface.expect(&mock::protocol2::ping(id)).reply(&mock::protocol2::pong(id, 1020, 44));
+//!
+//! let mut servo = dynamixel::pro::M4210S260R::new(id, BaudRate::Baud57600);
+//! assert_eq!(servo.ping(&mut interface).unwrap().model_number, 1020);
+//! interface.assert_done();
+//! # }
+//! ```
+//!
+//! Status packets can be corrupted to inject checksum and CRC errors, left out to inject timeouts,
+//! and the servos can switch baud rate to test recovery code.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use BaudRate;
+use CommunicationError;
+use Interface;
+
+/// An instruction the mock expects, and what the servos do about it
+#[derive(Debug, Clone)]
+struct Step {
+    instruction: Vec<u8>,
+    status: Option<Vec<u8>>,
+    baud_rate: Option<BaudRate>,
+}
+
+/// An `Interface` replaying a script of expected instructions and statuses
+///
+/// Written bytes not matching the next expected instruction make the mock panic, as an assertion would.
+#[derive(Debug, Default)]
+pub struct MockInterface {
+    script: VecDeque<Step>,
+    written: Vec<u8>,
+    instruction: Vec<u8>,
+    received: VecDeque<u8>,
+    baud_rate: Option<BaudRate>,
+    servo_baud_rate: Option<BaudRate>,
+    rejected_baud_rates: Vec<BaudRate>,
+    timeout: Option<Duration>,
+}
+
+/// An expected instruction, returned by `MockInterface::expect`
+///
+/// Without a `reply` the servos stay silent and the read times out.
+pub struct Expectation<'a> {
+    step: &'a mut Step,
+}
+
+impl<'a> Expectation<'a> {
+    /// Reply with the status packet `status`
+    pub fn reply(self, status: &[u8]) -> Self {
+        self.step.status = Some(status.to_vec());
+        self
+    }
+
+    /// Reply with `status`, with the last byte flipped to corrupt the checksum (protocol 1) or CRC (protocol 2)
+    pub fn reply_corrupted(self, status: &[u8]) -> Self {
+        let mut status = status.to_vec();
+        if let Some(last) = status.last_mut() {
+            *last = !*last;
+        }
+        self.step.status = Some(status);
+        self
+    }
+
+    /// Do not reply, making the read time out
+    pub fn time_out(self) -> Self {
+        self.step.status = None;
+        self
+    }
+
+    /// Switch the servos to `baud_rate` after the instruction, as when writing the baud rate register
+    pub fn then_change_baud_rate(self, baud_rate: BaudRate) -> Self {
+        self.step.baud_rate = Some(baud_rate);
+        self
+    }
+}
+
+impl MockInterface {
+    pub fn new() -> Self {
+        MockInterface::default()
+    }
+
+    /// Expect `instruction` to be written next
+    pub fn expect(&mut self, instruction: &[u8]) -> Expectation<'_> {
+        self.script.push_back(Step{instruction: instruction.to_vec(), status: None, baud_rate: None});
+        Expectation{step: self.script.back_mut().expect("a step was just added")}
+    }
+
+    /// Only let the servos understand instructions written with `baud_rate`
+    ///
+    /// Instructions written with another baud rate are garbled on the bus, they are discarded and not answered.
+    /// By default the servos understand every baud rate.
+    pub fn set_servo_baud_rate(&mut self, baud_rate: BaudRate) {
+        self.servo_baud_rate = Some(baud_rate);
+    }
+
+    /// Make `set_baud_rate` fail with `CommunicationError::UnsupportedBaud` for `baud_rate`
+    pub fn reject_baud_rate(&mut self, baud_rate: BaudRate) {
+        self.rejected_baud_rates.push(baud_rate);
+    }
+
+    /// The baud rate last set on the interface
+    pub fn baud_rate(&self) -> Option<BaudRate> {
+        self.baud_rate
+    }
+
+    /// The timeout last set on the interface
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// All bytes written to the interface
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// Returns `true` if every expected instruction was written
+    pub fn is_done(&self) -> bool {
+        self.script.is_empty() && self.instruction.is_empty()
+    }
+
+    /// Panic if an expected instruction was not written
+    pub fn assert_done(&self) {
+        if let Some(step) = self.script.front() {
+            panic!("expected instruction {:02x?} was never written", step.instruction);
+        }
+        if !self.instruction.is_empty() {
+            panic!("incomplete instruction written: {:02x?}", self.instruction);
+        }
+    }
+
+    /// Act on the written instruction once it is complete
+    fn instruction_written(&mut self) {
+        let step = match self.script.front() {
+            Some(step) => step,
+            None => panic!("unexpected instruction written: {:02x?}", self.instruction),
+        };
+        if self.instruction.len() < step.instruction.len() {
+            return;
+        }
+        let instruction = ::std::mem::take(&mut self.instruction);
+
+        if self.servo_baud_rate.is_some() && self.servo_baud_rate != self.baud_rate {
+            return;
+        }
+        if instruction != step.instruction {
+            panic!("unexpected instruction written: {:02x?}, expected: {:02x?}", instruction, step.instruction);
+        }
+
+        let step = self.script.pop_front().expect("the step was just inspected");
+        if let Some(status) = step.status {
+            self.received.extend(status);
+        }
+        if step.baud_rate.is_some() {
+            self.servo_baud_rate = step.baud_rate;
+        }
+    }
+}
+
+impl Interface for MockInterface {
+    type Error = CommunicationError;
+
+    fn set_baud_rate(&mut self, b: BaudRate) -> Result<(), CommunicationError> {
+        if self.rejected_baud_rates.contains(&b) {
+            return Err(CommunicationError::UnsupportedBaud(b));
+        }
+        self.baud_rate = Some(b);
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), CommunicationError> {
+        self.timeout = Some(timeout);
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        self.received.clear();
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), CommunicationError> {
+        if self.received.len() < data.len() {
+            self.received.clear();
+            return Err(CommunicationError::TimedOut);
+        }
+        for b in data.iter_mut() {
+            *b = self.received.pop_front().expect("the length was checked");
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), CommunicationError> {
+        self.written.extend_from_slice(data);
+        for b in data {
+            self.instruction.push(*b);
+            self.instruction_written();
+        }
+        Ok(())
+    }
+}
+
+/// Protocol 1 instructions and statuses for scripting a `MockInterface`
+pub mod protocol1 {
+    use protocol1::{checksum, instruction, packet, PacketID, ProcessingError, ReadRegister, ServoID, WriteRegister};
+
+    pub fn ping(id: ServoID) -> Vec<u8> {
+        packet(&instruction::Ping::new(PacketID::from(id))).as_slice().to_vec()
+    }
+
+    pub fn read<R: ReadRegister>(id: ServoID) -> Vec<u8> {
+        packet(&instruction::ReadData::<R>::new(PacketID::from(id))).as_slice().to_vec()
+    }
+
+    pub fn write<W: WriteRegister>(id: ServoID, register: W) -> Vec<u8> {
+        packet(&instruction::WriteData::new(PacketID::from(id), register)).as_slice().to_vec()
+    }
+
+    /// A status packet with the given parameters, such as the data of a read
+    pub fn status(id: ServoID, parameters: &[u8]) -> Vec<u8> {
+        error_status(id, None, parameters)
+    }
+
+    /// A status packet reporting `error`
+    pub fn error_status(id: ServoID, error: Option<ProcessingError>, parameters: &[u8]) -> Vec<u8> {
+        let mut status = vec![0xff, 0xff, u8::from(id), parameters.len() as u8 + 2, error.map(u8::from).unwrap_or(0)];
+        status.extend_from_slice(parameters);
+        let checksum = checksum::Checksum::calc(&status[2..]);
+        status.push(u8::from(checksum));
+        status
+    }
+
+    /// The status answering a ping
+    pub fn pong(id: ServoID) -> Vec<u8> {
+        status(id, &[])
+    }
+}
+
+/// Protocol 2 instructions and statuses for scripting a `MockInterface`
+pub mod protocol2 {
+    use protocol2::{instruction, packet, Instruction, PacketID, ProcessingError, ReadRegister, ServoID, WriteRegister};
+
+    /// A status packet is serialized like an instruction with the status instruction value, and the error as the first parameter
+    struct StatusPacket {
+        id: ServoID,
+        parameters: Vec<u8>,
+    }
+
+    impl Instruction for StatusPacket {
+        const INSTRUCTION_VALUE: u8 = 0x55;
+
+        fn id(&self) -> PacketID {
+            PacketID::from(self.id)
+        }
+
+        fn parameters(&self) -> u16 {
+            self.parameters.len() as u16
+        }
+
+        fn parameter(&self, index: usize) -> u8 {
+            self.parameters[index]
+        }
+    }
+
+    pub fn ping(id: ServoID) -> Vec<u8> {
+        packet(&instruction::Ping::new(PacketID::from(id))).as_slice().to_vec()
+    }
+
+    pub fn read<R: ReadRegister>(id: ServoID) -> Vec<u8> {
+        packet(&instruction::Read::<R>::new(PacketID::from(id))).as_slice().to_vec()
+    }
+
+    pub fn write<W: WriteRegister>(id: ServoID, register: W) -> Vec<u8> {
+        packet(&instruction::Write::new(PacketID::from(id), register)).as_slice().to_vec()
+    }
+
+    /// A status packet with the given parameters, such as the data of a read
+    pub fn status(id: ServoID, parameters: &[u8]) -> Vec<u8> {
+        error_status(id, false, None, parameters)
+    }
+
+    /// A status packet with the alert flag and the processing `error`
+    pub fn error_status(id: ServoID, alert: bool, error: Option<ProcessingError>, parameters: &[u8]) -> Vec<u8> {
+        let error = error.map(u8::from).unwrap_or(0) | if alert {0x80} else {0x00};
+        let mut status = StatusPacket{id: id, parameters: vec![error]};
+        status.parameters.extend_from_slice(parameters);
+        packet(&status).as_slice().to_vec()
+    }
+
+    /// The status answering a ping
+    pub fn pong(id: ServoID, model_number: u16, fw_version: u8) -> Vec<u8> {
+        status(id, &[model_number as u8, (model_number >> 8) as u8, fw_version])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol2::ServoID;
+    use pro::M4210S260R;
+
+    #[test]
+    fn test_scripted_servo() {
+        let id = ServoID::new(3);
+        let mut interface = MockInterface::new();
+        interface.expect(&protocol2::ping(id)).reply(&protocol2::pong(id, M4210S260R::<MockInterface>::MODEL_NUMBER, 1));
+        interface.expect(&protocol2::ping(id)).reply_corrupted(&protocol2::pong(id, 0, 1));
+        interface.expect(&protocol2::ping(id)).time_out();
+        interface.expect(&protocol2::read::<::pro::control_table::PresentPosition>(id)).reply(&protocol2::error_status(id, true, None, &[0x10, 0x00, 0x00, 0x00]));
+
+        let mut servo = M4210S260R::new(id, BaudRate::Baud57600);
+        assert_eq!(servo.ping(&mut interface).unwrap().model_number, M4210S260R::<MockInterface>::MODEL_NUMBER);
+        assert_eq!(servo.ping(&mut interface).unwrap_err(), ::protocol2::Error::Format(::protocol2::FormatError::CRC));
+        assert_eq!(servo.ping(&mut interface).unwrap_err(), ::protocol2::Error::Communication(CommunicationError::TimedOut));
+        assert_eq!(i32::from(servo.read::<::pro::control_table::PresentPosition>(&mut interface).unwrap()), 0x10);
+        assert!(servo.alert());
+        assert_eq!(interface.baud_rate(), Some(BaudRate::Baud57600));
+        interface.assert_done();
+    }
+
+    #[test]
+    fn test_baud_rate_change() {
+        let id = ::protocol1::ServoID::new(1);
+        let mut interface = MockInterface::new();
+        interface.reject_baud_rate(BaudRate::Baud9600);
+        assert_eq!(interface.set_baud_rate(BaudRate::Baud9600), Err(CommunicationError::UnsupportedBaud(BaudRate::Baud9600)));
+
+        interface.set_servo_baud_rate(BaudRate::Baud57600);
+        interface.expect(&protocol1::ping(id)).reply(&protocol1::pong(id)).then_change_baud_rate(BaudRate::Baud1000000);
+        interface.expect(&protocol1::ping(id)).reply(&protocol1::pong(id));
+
+        let mut servo = ::dynamixel::mx28::MX28::new(id, BaudRate::Baud57600);
+        assert_eq!(servo.ping(&mut interface), Ok(()));
+        assert_eq!(servo.ping(&mut interface), Err(::protocol1::Error::Communication(CommunicationError::TimedOut)));
+
+        let mut servo = ::dynamixel::mx28::MX28::new(id, BaudRate::Baud1000000);
+        assert_eq!(servo.ping(&mut interface), Ok(()));
+        interface.assert_done();
+    }
+}
@@ -0,0 +1,9 @@
+//! Control tables generated from the model definition files in `models/`
+//!
+//! Every model file becomes a module with the typed registers, `MODEL_NUMBER` and the runtime `CONTROL_TABLE` of that model.
+//! Adding support for the registers of a new model only requires adding its model file.
+
+// The radian values are copied verbatim from the model files
+#![allow(clippy::approx_constant, clippy::excessive_precision)]
+
+include!(concat!(env!("OUT_DIR"), "/models.rs"));
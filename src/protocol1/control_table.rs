@@ -1,3 +1,54 @@
+/// Declare the registers of a servo model together with its runtime `ControlTable`.
+///
+/// Every entry expands to a `r_reg1!` or `rw_reg1!` register and a `RegisterDescriptor` in the static `CONTROL_TABLE`.
+/// A `MODEL_NUMBER` constant is declared as well.
+/// The optional `position` lists the raw values of the min, zero and max radian position followed by the min and max radian.
+///
+/// ```text
+/// control_table1!{
+///     "Model Name", 0x1234, position 0, 2048, 4095, -3.14159265, 3.14159265;
+///     rw GoalPosition: u16 = 30, Ram, "Goal Position", range 0..=4095, unit "0.088 deg";
+/// }
+/// ```
+macro_rules! control_table1{
+    ($model:expr, $model_number:expr $(, position $min_value:literal, $zero_value:literal, $max_value:literal, $min_radian:literal, $max_radian:literal)*;
+     $($access:ident $name:ident: $type:ident = $address:expr, $area:ident, $label:expr $(, range $min:literal ..= $max:literal)* $(, unit $unit:expr)*;)+
+    ) => {
+        pub const MODEL_NUMBER: u16 = $model_number;
+        
+        $(control_table_register1!($access $name, $type, $address);)+
+
+        pub static CONTROL_TABLE: ::descriptor::ControlTable = ::descriptor::ControlTable {
+            name: $model,
+            model_number: $model_number,
+            protocol: ::Protocol::Protocol1,
+            position: descriptor_position!($($min_value, $zero_value, $max_value, $min_radian, $max_radian)*),
+            registers: &[
+                $(::descriptor::RegisterDescriptor {
+                    name: $label,
+                    address: <$name as ::protocol1::Register>::ADDRESS as u16,
+                    size: <$name as ::protocol1::Register>::SIZE as u16,
+                    signed: descriptor_signed!($type),
+                    access: descriptor_access!($access),
+                    area: ::descriptor::Area::$area,
+                    min: descriptor_option!($($min as i64)*),
+                    max: descriptor_option!($($max as i64)*),
+                    unit: descriptor_option!($($unit)*),
+                },)+
+            ],
+        };
+    };
+}
+
+macro_rules! control_table_register1{
+    (r $name:ident, $type:ident, $address:expr) => {
+        r_reg1!($name, $type, $address);
+    };
+    (rw $name:ident, $type:ident, $address:expr) => {
+        rw_reg1!($name, $type, $address);
+    };
+}
+
 macro_rules! rw_reg1{
     ($name:ident, $type:ident, $address:expr) => {
         register_impl1!($name, $type, $address);
@@ -33,6 +84,109 @@ macro_rules! r_reg1{
     };
 }
 
+/// A block of registers, read with a single instruction.
+///
+/// The registers must be listed in increasing address order and may not overlap.
+/// Gaps between registers are allowed and are read but discarded.
+macro_rules! r_block1{
+    ($name:ident { $($field:ident: $register:ident),+ $(,)* }) => {
+        block_impl1!($name { $($field: $register),+ });
+
+        const _: () = {
+            let mut end = 0;
+            $(
+                assert!(<$register as ::protocol1::Register>::ADDRESS >= end, "registers in a block must be in increasing address order and not overlap");
+                end = <$register as ::protocol1::Register>::ADDRESS + <$register as ::protocol1::Register>::SIZE;
+            )+
+            let _ = end;
+        };
+        
+        block_read_impl1!($name { $($field: $register),+ });
+    };
+}
+
+/// A block of registers, read or written with a single instruction.
+///
+/// The registers must be listed in increasing address order and cover a contiguous address range.
+macro_rules! rw_block1{
+    ($name:ident { $($field:ident: $register:ident),+ $(,)* }) => {
+        block_impl1!($name { $($field: $register),+ });
+
+        const _: () = {
+            let mut end = <$name as ::protocol1::Register>::ADDRESS;
+            $(
+                assert!(<$register as ::protocol1::Register>::ADDRESS == end, "registers in a writable block must cover a contiguous address range");
+                end = <$register as ::protocol1::Register>::ADDRESS + <$register as ::protocol1::Register>::SIZE;
+            )+
+            let _ = end;
+        };
+
+        block_read_impl1!($name { $($field: $register),+ });
+        
+        impl WriteRegister for $name {}
+        
+        impl ::protocol1::WriteRegister for $name {
+            fn serialize(&self, data: &mut [u8]) {
+                assert_eq!(data.len(), <$name as ::protocol1::Register>::SIZE as usize);
+                $(
+                    let start = (<$register as ::protocol1::Register>::ADDRESS - <$name as ::protocol1::Register>::ADDRESS) as usize;
+                    let end = start + <$register as ::protocol1::Register>::SIZE as usize;
+                    ::protocol1::WriteRegister::serialize(&self.$field, &mut data[start..end]);
+                )+
+            }
+        }
+    };
+}
+
+macro_rules! block_impl1{
+    ($name:ident { $($field:ident: $register:ident),+ }) => {
+        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+        pub struct $name {
+            $(pub $field: $register,)+
+        }
+
+        impl ::protocol1::Register for $name {
+            const ADDRESS: u8 = {
+                let mut address = u8::MAX;
+                $(
+                    if <$register as ::protocol1::Register>::ADDRESS < address {
+                        address = <$register as ::protocol1::Register>::ADDRESS;
+                    }
+                )+
+                address
+            };
+            const SIZE: u8 = {
+                let mut end = 0;
+                $(
+                    if <$register as ::protocol1::Register>::ADDRESS + <$register as ::protocol1::Register>::SIZE > end {
+                        end = <$register as ::protocol1::Register>::ADDRESS + <$register as ::protocol1::Register>::SIZE;
+                    }
+                )+
+                end - <$name as ::protocol1::Register>::ADDRESS
+            };
+        }
+    };
+}
+
+macro_rules! block_read_impl1{
+    ($name:ident { $($field:ident: $register:ident),+ }) => {
+        impl ReadRegister for $name {}
+        
+        impl ::protocol1::ReadRegister for $name {
+            fn deserialize(data: &[u8]) -> Self {
+                assert_eq!(data.len(), <$name as ::protocol1::Register>::SIZE as usize);
+                $name {
+                    $($field: {
+                        let start = (<$register as ::protocol1::Register>::ADDRESS - <$name as ::protocol1::Register>::ADDRESS) as usize;
+                        let end = start + <$register as ::protocol1::Register>::SIZE as usize;
+                        <$register as ::protocol1::ReadRegister>::deserialize(&data[start..end])
+                    },)+
+                }
+            }
+        }
+    };
+}
+
 macro_rules! register_impl1{
     ($name:ident, bool, $address:expr) => {
         #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -52,6 +206,15 @@ macro_rules! register_impl1{
             const ADDRESS: u8 = $address;
         }
     };
+    ($name:ident, i8, $address:expr) => {
+        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+        pub struct $name(i8);
+        
+        impl ::protocol1::Register for $name {
+            const SIZE: u8 = 1;
+            const ADDRESS: u8 = $address;
+        }
+    };
     ($name:ident, i16, $address:expr) => {
         #[derive(Debug, PartialEq, Eq, Clone, Copy)]
         pub struct $name(i16);
@@ -79,6 +242,15 @@ macro_rules! register_impl1{
             const ADDRESS: u8 = $address;
         }
     };
+    ($name:ident, u32, $address:expr) => {
+        #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+        pub struct $name(u32);
+        
+        impl ::protocol1::Register for $name {
+            const SIZE: u8 = 4;
+            const ADDRESS: u8 = $address;
+        }
+    };
 
 }
 
@@ -160,8 +332,9 @@ macro_rules! write_register_impl1{
         impl WriteRegister for $name {}
         
         impl ::protocol1::WriteRegister for $name {
-            fn serialize(&self) -> [u8; 4] {
-                [self.0 as u8, 0, 0, 0]
+            fn serialize(&self, data: &mut [u8]) {
+                assert_eq!(data.len(), 1);
+                data.copy_from_slice(&[self.0 as u8]);
             }    
         }
     };
@@ -169,8 +342,9 @@ macro_rules! write_register_impl1{
         impl WriteRegister for $name {}
         
         impl ::protocol1::WriteRegister for $name {
-            fn serialize(&self) -> [u8; 4] {
-                [self.0, 0, 0, 0]
+            fn serialize(&self, data: &mut [u8]) {
+                assert_eq!(data.len(), 1);
+                data.copy_from_slice(&[self.0]);
             }    
         }
     };
@@ -178,8 +352,9 @@ macro_rules! write_register_impl1{
         impl WriteRegister for $name {}
         
         impl ::protocol1::WriteRegister for $name {
-            fn serialize(&self) -> [u8; 4] {
-                [self.0 as u8, 0, 0, 0]
+            fn serialize(&self, data: &mut [u8]) {
+                assert_eq!(data.len(), 1);
+                data.copy_from_slice(&[self.0 as u8]);
             }    
         }
     };
@@ -187,8 +362,9 @@ macro_rules! write_register_impl1{
         impl WriteRegister for $name {}
         
         impl ::protocol1::WriteRegister for $name {
-            fn serialize(&self) -> [u8; 4] {
-                [self.0 as u8, (self.0 >> 8) as u8, 0, 0]
+            fn serialize(&self, data: &mut [u8]) {
+                assert_eq!(data.len(), 2);
+                data.copy_from_slice(&[self.0 as u8, (self.0 >> 8) as u8]);
             }    
         }
     };
@@ -196,8 +372,9 @@ macro_rules! write_register_impl1{
         impl WriteRegister for $name {}
         
         impl ::protocol1::WriteRegister for $name {
-            fn serialize(&self) -> [u8; 4] {
-                [self.0 as u8, (self.0 >> 8) as u8, 0, 0]
+            fn serialize(&self, data: &mut [u8]) {
+                assert_eq!(data.len(), 2);
+                data.copy_from_slice(&[self.0 as u8, (self.0 >> 8) as u8]);
             }    
         }
     };
@@ -205,8 +382,9 @@ macro_rules! write_register_impl1{
         impl WriteRegister for $name {}
         
         impl ::protocol1::WriteRegister for $name {
-            fn serialize(&self) -> [u8; 4] {
-                [self.0 as u8, (self.0 >> 8) as u8, (self.0 >> 16) as u8, (self.0 >> 24) as u8]
+            fn serialize(&self, data: &mut [u8]) {
+                assert_eq!(data.len(), 4);
+                data.copy_from_slice(&[self.0 as u8, (self.0 >> 8) as u8, (self.0 >> 16) as u8, (self.0 >> 24) as u8]);
             }    
         }
     };
@@ -214,8 +392,9 @@ macro_rules! write_register_impl1{
         impl WriteRegister for $name {}
         
         impl ::protocol1::WriteRegister for $name {
-            fn serialize(&self) -> [u8; 4] {
-                [self.0 as u8, (self.0 >> 8) as u8, (self.0 >> 16) as u8, (self.0 >> 24) as u8]
+            fn serialize(&self, data: &mut [u8]) {
+                assert_eq!(data.len(), 4);
+                data.copy_from_slice(&[self.0 as u8, (self.0 >> 8) as u8, (self.0 >> 16) as u8, (self.0 >> 24) as u8]);
             }    
         }
     };
@@ -13,11 +13,14 @@ impl Ping {
 
 impl Instruction for Ping {
     type Array = [u8; 6];
-    const LENGTH: u8 = 2;
     const INSTRUCTION_VALUE: u8 = 0x01;
 
+    fn length(&self) -> u8 {
+        2
+    }
+
     fn serialize(&self) -> [u8; 6] {
-        let mut array = [0xff, 0xff, u8::from(self.id), Self::LENGTH, Self::INSTRUCTION_VALUE, 0x00];
+        let mut array = [0xff, 0xff, u8::from(self.id), self.length(), Self::INSTRUCTION_VALUE, 0x00];
         array[5] = u8::from(checksum::Checksum::calc(&array[2..5]));
         array
     }
@@ -29,11 +32,11 @@ pub(crate) struct Pong {
 }
 
 impl Status for Pong {
-    const LENGTH: u8 = 2;
-
-    fn deserialize_parameters(id: ServoID, parameters: &[u8]) -> Self {
-        assert_eq!(parameters.len(), 0);
-        Pong {id: id}
+    fn deserialize_parameters(id: ServoID, parameters: &[u8]) -> Result<Self, FormatError> {
+        if !parameters.is_empty() {
+            return Err(FormatError::Length);
+        }
+        Ok(Pong {id: id})
     }
 }
 
@@ -50,32 +53,64 @@ impl<T: WriteRegister> WriteData<T> {
 }
 
 impl<T: WriteRegister> Instruction for WriteData<T>{
-    // Use max size (4) untill const generics land
-    type Array = [u8; 11];
-    const LENGTH: u8 = 3 + T::SIZE;
+    // Use max size untill const generics land
+    type Array = [u8; MAX_DATA_LENGTH + 7];
     const INSTRUCTION_VALUE: u8 = 0x03;
 
-    fn serialize(&self) -> [u8; 11] {
-        let mut array = [0xff, 0xff, u8::from(self.id), Self::LENGTH, Self::INSTRUCTION_VALUE, T::ADDRESS, 0x00, 0x00, 0x00, 0x00, 0x00];
-        let data = self.data.serialize();
-        for i in 0..T::SIZE as usize {
-            array[6+i] = data[i];
-        }
+    fn length(&self) -> u8 {
+        3 + T::SIZE
+    }
+
+    fn serialize(&self) -> Self::Array {
+        let mut array = [0u8; MAX_DATA_LENGTH + 7];
+        array[..6].copy_from_slice(&[0xff, 0xff, u8::from(self.id), self.length(), Self::INSTRUCTION_VALUE, T::ADDRESS]);
+        self.data.serialize(&mut array[6..(6+T::SIZE as usize)]);
         array[6+T::SIZE as usize] = u8::from(checksum::Checksum::calc(&array[2..(6+T::SIZE) as usize]));
         array
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub(crate) struct WriteDataResponse {
+    #[allow(dead_code)]
     pub id: ServoID,
 }
 
 impl Status for WriteDataResponse {
-    const LENGTH: u8 = 2;
-    
-    fn deserialize_parameters(id: ServoID, parameters: &[u8]) -> Self {
-        assert_eq!(parameters.len(), 0);
-        WriteDataResponse {id: id}
+    fn deserialize_parameters(id: ServoID, parameters: &[u8]) -> Result<Self, FormatError> {
+        if !parameters.is_empty() {
+            return Err(FormatError::Length);
+        }
+        Ok(WriteDataResponse {id: id})
+    }
+}
+
+pub(crate) struct WriteDataRaw<'a> {
+    pub id: PacketID,
+    pub address: u8,
+    pub data: &'a [u8],
+}
+
+impl<'a> WriteDataRaw<'a> {
+    pub fn new(id: PacketID, address: u8, data: &'a [u8]) -> Self {
+        WriteDataRaw{id: id, address: address, data: data}
+    }
+}
+
+impl<'a> Instruction for WriteDataRaw<'a> {
+    type Array = [u8; MAX_DATA_LENGTH + 7];
+    const INSTRUCTION_VALUE: u8 = 0x03;
+
+    fn length(&self) -> u8 {
+        3 + self.data.len() as u8
+    }
+
+    fn serialize(&self) -> Self::Array {
+        let mut array = [0u8; MAX_DATA_LENGTH + 7];
+        array[..6].copy_from_slice(&[0xff, 0xff, u8::from(self.id), self.length(), Self::INSTRUCTION_VALUE, self.address]);
+        array[6..6+self.data.len()].copy_from_slice(self.data);
+        array[6+self.data.len()] = u8::from(checksum::Checksum::calc(&array[2..6+self.data.len()]));
+        array
     }
 }
 
@@ -95,11 +130,14 @@ impl<T: ReadRegister> ReadData<T> {
 
 impl<T: ReadRegister> Instruction for ReadData<T>{
     type Array = [u8; 8];
-    const LENGTH: u8 = 4;
     const INSTRUCTION_VALUE: u8 = 0x02;
 
+    fn length(&self) -> u8 {
+        4
+    }
+
     fn serialize(&self) -> [u8; 8] {
-        let mut array = [0xff, 0xff, u8::from(self.id), Self::LENGTH, Self::INSTRUCTION_VALUE, T::ADDRESS, T::SIZE, 0x00];
+        let mut array = [0xff, 0xff, u8::from(self.id), self.length(), Self::INSTRUCTION_VALUE, T::ADDRESS, T::SIZE, 0x00];
         array[7] = u8::from(checksum::Checksum::calc(&array[2..7]));
         array
     }
@@ -107,17 +145,94 @@ impl<T: ReadRegister> Instruction for ReadData<T>{
 
 
 pub(crate) struct ReadDataResponse<T: ReadRegister> {
+    #[allow(dead_code)]
     pub id: ServoID,
     pub data: T,
 }
 
 impl<T: ReadRegister> Status for ReadDataResponse<T> {
-    // Use max size (4) untill const generics land
-    const LENGTH: u8 = 2 + T::SIZE;
-    
-    fn deserialize_parameters(id: ServoID, parameters: &[u8]) -> Self {
-        assert_eq!(parameters.len(), T::SIZE as usize);
-        ReadDataResponse {id: id, data: T::deserialize(parameters)}
+    fn deserialize_parameters(id: ServoID, parameters: &[u8]) -> Result<Self, FormatError> {
+        if parameters.len() != T::SIZE as usize {
+            return Err(FormatError::Length);
+        }
+        Ok(ReadDataResponse {id: id, data: T::deserialize(parameters)})
+    }
+}
+
+pub(crate) struct ReadDataRaw {
+    pub id: PacketID,
+    pub address: u8,
+    pub length: u8,
+}
+
+impl ReadDataRaw {
+    pub fn new(id: PacketID, address: u8, length: u8) -> Self {
+        ReadDataRaw{id: id, address: address, length: length}
+    }
+}
+
+impl Instruction for ReadDataRaw {
+    type Array = [u8; 8];
+    const INSTRUCTION_VALUE: u8 = 0x02;
+
+    fn length(&self) -> u8 {
+        4
+    }
+
+    fn serialize(&self) -> [u8; 8] {
+        let mut array = [0xff, 0xff, u8::from(self.id), self.length(), Self::INSTRUCTION_VALUE, self.address, self.length, 0x00];
+        array[7] = u8::from(checksum::Checksum::calc(&array[2..7]));
+        array
+    }
+}
+
+pub(crate) struct ReadDataRawResponse {
+    #[allow(dead_code)]
+    pub id: ServoID,
+    pub length: usize,
+    pub data: [u8; MAX_DATA_LENGTH],
+}
+
+impl Status for ReadDataRawResponse {
+    fn deserialize_parameters(id: ServoID, parameters: &[u8]) -> Result<Self, FormatError> {
+        let mut data = [0u8; MAX_DATA_LENGTH];
+        data[..parameters.len()].copy_from_slice(parameters);
+        Ok(ReadDataRawResponse {id: id, length: parameters.len(), data: data})
+    }
+}
+
+/// Writes the same registers of several servos, the servos do not answer it
+pub(crate) struct SyncWrite<'a> {
+    pub address: u8,
+    pub length: u8,
+    pub data: &'a [(ServoID, &'a [u8])],
+}
+
+impl<'a> SyncWrite<'a> {
+    pub fn new(address: u8, length: u8, data: &'a [(ServoID, &'a [u8])]) -> Self {
+        SyncWrite{address: address, length: length, data: data}
+    }
+}
+
+impl<'a> Instruction for SyncWrite<'a> {
+    type Array = [u8; 259];
+    const INSTRUCTION_VALUE: u8 = 0x83;
+
+    fn length(&self) -> u8 {
+        4 + self.data.len() as u8 * (1 + self.length)
+    }
+
+    fn serialize(&self) -> Self::Array {
+        let mut array = [0u8; 259];
+        array[..7].copy_from_slice(&[0xff, 0xff, u8::from(PacketID::broadcast()), self.length(), Self::INSTRUCTION_VALUE, self.address, self.length]);
+        let mut index = 7;
+        for &(id, data) in self.data {
+            array[index] = u8::from(id);
+            array[index+1..index+1+data.len()].copy_from_slice(data);
+            index += 1 + data.len();
+        }
+        array[index] = u8::from(checksum::Checksum::calc(&array[2..index]));
+        array
     }
 }
 
@@ -138,17 +253,92 @@ mod tests {
         assert_eq!(Ping::new(PacketID::unicast(1)).serialize(), [0xff, 0xff, 0x01, 0x02, 0x01, 0xfb]);
     }
     
+    #[test]
+    fn test_sync_write() {
+        let data: &[(ServoID, &[u8])] = &[
+            (ServoID::new(0), &[0x10, 0x00, 0x50, 0x01]),
+            (ServoID::new(1), &[0x20, 0x02, 0x60, 0x03]),
+            (ServoID::new(2), &[0x30, 0x00, 0x70, 0x01]),
+            (ServoID::new(3), &[0x20, 0x02, 0x80, 0x03]),
+        ];
+        let write = SyncWrite::new(0x1e, 4, data);
+        assert_eq!(
+            &write.serialize()[..write.length() as usize + 4],
+            &[0xff, 0xff, 0xfe, 0x18, 0x83, 0x1e, 0x04, 0x00, 0x10, 0x00, 0x50, 0x01, 0x01, 0x20, 0x02, 0x60, 0x03,
+              0x02, 0x30, 0x00, 0x70, 0x01, 0x03, 0x20, 0x02, 0x80, 0x03, 0x12][..]
+        );
+    }
+
     #[test]
     fn test_pong() {
-        assert_eq!(Pong::deserialize(&[0xff, 0xff, 0x01, 0x02, 0x00, 0x00]), //todo: fix checksum
+        assert_eq!(Pong::deserialize(&[0xff, 0xff, 0x01, 0x02, 0x00, 0xfc]),
                    Ok(Pong{id: ServoID::new(1)})
         );
     }
+
+    #[test]
+    fn test_status_validation() {
+        assert_eq!(Pong::deserialize(&[0xff, 0xfe, 0x01, 0x02, 0x00, 0xfc]), Err(Error::Format(FormatError::Header)));
+        assert_eq!(Pong::deserialize(&[0xff, 0xff, 0x01, 0x02, 0x00, 0xfb]), Err(Error::Format(FormatError::CRC)));
+        assert_eq!(Pong::deserialize(&[0xff, 0xff, 0x01, 0x03, 0x00, 0xfb]), Err(Error::Format(FormatError::Length)));
+        assert_eq!(Pong::deserialize(&[0xff, 0xff, 0xfe, 0x02, 0x00, 0xff]), Err(Error::Format(FormatError::ID)));
+        assert_eq!(Pong::deserialize(&[0xff, 0xff, 0x01, 0x02, 0x80, 0x7c]), Err(Error::Format(FormatError::InvalidError)));
+        assert_eq!(Pong::deserialize(&[0xff, 0xff, 0x01, 0x02, 0x24, 0xd8]),
+                   Err(Error::Processing(ProcessingError::OVERHEATING | ProcessingError::OVERLOAD))
+        );
+    }
     
     #[test]
     fn test_write() {
-        assert_eq!(WriteData::new(PacketID::unicast(1), ::dynamixel::mx28::control_table::GoalPosition::new(0x123)).serialize(), [0xff, 0xff, 0x01, 0x05, 0x03, 30, 0x23, 0x01, 180, 0x00, 0x00]);
-        assert_eq!(WriteData::new(PacketID::broadcast(), ::dynamixel::mx28::control_table::GoalPosition::new(0x123)).serialize(), [0xff, 0xff, 0xfe, 0x05, 0x03, 30, 0x23, 0x01, 183, 0x00, 0x00]);
+        assert_eq!(&WriteData::new(PacketID::unicast(1), ::dynamixel::mx28::control_table::GoalPosition::new(0x123)).serialize()[..11], &[0xff, 0xff, 0x01, 0x05, 0x03, 30, 0x23, 0x01, 180, 0x00, 0x00]);
+        assert_eq!(&WriteData::new(PacketID::broadcast(), ::dynamixel::mx28::control_table::GoalPosition::new(0x123)).serialize()[..11], &[0xff, 0xff, 0xfe, 0x05, 0x03, 30, 0x23, 0x01, 183, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_write_raw() {
+        let write = WriteDataRaw::new(PacketID::unicast(1), 30, &[0x23, 0x01]);
+        assert_eq!(&write.serialize()[..write.length() as usize + 4], &[0xff, 0xff, 0x01, 0x05, 0x03, 30, 0x23, 0x01, 180]);
+    }
+
+    #[test]
+    fn test_read_raw() {
+        assert_eq!(ReadDataRaw::new(PacketID::unicast(1), 36, 2).serialize(), [0xff, 0xff, 0x01, 0x04, 0x02, 36, 0x2, 210]);
+    }
+
+    #[test]
+    fn test_read_raw_response() {
+        let response = ReadDataRawResponse::deserialize(&[0xff, 0xff, 0x01, 0x04, 0x00, 0x23, 0x01, 0xd6]).unwrap();
+        assert_eq!(response.id, ServoID::new(1));
+        assert_eq!(&response.data[..response.length], &[0x23, 0x01]);
+    }
+
+    #[test]
+    fn test_write_block() {
+        use dynamixel::mx28::control_table::*;
+        let goal = Goal{
+            goal_position: GoalPosition::new(0x123),
+            moving_speed: MovingSpeed::new(0x45),
+            torque_limit: TorqueLimit::new(0x3ff),
+        };
+        let write = WriteData::new(PacketID::unicast(1), goal);
+        let raw = WriteDataRaw::new(PacketID::unicast(1), 30, &[0x23, 0x01, 0x45, 0x00, 0xff, 0x03]);
+        assert_eq!(write.length(), raw.length());
+        assert_eq!(&write.serialize()[..], &raw.serialize()[..]);
+    }
+
+    #[test]
+    fn test_read_block() {
+        use dynamixel::mx28::control_table::*;
+        assert_eq!(ReadData::<PresentState>::new(PacketID::unicast(1)).serialize(), [0xff, 0xff, 0x01, 0x04, 0x02, 36, 8, 204]);
+
+        let mut data = [0xff, 0xff, 0x01, 10, 0x00, 0x00, 0x08, 0x10, 0x00, 0x20, 0x04, 120, 35, 0x00];
+        data[13] = u8::from(::protocol1::checksum::Checksum::calc(&data[2..13]));
+        let response = ReadDataResponse::<PresentState>::deserialize(&data).unwrap();
+        assert_eq!(u16::from(response.data.present_position), 0x800);
+        assert_eq!(u16::from(response.data.present_speed), 0x10);
+        assert_eq!(u16::from(response.data.present_load), 0x420);
+        assert_eq!(u8::from(response.data.present_voltage), 120);
+        assert_eq!(u8::from(response.data.present_temperature), 35);
     }
 
     #[test]
@@ -2,92 +2,260 @@
 //!
 //! Documentation can be found in: http://support.robotis.com/en/product/actuator/dynamixel/dxl_communication.html
 
+#[cfg(feature="std")]
 use BaudRate;
+#[cfg(feature="std")]
+use Servo;
 use CommunicationError;
+use InterfaceExt;
+use Reply;
 
 use bit_field::BitField;
 
 #[macro_use]
 mod control_table;
 pub(crate) mod instruction;
-mod checksum;
+pub(crate) mod checksum;
 
-/// Enumerate all protocol 1 servos connected to the interface
-/// This functions returns a Vec and thus requires the `std` feature.
-#[cfg(feature="std")]
-pub fn enumerate<I: ::Interface>(interface: &mut I) -> Result<Vec<ServoInfo>, CommunicationError> {
-    let mut servos = Vec::new();
+/// Write the instruction on the interface
+pub(crate) fn write_instruction<I: ::Interface, T: Instruction>(interface: &mut I, instruction: &T) -> Result<(), CommunicationError> {
+    let length = instruction.length() as usize + 4;
+    interface.transmit(&instruction.serialize().as_ref()[..length])
+}
 
-    for b in BaudRate::variants() {
+/// Serialize the instruction into a packet for a `Transaction`
+pub(crate) fn packet<T: Instruction>(instruction: &T) -> ::transaction::Packet {
+    let length = instruction.length() as usize + 4;
+    ::transaction::Packet::from_bytes(instruction.serialize().as_ref()[..length].iter().cloned())
+}
+
+/// Read a status from the interface
+///
+/// If no instructions have been sent, there will not be any status to read.
+/// When `id` is a unicast ID, the status must be echoed from that servo.
+pub(crate) fn read_status<I: ::Interface, T: Status>(interface: &mut I, id: PacketID) -> Result<T, Error> {
+    let mut decoder = StatusDecoder::<T>::new(id);
+    let mut data = [0u8; MAX_DATA_LENGTH + 6];
+    while decoder.wanted() > 0 {
+        let wanted = decoder.wanted();
+        interface.receive(&mut data[..wanted])?;
+        decoder.feed(&data[..wanted])?;
+    }
+    decoder.finish()
+}
 
-        if let Err(_) = interface.set_baud_rate(*b) {
-            warn!(target: "protocol1", "not able to enumerate devices on baudrate: {}", u32::from(*b));
+/// Incremental decoding of a status packet
+///
+/// Received bytes are fed as they arrive, the header is validated as soon as it is complete.
+#[derive(Debug, Clone)]
+pub(crate) struct StatusDecoder<T: Status> {
+    id: PacketID,
+    data: [u8; MAX_DATA_LENGTH + 6],
+    received: usize,
+    phantom: ::lib::marker::PhantomData<T>,
+}
+
+impl<T: Status> StatusDecoder<T> {
+    /// Decode a status that, when `id` is a unicast ID, must be echoed from that servo.
+    pub fn new(id: PacketID) -> Self {
+        StatusDecoder {
+            id: id,
+            data: [0u8; MAX_DATA_LENGTH + 6],
+            received: 0,
+            phantom: ::lib::marker::PhantomData{},
         }
+    }
 
-        interface.flush();
-        let ping = ::protocol1::instruction::Ping::new(PacketID::Broadcast);
-        interface.write(&::protocol1::Instruction::serialize(&ping))?;
+    /// The number of bytes still missing from the packet, as far as it is known yet
+    pub fn wanted(&self) -> usize {
+        if self.received < 4 {
+            4 - self.received
+        } else {
+            // A corrupted length must not make the packet overrun the buffer
+            ::lib::cmp::min(4 + self.data[3] as usize, self.data.len()) - self.received
+        }
+    }
 
-        loop {
-            let mut received_data = [0u8; 6];
+    /// Feed received bytes, at most `wanted()` of them
+    pub fn feed(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.len() > self.wanted() {
+            return Err(Error::Format(FormatError::Length));
+        }
+        let header_done = self.received >= 4;
+        self.data[self.received..self.received + data.len()].copy_from_slice(data);
+        self.received += data.len();
 
-            match interface.read(&mut received_data) {
-                Ok(_) => (),
-                Err(CommunicationError::TimedOut) => break,
-                Err(e) => return Err(e),
-            };
+        if !header_done && self.received == 4 {
+            if self.data[0] != 0xff || self.data[1] != 0xff {
+                return Err(Error::Format(FormatError::Header));
+            }
+            let length = self.data[3] as usize;
+            if !(2..=MAX_DATA_LENGTH + 2).contains(&length) {
+                return Err(Error::Format(FormatError::Length));
+            }
+        }
+        Ok(())
+    }
 
-            match <::protocol1::instruction::Pong as ::protocol1::Status>::deserialize(&received_data) {
-                Ok(pong) => {
-                    interface.flush();
-                    let read_model = ::protocol1::instruction::ReadData::<GenericModelNumber>::new(::protocol1::PacketID::from(pong.id));
-                    interface.write(&::protocol1::Instruction::serialize(&read_model))?;
-                    let mut received_data_model = [0u8; 8];
-                    interface.read(&mut received_data_model)?;
-
-                    let model_number = match <::protocol1::instruction::ReadDataResponse<GenericModelNumber> as ::protocol1::Status>::deserialize(&received_data_model) {
-                        Ok(response) => response.data.value(),
-                        Err(e) => {
-                            warn!(target: "protocol1", "Found servo with baud: {} and id: {}. Could not resolve model number due to error: {:?}", u32::from(*b), u8::from(pong.id), e);
-                            continue;
-                        },
-                    };
-                                            
-                    servos.push(
-                        ServoInfo{
-                            baud_rate: *b,
-                            model_number: model_number,
-                            id: pong.id,
-                        });
-                },
-                Err(e) => {
-                    warn!(target: "protocol1", "received error: {:?} when waiting for enumeration on baud: {}", e, u32::from(*b));
-                    continue;
-                },
-            };
-        }   
+    /// Deserialize the complete packet
+    pub fn finish(self) -> Result<T, Error> {
+        if self.received < 4 || self.wanted() > 0 {
+            return Err(Error::Format(FormatError::Length));
+        }
+        let status = T::deserialize(&self.data[..self.received]);
+
+        // The ID is only trustworthy when the packet itself is well formed
+        if let (PacketID::Unicast(expected), false) = (self.id, matches!(status, Err(Error::Format(_)))) {
+            if self.data[2] != u8::from(expected) {
+                return Err(Error::Format(FormatError::ID));
+            }
+        }
+        status
+    }
+}
+
+/// Read the status following an instruction, as far as `reply` expects one
+///
+/// Returns `Ok(None)` if no status was expected, or an optional status did not arrive.
+pub(crate) fn read_reply<I: ::Interface, T: Status>(interface: &mut I, id: PacketID, reply: Reply) -> Result<Option<T>, Error> {
+    match reply {
+        Reply::Never => Ok(None),
+        Reply::Maybe => match read_status(interface, id) {
+            Err(Error::Communication(CommunicationError::TimedOut)) => Ok(None),
+            status => status.map(Some),
+        },
+        Reply::Always => read_status(interface, id).map(Some),
     }
-    Ok(servos)
 }
 
-/// Connect genericly to a protocol 2 servo
+/// Read `data.len()` bytes, starting at `address`, from the servo with the given `id`
+///
+/// This bypasses the typed registers and is useful for accessing registers not yet modeled by this crate.
+/// The interface must already be configured with the baud rate of the servo.
+/// Returns a `FormatError::Length` if `data.len()` is larger than `MAX_DATA_LENGTH`.
+pub fn read_raw<I: ::Interface>(interface: &mut I, id: ServoID, address: u8, data: &mut [u8]) -> Result<(), Error> {
+    if data.len() > MAX_DATA_LENGTH {
+        return Err(Error::Format(FormatError::Length));
+    }
+
+    let read = instruction::ReadDataRaw::new(PacketID::from(id), address, data.len() as u8);
+    write_instruction(interface, &read)?;
+    let response = read_status::<I, instruction::ReadDataRawResponse>(interface, PacketID::from(id))?;
+    if response.length != data.len() {
+        return Err(Error::Format(FormatError::Length));
+    }
+    data.copy_from_slice(&response.data[..response.length]);
+    Ok(())
+}
+
+/// Write `data` to the servo with the given `id`, starting at `address`
+///
+/// This bypasses the typed registers and is useful for accessing registers not yet modeled by this crate.
+/// The interface must already be configured with the baud rate of the servo, and the servo must answer writes.
+/// Returns a `FormatError::Length` if `data.len()` is larger than `MAX_DATA_LENGTH`.
+pub fn write_raw<I: ::Interface>(interface: &mut I, id: ServoID, address: u8, data: &[u8]) -> Result<(), Error> {
+    write_raw_with_reply(interface, id, address, data, Reply::Always)
+}
+
+/// Like `write_raw`, for a servo that returns the status to the write as described by `reply`
+pub(crate) fn write_raw_with_reply<I: ::Interface>(interface: &mut I, id: ServoID, address: u8, data: &[u8], reply: Reply) -> Result<(), Error> {
+    if data.len() > MAX_DATA_LENGTH {
+        return Err(Error::Format(FormatError::Length));
+    }
+
+    let write = instruction::WriteDataRaw::new(PacketID::from(id), address, data);
+    write_instruction(interface, &write)?;
+    read_reply::<I, instruction::WriteDataResponse>(interface, PacketID::from(id), reply)?;
+    Ok(())
+}
+
+/// Write to several servos with one instruction, starting at `address`
+///
+/// Every entry gives the ID of a servo and the bytes to write to it. The servos do not answer a sync write.
+/// The interface must already be configured with the baud rate of the servos, it is flushed before the instruction is sent.
+/// Returns a `FormatError::Length` if the entries are not of the same length, or if they do not fit in one instruction.
+pub fn sync_write<I: ::Interface>(interface: &mut I, address: u8, data: &[(ServoID, &[u8])]) -> Result<(), Error> {
+    let length = data.first().map_or(0, |&(_, data)| data.len());
+    if !data.iter().all(|&(_, data)| data.len() == length) || 4 + data.len() * (1 + length) > 255 {
+        return Err(Error::Format(FormatError::Length));
+    }
+
+    interface.flush();
+    write_instruction(interface, &instruction::SyncWrite::new(address, length as u8, data))?;
+    Ok(())
+}
+
+/// Enumerate all protocol 1 servos connected to the interface
+///
+/// Protocol 1 servos do not answer a broadcast ping reliably, so every ID is pinged on its own at every baud rate,
+/// with a timeout just long enough for the answer. Use an `enumeration::Enumeration` to search fewer baud rates or IDs.
+/// This functions returns a Vec and thus requires the `std` feature.
+#[cfg(feature="std")]
+pub fn enumerate<I: ::Interface>(interface: &mut I) -> Result<Vec<ServoInfo>, CommunicationError> {
+    let servos = ::enumeration::Enumeration::new().protocols(&[::Protocol::Protocol1]).run(interface)?;
+    Ok(servos.into_iter().filter_map(|servo| match servo {
+        ::ServoInfo::Protocol1(info) => Some(info),
+        ::ServoInfo::Protocol2(_) => None,
+    }).collect())
+}
+
+/// Ping the servo with the given `id` and read its model number and firmware version, `None` if no servo answers
+///
+/// The interface must already be configured with the baud rate and the timeout for the probe.
+#[cfg(feature="std")]
+pub(crate) fn probe<I: ::Interface>(interface: &mut I, id: ServoID, baud_rate: BaudRate) -> Result<Option<ServoInfo>, CommunicationError> {
+    interface.flush();
+    write_instruction(interface, &instruction::Ping::new(PacketID::from(id)))?;
+    match read_status::<I, instruction::Pong>(interface, PacketID::from(id)) {
+        // A servo reporting an error in the status is still there
+        Ok(_) | Err(Error::Processing(_)) => (),
+        Err(Error::Communication(CommunicationError::TimedOut)) => return Ok(None),
+        Err(Error::Communication(e)) => return Err(e),
+        Err(e) => {
+            warn!(target: "protocol1", "received error: {:?} when pinging id: {} on baud: {}", e, u8::from(id), u32::from(baud_rate));
+            return Ok(None);
+        },
+    }
+
+    // The firmware version follows the model number in the control table of every model
+    let mut data = [0u8; 3];
+    match read_raw(interface, id, MODEL_NUMBER_ADDRESS, &mut data) {
+        Ok(()) => Ok(Some(ServoInfo{
+            baud_rate: baud_rate,
+            model_number: data[0] as u16 | (data[1] as u16) << 8,
+            fw_version: data[2],
+            id: id,
+        })),
+        Err(Error::Communication(e)) if e != CommunicationError::TimedOut => Err(e),
+        Err(e) => {
+            warn!(target: "protocol1", "Found servo with baud: {} and id: {}. Could not resolve model number due to error: {:?}", u32::from(baud_rate), u8::from(id), e);
+            Ok(None)
+        },
+    }
+}
+
+/// Connect genericly to a protocol 1 servo
 ///
 /// Only offers basic functionality. If you need more functionality use the connect method of the correct servo type instead.
 /// This functions returns a Boxed trait and this requires the `std` feature.
 #[cfg(feature="std")]
-pub(crate) fn connect<I: ::Interface + 'static>(_interface: &mut I, info: ServoInfo) -> Result<Box<::Servo<I>>, CommunicationError>{
+pub(crate) fn connect<I: ::Interface + 'static>(_interface: &mut I, info: ServoInfo) -> Result<Box<dyn Servo<I>>, ::Error>{
     match info.model_number {
         ::dynamixel::mx28::MX28::<I>::MODEL_NUMBER => Ok(Box::new(::dynamixel::mx28::MX28::<I>::new(info.id, info.baud_rate))),
-        _ => unimplemented!(),
+        _ => Ok(Box::new(::dynamic::DynamicServo::new(::ServoInfo::Protocol1(info))?)),
     }
 }
 
 
 macro_rules! protocol1_servo {
-    ($name:ident, $write:path, $read:path, $model_number:expr) => {
+    ($name:ident, $write:path, $read:path, $model_number:expr, $status_return_level:path) => {
         pub struct $name<I: ::Interface> {
             id: ::protocol1::ServoID,
             baudrate: ::BaudRate,
+            timeout: ::lib::time::Duration,
+            status_return_level: ::StatusReturnLevel,
+            retry_policy: ::retry::RetryPolicy,
+            retry_stats: ::retry::RetryStats,
             interface: ::lib::marker::PhantomData<I>,
         }
         
@@ -99,65 +267,205 @@ macro_rules! protocol1_servo {
                 $name{
                     id: id,
                     baudrate: baudrate,
+                    timeout: ::DEFAULT_TIMEOUT,
+                    status_return_level: ::StatusReturnLevel::default(),
+                    retry_policy: ::retry::RetryPolicy::default(),
+                    retry_stats: ::retry::RetryStats::default(),
                     interface: ::lib::marker::PhantomData{},
                 }
             }
+
+            /// Set the time to wait for a status from the servo.
+            ///
+            /// Defaults to `DEFAULT_TIMEOUT`. Use short timeouts for fast polling and longer ones for e.g. EEPROM writes.
+            pub fn set_timeout(&mut self, timeout: ::lib::time::Duration) {
+                self.timeout = timeout;
+            }
+
+            pub fn timeout(&self) -> ::lib::time::Duration {
+                self.timeout
+            }
+
+            /// The status return level the servo is assumed to have
+            pub fn status_return_level(&self) -> ::StatusReturnLevel {
+                self.status_return_level
+            }
+
+            /// Configure the status return level the servo has, without communicating with it.
+            ///
+            /// Writes only wait for a status when the level is `All`.
+            /// Reads fail with `Error::NotAnswered` right away when the servo only answers pings, except `read_status_return_level`.
+            pub fn assume_status_return_level(&mut self, status_return_level: ::StatusReturnLevel) {
+                self.status_return_level = status_return_level;
+            }
+
+            /// Read the status return level from the servo and use it from now on.
+            pub fn read_status_return_level(&mut self, interface: &mut I) -> Result<::StatusReturnLevel, ::protocol1::Error> {
+                let address = <$status_return_level as ::protocol1::Register>::ADDRESS;
+                let mut data = [0u8];
+                // Sent whatever level is assumed, the assumption may be wrong
+                self.read_raw_unchecked(interface, address, &mut data)?;
+                self.status_return_level = ::StatusReturnLevel::from(data[0]);
+                Ok(self.status_return_level)
+            }
+
+            /// Change the status return level of the servo.
+            ///
+            /// A missing status for the write is accepted when either the old or the new level does not answer writes.
+            /// If the new level answers reads, the level is read back from the servo.
+            pub fn set_status_return_level(&mut self, interface: &mut I, status_return_level: ::StatusReturnLevel) -> Result<(), ::protocol1::Error> {
+                let (id, baudrate, timeout, address) = (self.id, self.baudrate, self.timeout, <$status_return_level as ::protocol1::Register>::ADDRESS);
+                let reply = self.status_return_level.change_reply(status_return_level);
+                self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol1::write_raw_with_reply(interface, id, address, &[u8::from(status_return_level)], reply)
+                })?;
+                self.status_return_level = status_return_level;
+                if status_return_level.replies_to_read() {
+                    self.read_status_return_level(interface)?;
+                }
+                Ok(())
+            }
+
+            /// Set the policy used for retrying failed transactions with this servo.
+            pub fn set_retry_policy(&mut self, retry_policy: ::retry::RetryPolicy) {
+                self.retry_policy = retry_policy;
+            }
+
+            pub fn retry_policy(&self) -> ::retry::RetryPolicy {
+                self.retry_policy
+            }
+
+            /// Retry counters for the transactions with this servo
+            pub fn retry_stats(&self) -> ::retry::RetryStats {
+                self.retry_stats
+            }
+
+            pub fn reset_retry_stats(&mut self) {
+                self.retry_stats = ::retry::RetryStats::default();
+            }
             
-            fn read_response(&mut self, interface: &mut I, data: &mut [u8]) -> Result<usize, ::CommunicationError> {
-                // first read header
-                interface.read(&mut data[..4])?;
-
-                // then read rest of message depending on header length
-                let length = data[3] as usize;
-                interface.read(&mut data[4..4+length])?;
-                Ok(4+length)
+            /// Fail a read right away if the servo does not answer it, rather than waiting for the timeout
+            fn check_replies_to_read(&self) -> Result<(), ::protocol1::Error> {
+                if self.status_return_level.replies_to_read() {
+                    Ok(())
+                } else {
+                    Err(::protocol1::Error::NotAnswered)
+                }
+            }
+
+            /// Convert a protocol error into an `Error` with the ID of this servo and the failed instruction
+            fn context(&self, instruction: ::Instruction) -> impl Fn(::protocol1::Error) -> ::Error {
+                let id = u8::from(self.id);
+                move |e| ::Error::from(e).with_id(id).with_instruction(instruction)
             }
 
             /// Ping the servo, returning `Ok(())` if it exists.
             pub fn ping(&mut self, interface: &mut I) -> Result<(), ::protocol1::Error> {
-                interface.set_baud_rate(self.baudrate)?;
-                interface.flush();
-
-                let ping = ::protocol1::instruction::Ping::new(::protocol1::PacketID::from(self.id));
-                interface.write(&::protocol1::Instruction::serialize(&ping))?;
-                let mut received_data = [0u8; 6];
-                self.read_response(interface, &mut received_data)?;
-                <::protocol1::instruction::Pong as ::protocol1::Status>::deserialize(&received_data)?;
-                Ok(())
+                let (id, baudrate, timeout) = (self.id, self.baudrate, self.timeout);
+                let ping = ::protocol1::instruction::Ping::new(::protocol1::PacketID::from(id));
+                self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol1::write_instruction(interface, &ping)?;
+                    ::protocol1::read_status::<I, ::protocol1::instruction::Pong>(interface, ::protocol1::PacketID::from(id))?;
+                    Ok(())
+                })
             }
             
             /// Write the given data `register` to the servo.
             pub fn write_data<W: $write>(&mut self, interface: &mut I, register: W) -> Result<(), ::protocol1::Error> {
-                interface.set_baud_rate(self.baudrate)?;
-                interface.flush();
-                
-                let write = ::protocol1::instruction::WriteData::new(::protocol1::PacketID::from(self.id), register);
-                interface.write(&::protocol1::Instruction::serialize(&write)[0..<::protocol1::instruction::WriteData<W> as ::protocol1::Instruction>::LENGTH as usize + 4])?;
-                let mut received_data = [0u8; 11];
-                let length = self.read_response(interface, &mut received_data)?;
-                match <::protocol1::instruction::WriteDataResponse as ::protocol1::Status>::deserialize(&received_data[0..length]) {
-                    Ok(::protocol1::instruction::WriteDataResponse{id: _}) => Ok(()),
-                    Err(e) => Err(e),
-                }
+                let (id, baudrate, timeout) = (self.id, self.baudrate, self.timeout);
+                let write = ::protocol1::instruction::WriteData::new(::protocol1::PacketID::from(id), register);
+                let reply = self.status_return_level.write_reply();
+                self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol1::write_instruction(interface, &write)?;
+                    ::protocol1::read_reply::<I, ::protocol1::instruction::WriteDataResponse>(interface, ::protocol1::PacketID::from(id), reply)?;
+                    Ok(())
+                })
             }
             
             pub fn read_data<R: $read>(&mut self, interface: &mut I) -> Result<R, ::protocol1::Error> {
-                interface.set_baud_rate(self.baudrate)?;
-                interface.flush();
-
-                let read = ::protocol1::instruction::ReadData::<R>::new(::protocol1::PacketID::from(self.id));
-                interface.write(&::protocol1::Instruction::serialize(&read))?;
-                let mut received_data = [0u8; 20];
-                let length = self.read_response(interface, &mut received_data)?;
-                match <::protocol1::instruction::ReadDataResponse<R> as ::protocol1::Status>::deserialize(&received_data[0..length]) {
-                    Ok(r) => Ok(r.data),
-                    Err(e) => Err(e),
-                }
+                self.check_replies_to_read()?;
+                let (id, baudrate, timeout) = (self.id, self.baudrate, self.timeout);
+                let read = ::protocol1::instruction::ReadData::<R>::new(::protocol1::PacketID::from(id));
+                self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol1::write_instruction(interface, &read)?;
+                    Ok(::protocol1::read_status::<I, ::protocol1::instruction::ReadDataResponse<R>>(interface, ::protocol1::PacketID::from(id))?.data)
+                })
+            }
+
+            /// Read `data.len()` bytes starting at `address`, bypassing the typed registers.
+            pub fn read_raw(&mut self, interface: &mut I, address: u8, data: &mut [u8]) -> Result<(), ::protocol1::Error> {
+                self.check_replies_to_read()?;
+                self.read_raw_unchecked(interface, address, data)
+            }
+
+            fn read_raw_unchecked(&mut self, interface: &mut I, address: u8, data: &mut [u8]) -> Result<(), ::protocol1::Error> {
+                let (id, baudrate, timeout) = (self.id, self.baudrate, self.timeout);
+                self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol1::read_raw(interface, id, address, &mut *data)
+                })
+            }
+
+            /// Write `data` starting at `address`, bypassing the typed registers.
+            pub fn write_raw(&mut self, interface: &mut I, address: u8, data: &[u8]) -> Result<(), ::protocol1::Error> {
+                let (id, baudrate, timeout) = (self.id, self.baudrate, self.timeout);
+                let reply = self.status_return_level.write_reply();
+                self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol1::write_raw_with_reply(interface, id, address, data, reply)
+                })
+            }
+        }
+
+        impl<'a, I: ::Interface> ::bus::Handle<'a, I, $name<::bus::Bus<I>>> {
+            /// See `read_status_return_level` of the servo.
+            pub fn read_status_return_level(&mut self) -> Result<::StatusReturnLevel, ::protocol1::Error> {
+                self.servo.read_status_return_level(self.bus)
+            }
+
+            /// See `set_status_return_level` of the servo.
+            pub fn set_status_return_level(&mut self, status_return_level: ::StatusReturnLevel) -> Result<(), ::protocol1::Error> {
+                self.servo.set_status_return_level(self.bus, status_return_level)
+            }
+
+            /// Ping the servo, returning `Ok(())` if it exists.
+            pub fn ping(&mut self) -> Result<(), ::protocol1::Error> {
+                self.servo.ping(self.bus)
+            }
+
+            /// Write the given data `register` to the servo.
+            pub fn write_data<W: $write>(&mut self, register: W) -> Result<(), ::protocol1::Error> {
+                self.servo.write_data(self.bus, register)
+            }
+
+            /// Read data from a register
+            pub fn read_data<R: $read>(&mut self) -> Result<R, ::protocol1::Error> {
+                self.servo.read_data(self.bus)
+            }
+
+            /// Read `data.len()` bytes starting at `address`, bypassing the typed registers.
+            pub fn read_raw(&mut self, address: u8, data: &mut [u8]) -> Result<(), ::protocol1::Error> {
+                self.servo.read_raw(self.bus, address, data)
+            }
+
+            /// Write `data` starting at `address`, bypassing the typed registers.
+            pub fn write_raw(&mut self, address: u8, data: &[u8]) -> Result<(), ::protocol1::Error> {
+                self.servo.write_raw(self.bus, address, data)
             }
         }
     };
 }
 
+/// The maximum number of data bytes that can be transferred in a single instruction
+pub const MAX_DATA_LENGTH: usize = 128;
+
+/// The address of the model number, this is the same for all protocol 1 servos.
+#[cfg(feature="std")]
+pub(crate) const MODEL_NUMBER_ADDRESS: u8 = 0x00;
 
 pub trait Register {
     const SIZE: u8;
@@ -165,73 +473,91 @@ pub trait Register {
 }
     
 pub trait ReadRegister: Register {
-    fn deserialize(&[u8]) -> Self;
+    fn deserialize(data: &[u8]) -> Self;
 }
 
 pub trait WriteRegister: Register {
-    // TODO: change 4 to Self::SIZE when const generics land
-    fn serialize(&self) -> [u8; 4];
+    /// Serialize the register into `data`, which is exactly `Self::SIZE` bytes long.
+    fn serialize(&self, data: &mut [u8]);
 }
 
 pub(crate) trait Instruction {
     // The array type is no longer needed when const generics land
-    // replace with [u8; Self::LENGTH]
-    type Array;
-    const LENGTH: u8;
+    // replace with [u8; self.length()]
+    type Array: AsRef<[u8]>;
     const INSTRUCTION_VALUE: u8;
 
+    fn length(&self) -> u8;
+
     // Serialize can be implemented generically once const generics land
     fn serialize(&self) -> Self::Array;
 }
 
 pub(crate) trait Status {
-    const LENGTH: u8;
-
-    fn deserialize_parameters(id: ServoID, parameters: &[u8]) -> Self;
+    fn deserialize_parameters(id: ServoID, parameters: &[u8]) -> Result<Self, FormatError> where Self: Sized;
     
+    /// Deserialize a complete status packet, including header and checksum
     fn deserialize(data: &[u8]) -> Result<Self, Error>
         where Self: Sized {
-        // check for formating error stuff
-        
-        // check for processing errors
-        if let Some(error) = ProcessingError::decode(data[4]).map_err(|()| Error::Format(FormatError::InvalidError))? {
-            return Err(Error::Processing(error));
+        if data.len() < 6 {
+            return Err(Error::Format(FormatError::Length));
+        }
+
+        if data[0] != 0xff || data[1] != 0xff {
+            return Err(Error::Format(FormatError::Header));
         }
         
-        let length = data[3];
-        if length != Self::LENGTH {
+        let length = data[3] as usize;
+        if length < 2 || data.len() != 4 + length {
             return Err(Error::Format(FormatError::Length));
         }
 
+        if data[3 + length] != u8::from(checksum::Checksum::calc(&data[2..3 + length])) {
+            return Err(Error::Format(FormatError::CRC));
+        }
+
+        // 254 is the broadcast ID, which is never used in a status
+        if data[2] > 253 {
+            return Err(Error::Format(FormatError::ID));
+        }
         let id = ServoID::new(data[2]);
         
-        let parameters_range = 5..(5 + Self::LENGTH as usize - 2);
-        Ok( Self::deserialize_parameters(id, &data[parameters_range]) )
+        if let Some(error) = ProcessingError::decode(data[4])? {
+            return Err(Error::Processing(error));
+        }
+        
+        let parameters_range = 5..(5 + length - 2);
+        Ok( Self::deserialize_parameters(id, &data[parameters_range])? )
     }
 }
 
 /// All information needed to connect to a protocol 1 servo
 #[derive(Debug, Clone)]
 pub struct ServoInfo {
-    baud_rate: ::BaudRate,
-    model_number: u16,
-    id: ServoID,
+    pub baud_rate: ::BaudRate,
+    pub model_number: u16,
+    pub fw_version: u8,
+    pub id: ServoID,
 }
 
    
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Error {
     Communication(::CommunicationError),
     Format(FormatError),
     Processing(ProcessingError),
+
+    /// The servo does not answer the instruction at its status return level, so it was not sent
+    NotAnswered,
 }
 
 impl From<::protocol1::Error> for ::Error {
     fn from(e: ::protocol1::Error) -> ::Error {
         match e {
-            ::protocol1::Error::Communication(ce) => ::Error::Communication(ce),
-            ::protocol1::Error::Format(_) => ::Error::Format,
-            ::protocol1::Error::Processing(_) => ::Error::Processing,
+            ::protocol1::Error::Communication(ce) => ::Error::new(::ErrorKind::Communication(ce)),
+            ::protocol1::Error::Format(fe) => ::Error::new(::ErrorKind::Format(::FormatError::Protocol1(fe))),
+            ::protocol1::Error::Processing(pe) => ::Error::new(::ErrorKind::Processing(::ProcessingError::Protocol1(pe))),
+            ::protocol1::Error::NotAnswered => ::Error::new(::ErrorKind::NotAnswered),
         }
     }
 }
@@ -242,6 +568,12 @@ impl From<::CommunicationError> for Error {
     }
 }
 
+impl From<FormatError> for Error {
+    fn from(e: FormatError) -> Error {
+        Error::Format(e)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum FormatError {
     ID,
@@ -251,6 +583,22 @@ pub enum FormatError {
     InvalidError,
 }
 
+impl ::lib::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut ::lib::fmt::Formatter) -> ::lib::fmt::Result {
+        match *self {
+            FormatError::ID => write!(f, "unexpected servo ID"),
+            FormatError::Header => write!(f, "invalid header"),
+            FormatError::CRC => write!(f, "checksum mismatch"),
+            FormatError::Length => write!(f, "invalid length"),
+            FormatError::InvalidError => write!(f, "invalid error byte"),
+        }
+    }
+}
+
+/// The error bits of a protocol 1 status packet
+///
+/// A bitflags style set of errors. Single errors are available as associated constants that can be matched against,
+/// tested for with `contains` and the errors that are set can be iterated with `iter`.
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub struct ProcessingError(u8);
 
@@ -261,56 +609,146 @@ impl From<ProcessingError> for u8 {
 }
 
 impl ProcessingError {
-    pub fn decode(v: u8) -> Result<Option<Self>, ()> {
+    pub const INPUT_VOLTAGE: ProcessingError = ProcessingError(1 << 0);
+    pub const ANGLE_LIMIT: ProcessingError = ProcessingError(1 << 1);
+    pub const OVERHEATING: ProcessingError = ProcessingError(1 << 2);
+    pub const RANGE: ProcessingError = ProcessingError(1 << 3);
+    pub const CHECKSUM: ProcessingError = ProcessingError(1 << 4);
+    pub const OVERLOAD: ProcessingError = ProcessingError(1 << 5);
+    pub const INSTRUCTION: ProcessingError = ProcessingError(1 << 6);
+
+    const NAMES: [(ProcessingError, &'static str); 7] = [
+        (ProcessingError::INPUT_VOLTAGE, "INPUT_VOLTAGE"),
+        (ProcessingError::ANGLE_LIMIT, "ANGLE_LIMIT"),
+        (ProcessingError::OVERHEATING, "OVERHEATING"),
+        (ProcessingError::RANGE, "RANGE"),
+        (ProcessingError::CHECKSUM, "CHECKSUM"),
+        (ProcessingError::OVERLOAD, "OVERLOAD"),
+        (ProcessingError::INSTRUCTION, "INSTRUCTION"),
+    ];
+
+    pub fn decode(v: u8) -> Result<Option<Self>, FormatError> {
         if v == 0 {
             Ok(None)
         } else if v.get_bit(7) {
-            Err(())
+            Err(FormatError::InvalidError)
         } else {
             Ok(Some(ProcessingError(v)))
         }
     }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns `true` if all errors in `other` are set
+    pub fn contains(&self, other: ProcessingError) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Iterate over the single errors that are set, from the lowest bit to the highest
+    pub fn iter(&self) -> ProcessingErrorIter {
+        ProcessingErrorIter {
+            bits: self.0,
+            index: 0,
+        }
+    }
     
     pub fn instruction_error(&self) -> bool {
-        self.0.get_bit(6)
+        self.contains(ProcessingError::INSTRUCTION)
     }
 
     pub fn overload_error(&self) -> bool {
-        self.0.get_bit(5)
+        self.contains(ProcessingError::OVERLOAD)
     }
 
     pub fn checksum_error(&self) -> bool {
-        self.0.get_bit(4)
+        self.contains(ProcessingError::CHECKSUM)
     }
 
     pub fn range_error(&self) -> bool {
-        self.0.get_bit(3)
+        self.contains(ProcessingError::RANGE)
     }
 
     pub fn overheating_error(&self) -> bool {
-        self.0.get_bit(3)
+        self.contains(ProcessingError::OVERHEATING)
     }
 
     pub fn angle_limit_error(&self) -> bool {
-        self.0.get_bit(1)
+        self.contains(ProcessingError::ANGLE_LIMIT)
     }
 
     pub fn input_voltage_error(&self) -> bool {
-        self.0.get_bit(0)
+        self.contains(ProcessingError::INPUT_VOLTAGE)
+    }
+}
+
+impl ::lib::ops::BitOr for ProcessingError {
+    type Output = ProcessingError;
+
+    fn bitor(self, rhs: ProcessingError) -> ProcessingError {
+        ProcessingError(self.0 | rhs.0)
+    }
+}
+
+impl IntoIterator for ProcessingError {
+    type Item = ProcessingError;
+    type IntoIter = ProcessingErrorIter;
+
+    fn into_iter(self) -> ProcessingErrorIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the single errors of a `ProcessingError`
+#[derive(Debug, Clone)]
+pub struct ProcessingErrorIter {
+    bits: u8,
+    index: usize,
+}
+
+impl Iterator for ProcessingErrorIter {
+    type Item = ProcessingError;
+
+    fn next(&mut self) -> Option<ProcessingError> {
+        while self.index < ProcessingError::NAMES.len() {
+            let (flag, _) = ProcessingError::NAMES[self.index];
+            self.index += 1;
+            if self.bits & flag.0 != 0 {
+                return Some(flag);
+            }
+        }
+        None
     }
 }
 
 impl ::lib::fmt::Debug for ProcessingError {
     fn fmt(&self, f: &mut ::lib::fmt::Formatter) -> ::lib::fmt::Result {
-        write!(f, "The current ProcessingError, {:?}, decodes to the following errors: [", self.0)?;
-        if self.instruction_error() {write!(f, "instruction_error")?;}
-        if self.overload_error() {write!(f, "overload_error")?;}
-        if self.checksum_error() {write!(f, "checksum_error")?;}
-        if self.range_error() {write!(f, "range_error")?;}
-        if self.overheating_error() {write!(f, "overheating_error")?;}
-        if self.angle_limit_error() {write!(f, "angle_limit_error")?;}
-        if self.input_voltage_error() {write!(f, "input_voltage_error")?;}
-        write!(f, "]")
+        write!(f, "ProcessingError(")?;
+        for (i, &(_, name)) in ProcessingError::NAMES.iter().filter(|&&(flag, _)| self.contains(flag)).enumerate() {
+            if i != 0 {write!(f, " | ")?;}
+            write!(f, "{}", name)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl ::lib::fmt::Display for ProcessingError {
+    fn fmt(&self, f: &mut ::lib::fmt::Formatter) -> ::lib::fmt::Result {
+        for (i, error) in self.iter().enumerate() {
+            if i != 0 {write!(f, ", ")?;}
+            let description = match error {
+                ProcessingError::INPUT_VOLTAGE => "input voltage out of range",
+                ProcessingError::ANGLE_LIMIT => "goal position out of the angle limits",
+                ProcessingError::OVERHEATING => "overheating",
+                ProcessingError::RANGE => "instruction out of range",
+                ProcessingError::CHECKSUM => "checksum of the instruction packet incorrect",
+                ProcessingError::OVERLOAD => "overload",
+                _ => "undefined or invalid instruction",
+            };
+            write!(f, "{}", description)?;
+        }
+        Ok(())
     }
 }
 
@@ -331,6 +769,7 @@ pub(crate) enum PacketID {
 }
 
 impl PacketID {
+    #[allow(dead_code)]
     pub fn unicast(id: u8) -> PacketID {
         assert!(id <= 253);
         PacketID::Unicast(ServoID::new(id))
@@ -362,23 +801,77 @@ impl From<ServoID> for u8 {
     }
 }
 
-/// This is the model number register for all protocol 1 servos.
-struct GenericModelNumber(u16);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_processing_error() {
+        let error = ProcessingError::decode(0x24).unwrap().unwrap();
+        assert!(error.overheating_error());
+        assert!(error.overload_error());
+        assert!(!error.range_error());
+        assert!(error.contains(ProcessingError::OVERHEATING | ProcessingError::OVERLOAD));
+
+        let mut errors = error.iter();
+        assert_eq!(errors.next(), Some(ProcessingError::OVERHEATING));
+        assert_eq!(errors.next(), Some(ProcessingError::OVERLOAD));
+        assert_eq!(errors.next(), None);
+
+        match ProcessingError::decode(0x08).unwrap().unwrap() {
+            ProcessingError::RANGE => (),
+            e => panic!("unexpected error: {:?}", e),
+        }
 
-impl GenericModelNumber {
-    fn value(&self) -> u16 {
-        self.0
+        assert_eq!(ProcessingError::decode(0x00), Ok(None));
+        assert_eq!(ProcessingError::decode(0x80), Err(FormatError::InvalidError));
     }
-}
-                      
-impl Register for GenericModelNumber {
-    const SIZE: u8 =  2;
-    const ADDRESS: u8 = 0x00;
-}
-    
-impl ReadRegister for GenericModelNumber {
-    fn deserialize(bytes: &[u8]) -> Self {
-        assert_eq!(bytes.len(), 2);
-        GenericModelNumber(bytes[0] as u16 | (bytes[1] as u16) << 8)        
+
+    struct SilentInterface;
+
+    impl ::Interface for SilentInterface {
+        type Error = CommunicationError;
+
+        fn set_baud_rate(&mut self, _b: ::BaudRate) -> Result<(), CommunicationError> {Ok(())}
+        fn flush(&mut self) {}
+        fn read(&mut self, _data: &mut [u8]) -> Result<(), CommunicationError> {Err(CommunicationError::TimedOut)}
+        fn write(&mut self, _data: &[u8]) -> Result<(), CommunicationError> {Ok(())}
+    }
+
+    #[test]
+    fn test_read_reply() {
+        let id = PacketID::unicast(1);
+        assert_eq!(read_reply::<_, instruction::WriteDataResponse>(&mut SilentInterface, id, Reply::Never), Ok(None));
+        assert_eq!(read_reply::<_, instruction::WriteDataResponse>(&mut SilentInterface, id, Reply::Maybe), Ok(None));
+        assert_eq!(read_reply::<_, instruction::WriteDataResponse>(&mut SilentInterface, id, Reply::Always), Err(Error::Communication(CommunicationError::TimedOut)));
+    }
+
+    #[test]
+    fn test_sync_write_length() {
+        let ids = [ServoID::new(1), ServoID::new(2)];
+        assert_eq!(sync_write(&mut SilentInterface, 30, &[(ids[0], &[0, 0]), (ids[1], &[0])]), Err(Error::Format(FormatError::Length)));
+        assert_eq!(sync_write(&mut SilentInterface, 30, &[(ids[0], &[0u8; 251])]), Err(Error::Format(FormatError::Length)));
+        assert_eq!(sync_write(&mut SilentInterface, 30, &[(ids[0], &[0, 0]), (ids[1], &[0, 0])]), Ok(()));
+    }
+
+    #[test]
+    fn test_raw_data_length() {
+        let mut data = [0u8; MAX_DATA_LENGTH + 1];
+        assert_eq!(read_raw(&mut SilentInterface, ServoID::new(1), 0, &mut data), Err(Error::Format(FormatError::Length)));
+        assert_eq!(write_raw(&mut SilentInterface, ServoID::new(1), 0, &data), Err(Error::Format(FormatError::Length)));
+    }
+
+    #[cfg(feature="std")]
+    #[test]
+    fn test_processing_error_debug() {
+        assert_eq!(format!("{:?}", ProcessingError::OVERHEATING | ProcessingError::INSTRUCTION), "ProcessingError(OVERHEATING | INSTRUCTION)");
+    }
+
+    #[cfg(feature="std")]
+    #[test]
+    fn test_error_display() {
+        assert_eq!(FormatError::CRC.to_string(), "checksum mismatch");
+        assert_eq!(ProcessingError::OVERHEATING.to_string(), "overheating");
+        assert_eq!((ProcessingError::INPUT_VOLTAGE | ProcessingError::INSTRUCTION).to_string(), "input voltage out of range, undefined or invalid instruction");
     }
 }
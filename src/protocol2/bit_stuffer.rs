@@ -1,3 +1,4 @@
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub(crate) enum BitStufferError {
     ExpectedFirstHeaderByte,
@@ -52,11 +53,7 @@ impl BitStuffer {
     }
     
     pub fn stuff_next(&self) -> bool {
-        if self.state == BitStufferState::B3 {
-            true
-        } else {
-            false
-        }
+        self.state == BitStufferState::B3
     }
     
     pub fn add_byte(self, byte: u8) -> Result<Self, BitStufferError> {
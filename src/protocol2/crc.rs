@@ -1,3 +1,4 @@
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub(crate) struct CRC(u16);
 
@@ -41,6 +42,7 @@ impl CRC {
         CRC(0)
     }
     
+    #[cfg(test)]
     pub fn calc(data: &[u8]) -> Self {
         let mut crc = CRC::new();
         crc.add(data);
@@ -12,13 +12,16 @@ impl Ping {
 }
 
 impl Instruction for Ping {
-    const PARAMETERS: u16 = 0;
     const INSTRUCTION_VALUE: u8 = 0x01;
 
     fn id(&self) -> PacketID {
         self.id
     }
 
+    fn parameters(&self) -> u16 {
+        0
+    }
+
     fn parameter(&self, _index: usize) -> u8 {
         panic!("No parameters exists for Ping");
     }
@@ -32,15 +35,15 @@ pub(crate) struct Pong {
 }
 
 impl Status for Pong {
-    const PARAMETERS: u16 = 3;
-    
-    fn deserialize(id: ServoID, parameters: &[u8]) -> Self {
-        assert_eq!(parameters.len(), 3);
-        Pong {
+    fn deserialize(id: ServoID, parameters: &[u8]) -> Result<Self, FormatError> {
+        if parameters.len() != 3 {
+            return Err(FormatError::Length);
+        }
+        Ok(Pong {
             id: id,
             model_number: (parameters[0] as u16) | (parameters[1] as u16) << 8,
             fw_version: parameters[2],
-        }
+        })
     }
 }
 
@@ -57,13 +60,16 @@ impl<T: ReadRegister> Read<T> {
 }
 
 impl<T: ReadRegister> Instruction for Read<T> {
-    const PARAMETERS: u16 = 4;
     const INSTRUCTION_VALUE: u8 = 0x02;
 
     fn id(&self) -> PacketID {
         self.id
     }
 
+    fn parameters(&self) -> u16 {
+        4
+    }
+
     fn parameter(&self, index: usize) -> u8 {
         match index {
             0 => T::ADDRESS as u8,
@@ -82,46 +88,103 @@ pub(crate) struct ReadResponse<T: ReadRegister> {
 }
 
 impl<T: ReadRegister> Status for ReadResponse<T> {
-    const PARAMETERS: u16 = T::SIZE;
-
-    fn deserialize(id: ServoID, parameters: &[u8]) -> Self{
-        ReadResponse{
+    fn deserialize(id: ServoID, parameters: &[u8]) -> Result<Self, FormatError> {
+        if parameters.len() != T::SIZE as usize {
+            return Err(FormatError::Length);
+        }
+        Ok(ReadResponse{
             id: id,
             value: T::deserialize(parameters)
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct ReadRaw {
+    id: PacketID,
+    address: u16,
+    length: u16,
+}
+
+impl ReadRaw {
+    pub fn new(id: PacketID, address: u16, length: u16) -> Self {
+        ReadRaw{id: id, address: address, length: length}
+    }
+}
+
+impl Instruction for ReadRaw {
+    const INSTRUCTION_VALUE: u8 = 0x02;
+
+    fn id(&self) -> PacketID {
+        self.id
+    }
+
+    fn parameters(&self) -> u16 {
+        4
+    }
+
+    fn parameter(&self, index: usize) -> u8 {
+        match index {
+            0 => self.address as u8,
+            1 => (self.address >> 8) as u8,
+            2 => self.length as u8,
+            3 => (self.length >> 8) as u8,
+            x => panic!("Read instruction parameter indexed with {}, only 4 parameters exists", x),
         }
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct ReadRawResponse {
+    pub id: ServoID,
+    pub length: usize,
+    pub data: [u8; MAX_DATA_LENGTH],
+}
+
+impl Status for ReadRawResponse {
+    fn deserialize(id: ServoID, parameters: &[u8]) -> Result<Self, FormatError> {
+        let mut data = [0u8; MAX_DATA_LENGTH];
+        data[..parameters.len()].copy_from_slice(parameters);
+        Ok(ReadRawResponse{
+            id: id,
+            length: parameters.len(),
+            data: data,
+        })
+    }
+}
+
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub(crate) struct Write<T: WriteRegister> {
     id: PacketID,
-    data: T,
+    data: [u8; MAX_DATA_LENGTH],
+    phantom: ::lib::marker::PhantomData<T>,
 }
 
 impl<T: WriteRegister> Write<T> {
-    pub fn new(id: PacketID, data: T) -> Self {
-        Write{id: id, data: data}
+    pub fn new(id: PacketID, register: T) -> Self {
+        let mut data = [0u8; MAX_DATA_LENGTH];
+        register.serialize(&mut data[..T::SIZE as usize]);
+        Write{id: id, data: data, phantom: ::lib::marker::PhantomData}
     }
 }
 
 impl<T: WriteRegister> Instruction for Write<T>{
-    const PARAMETERS: u16 = 2 + T::SIZE;
     const INSTRUCTION_VALUE: u8 = 0x03;
 
     fn id(&self) -> PacketID {
         self.id
     }
+
+    fn parameters(&self) -> u16 {
+        2 + T::SIZE
+    }
     
     fn parameter(&self, index: usize) -> u8 {
         match index {
             0 => T::ADDRESS as u8,
             1 => (T::ADDRESS >> 8) as u8,
-            2 => self.data.serialize()[0],
-            3 => self.data.serialize()[1],
-            4 => self.data.serialize()[2],
-            5 => self.data.serialize()[3],
-            x => panic!("Read instruction parameter indexed with {}, only 6 parameters exists", x),
+            x => self.data[x-2],
         }
     }
 }
@@ -131,12 +194,123 @@ pub(crate) struct WriteResponse {
     pub id: ServoID,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct WriteRaw<'a> {
+    id: PacketID,
+    address: u16,
+    data: &'a [u8],
+}
+
+impl<'a> WriteRaw<'a> {
+    pub fn new(id: PacketID, address: u16, data: &'a [u8]) -> Self {
+        WriteRaw{id: id, address: address, data: data}
+    }
+}
+
+impl<'a> Instruction for WriteRaw<'a> {
+    const INSTRUCTION_VALUE: u8 = 0x03;
+
+    fn id(&self) -> PacketID {
+        self.id
+    }
+
+    fn parameters(&self) -> u16 {
+        2 + self.data.len() as u16
+    }
+
+    fn parameter(&self, index: usize) -> u8 {
+        match index {
+            0 => self.address as u8,
+            1 => (self.address >> 8) as u8,
+            x => self.data[x-2],
+        }
+    }
+}
+
+/// Writes the same registers of several servos, the servos do not answer it
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct SyncWrite<'a> {
+    address: u16,
+    length: u16,
+    data: &'a [(ServoID, &'a [u8])],
+}
+
+impl<'a> SyncWrite<'a> {
+    pub fn new(address: u16, length: u16, data: &'a [(ServoID, &'a [u8])]) -> Self {
+        SyncWrite{address: address, length: length, data: data}
+    }
+}
+
+impl<'a> Instruction for SyncWrite<'a> {
+    const INSTRUCTION_VALUE: u8 = 0x83;
+
+    fn id(&self) -> PacketID {
+        PacketID::broadcast()
+    }
+
+    fn parameters(&self) -> u16 {
+        4 + self.data.len() as u16 * (1 + self.length)
+    }
+
+    fn parameter(&self, index: usize) -> u8 {
+        match index {
+            0 => self.address as u8,
+            1 => (self.address >> 8) as u8,
+            2 => self.length as u8,
+            3 => (self.length >> 8) as u8,
+            x => {
+                let (id, data) = self.data[(x-4) / (1 + self.length as usize)];
+                match (x-4) % (1 + self.length as usize) {
+                    0 => u8::from(id),
+                    offset => data[offset-1],
+                }
+            },
+        }
+    }
+}
+
+/// Reads the same registers of several servos, they answer one after another in the order of `ids`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct SyncRead<'a> {
+    address: u16,
+    length: u16,
+    ids: &'a [ServoID],
+}
+
+impl<'a> SyncRead<'a> {
+    pub fn new(address: u16, length: u16, ids: &'a [ServoID]) -> Self {
+        SyncRead{address: address, length: length, ids: ids}
+    }
+}
+
+impl<'a> Instruction for SyncRead<'a> {
+    const INSTRUCTION_VALUE: u8 = 0x82;
+
+    fn id(&self) -> PacketID {
+        PacketID::broadcast()
+    }
+
+    fn parameters(&self) -> u16 {
+        4 + self.ids.len() as u16
+    }
+
+    fn parameter(&self, index: usize) -> u8 {
+        match index {
+            0 => self.address as u8,
+            1 => (self.address >> 8) as u8,
+            2 => self.length as u8,
+            3 => (self.length >> 8) as u8,
+            x => u8::from(self.ids[x-4]),
+        }
+    }
+}
+
 impl Status for WriteResponse {
-    const PARAMETERS: u16 = 0;
-    
-    fn deserialize(id: ServoID, parameters: &[u8]) -> Self {
-        assert_eq!(parameters.len(), 0);
-        WriteResponse {id: id}
+    fn deserialize(id: ServoID, parameters: &[u8]) -> Result<Self, FormatError> {
+        if !parameters.is_empty() {
+            return Err(FormatError::Length);
+        }
+        Ok(WriteResponse {id: id})
     }
 }
 
@@ -173,7 +347,7 @@ mod tests {
         assert_eq!(deserializer.remaining_bytes(), 5);
         assert_eq!(deserializer.deserialize(&[0x06, 0x04, 0x026, 0x65, 0x5d]), Ok(DeserializationStatus::Finished));
         
-        assert_eq!(deserializer.build(),
+        assert_eq!(deserializer.build().map(|r| r.status),
                    Ok(Pong{
                        id: ServoID::new(0x01),
                        model_number: 0x0406,
@@ -197,7 +371,7 @@ mod tests {
         assert_eq!(deserializer.deserialize(&[0x32]), Ok(DeserializationStatus::Finished));        
         assert!(deserializer.is_finished());
         
-        assert_eq!(deserializer.build(),
+        assert_eq!(deserializer.build().map(|r| r.status),
                    Ok(Pong{
                        id: ServoID::new(0x01),
                        model_number: 0xa918,
@@ -244,12 +418,36 @@ mod tests {
 
         assert!(deserializer.is_finished());
         
-        assert_eq!(deserializer.build(),
+        assert_eq!(deserializer.build().map(|r| r.status),
                    Ok(WriteResponse{id: ServoID::new(0x01)})
         );
 
     }
 
+    #[test]
+    fn test_write_response_alert() {
+        let header = [0xff, 0xff, 0xfd, 0x00, 0x01, 0x04, 0x00, 0x55, 0x80];
+        let mut crc = ::protocol2::crc::CRC::new();
+        crc.add(&header);
+
+        let mut deserializer = Deserializer::<WriteResponse>::new()
+            .deserialize_header(header)
+            .unwrap();
+        assert_eq!(deserializer.deserialize(&[u16::from(crc) as u8, (u16::from(crc) >> 8) as u8]), Ok(DeserializationStatus::Finished));
+
+        let response = deserializer.build().unwrap();
+        assert!(response.alert);
+        assert_eq!(response.status, WriteResponse{id: ServoID::new(0x01)});
+    }
+
+    #[test]
+    fn test_malformed_header() {
+        assert_eq!(Deserializer::<WriteResponse>::new().deserialize_header([0xff, 0xff, 0xfd, 0x00, 0xfe, 0x07, 0x00, 0x55, 0x00]).err(), Some(FormatError::ID));
+        assert_eq!(Deserializer::<WriteResponse>::new().deserialize_header([0xff, 0xff, 0xfd, 0x00, 0xff, 0x04, 0x00, 0x55, 0x00]).err(), Some(FormatError::ID));
+        assert_eq!(Deserializer::<WriteResponse>::new().deserialize_header([0xff, 0xff, 0xfd, 0x00, 0x01, 0x01, 0x00, 0x55, 0x00]).err(), Some(FormatError::Length));
+        assert_eq!(Deserializer::<WriteResponse>::new().deserialize_header([0xff, 0xff, 0xfd, 0x00, 0x01, 0x03, 0x00, 0x55, 0x00]).err(), Some(FormatError::Length));
+    }
+
     #[test]
     fn test_write_response_mixed() {
         let mut deserializer = Deserializer::<WriteResponse>::new()
@@ -263,7 +461,7 @@ mod tests {
         assert_eq!(deserializer.deserialize(&[0x0c]), Ok(DeserializationStatus::Finished));        
         assert!(deserializer.is_finished());
         
-        assert_eq!(deserializer.build(),
+        assert_eq!(deserializer.build().map(|r| r.status),
                    Ok(WriteResponse{id: ServoID::new(0x01)})
         );
 
@@ -283,6 +481,126 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_write_raw() {
+        let mut array = [0u8; 16];
+        let write = WriteRaw::new(PacketID::unicast(1), 596, &[0xcd, 0xab, 0x00, 0x00]);
+        for (i, b) in write.serialize().enumerate() {
+            array[i] = b;
+        }
+        assert_eq!(
+            array,
+            [0xff, 0xff, 0xfd, 0x00, 0x01, 0x09, 0x00, 0x03, 0x54, 0x02, 0xcd, 0xab, 0x00, 0x00, 0x0d, 0xe5]
+        );
+    }
+
+    #[test]
+    fn test_sync_write() {
+        let data: &[(ServoID, &[u8])] = &[(ServoID::new(1), &[0x96, 0x00, 0x00, 0x00]), (ServoID::new(2), &[0xaa, 0x00, 0x00, 0x00])];
+        let mut array = [0u8; 24];
+        for (i, b) in SyncWrite::new(116, 4, data).serialize().enumerate() {
+            array[i] = b;
+        }
+        assert_eq!(
+            array,
+            [0xff, 0xff, 0xfd, 0x00, 0xfe, 0x11, 0x00, 0x83, 0x74, 0x00, 0x04, 0x00,
+                 0x01, 0x96, 0x00, 0x00, 0x00, 0x02, 0xaa, 0x00, 0x00, 0x00, 0x82, 0x87]
+        );
+    }
+
+    #[test]
+    fn test_sync_read() {
+        let mut array = [0u8; 16];
+        for (i, b) in SyncRead::new(132, 4, &[ServoID::new(1), ServoID::new(2)]).serialize().enumerate() {
+            array[i] = b;
+        }
+        assert_eq!(
+            array,
+            [0xff, 0xff, 0xfd, 0x00, 0xfe, 0x09, 0x00, 0x82, 0x84, 0x00, 0x04, 0x00, 0x01, 0x02, 0xce, 0xfa]
+        );
+    }
+
+    #[test]
+    fn test_read_raw() {
+        let mut array = [0u8; 14];
+        let read = ReadRaw::new(PacketID::unicast(1), 611, 4);
+        for (i, b) in read.serialize().enumerate() {
+            array[i] = b;
+        }
+        assert_eq!(
+            array,
+            [0xff, 0xff, 0xfd, 0x00, 0x01, 0x07, 0x00, 0x02, 611u16 as u8, (611u16 >> 8) as u8, 0x04, 0x00, 27, 249]
+        );
+    }
+
+    #[test]
+    fn test_read_raw_response() {
+        let mut deserializer = Deserializer::<ReadRawResponse>::new()
+            .deserialize_header([0xff, 0xff, 0xfd, 0x00, 0x01, 0x08, 0x00, 0x55, 0x00])
+            .unwrap();
+
+        assert_eq!(deserializer.deserialize(&[0xa6, 0x00, 0x00, 0x00, 0x8c, 0xc0]), Ok(DeserializationStatus::Finished));
+
+        let response = deserializer.build().unwrap().status;
+        assert_eq!(response.length, 4);
+        assert_eq!(&response.data[..response.length], &[0xa6, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_read_response_wrong_length() {
+        let mut deserializer = Deserializer::<ReadResponse<::pro::control_table::PresentCurrent>>::new()
+            .deserialize_header([0xff, 0xff, 0xfd, 0x00, 0x01, 0x08, 0x00, 0x55, 0x00])
+            .unwrap();
+
+        assert_eq!(deserializer.deserialize(&[0xa6, 0x00, 0x00, 0x00, 0x8c, 0xc0]), Ok(DeserializationStatus::Finished));
+        assert_eq!(deserializer.build(), Err(Error::Format(FormatError::Length)));
+    }
+
+    #[test]
+    fn test_write_block() {
+        let mut array = [0u8; 15];
+        let led = ::pro::control_table::LedColor{
+            red: ::pro::control_table::LedRed::new(0x10),
+            green: ::pro::control_table::LedGreen::new(0x20),
+            blue: ::pro::control_table::LedBlue::new(0x30),
+        };
+        let write = Write::new(PacketID::unicast(1), led);
+        for (i, b) in write.serialize().enumerate() {
+            array[i] = b;
+        }
+        let mut expected = [0u8; 15];
+        for (i, b) in WriteRaw::new(PacketID::unicast(1), 563, &[0x10, 0x20, 0x30]).serialize().enumerate() {
+            expected[i] = b;
+        }
+        assert_eq!(array, expected);
+    }
+
+    #[test]
+    fn test_read_block() {
+        use pro::control_table::*;
+        
+        assert_eq!(<PresentState as ::protocol2::Register>::ADDRESS, 611);
+        assert_eq!(<PresentState as ::protocol2::Register>::SIZE, 15);
+        
+        let mut deserializer = Deserializer::<ReadResponse<PresentState>>::new()
+            .deserialize_header([0xff, 0xff, 0xfd, 0x00, 0x01, 19, 0x00, 0x55, 0x00])
+            .unwrap();
+        let parameters = [0xa6, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x10, 0x00, 0xf0, 0x00, 40];
+        let mut crc = ::protocol2::crc::CRC::new();
+        crc.add(&[0xff, 0xff, 0xfd, 0x00, 0x01, 19, 0x00, 0x55, 0x00]);
+        crc.add(&parameters);
+        
+        deserializer.deserialize(&parameters).unwrap();
+        assert_eq!(deserializer.deserialize(&[u16::from(crc) as u8, (u16::from(crc) >> 8) as u8]), Ok(DeserializationStatus::Finished));
+        
+        let state = deserializer.build().unwrap().status.value;
+        assert_eq!(i32::from(state.present_position), 0xa6);
+        assert_eq!(i32::from(state.present_velocity), -1);
+        assert_eq!(i16::from(state.present_current), 0x10);
+        assert_eq!(u16::from(state.present_input_voltage), 0xf0);
+        assert_eq!(u8::from(state.present_temperature), 40);
+    }
+
     #[test]
     fn test_read_response_slice() {
         let mut deserializer = Deserializer::<ReadResponse<::pro::control_table::GoalPosition>>::new()
@@ -291,7 +609,7 @@ mod tests {
 
         assert_eq!(deserializer.deserialize(&[0xa6, 0x00, 0x00, 0x00, 0x8c, 0xc0]), Ok(DeserializationStatus::Finished));
         
-        assert_eq!(deserializer.build(),
+        assert_eq!(deserializer.build().map(|r| r.status),
                    Ok(ReadResponse{
                        value: ::pro::control_table::GoalPosition::new(0x000000a6),
                        id: ServoID::new(0x01),
@@ -312,7 +630,7 @@ mod tests {
 
         assert!(deserializer.is_finished());
         
-        assert_eq!(deserializer.build(),
+        assert_eq!(deserializer.build().map(|r| r.status),
                    Ok(ReadResponse{
                        value: ::pro::control_table::GoalPosition::new(0x000000a6),
                        id: ServoID::new(0x01),
@@ -6,49 +6,227 @@
 pub(crate) mod instruction;
 #[macro_use]
 mod control_table;
-mod crc;
+pub(crate) mod crc;
 mod bit_stuffer;
 
+#[cfg(feature="std")]
 use Interface;
+#[cfg(feature="std")]
 use BaudRate;
+#[cfg(feature="std")]
+use Servo;
 use CommunicationError;
+use InterfaceExt;
+use Reply;
 
 use bit_field::BitField;
 use self::bit_stuffer::BitStuffer;
 
 /// Write the instruction on the interface
-pub(crate) fn write_instruction<I: ::Interface, T: Instruction>(interface: &mut I, instruction: T) -> Result<(), CommunicationError> {
-    for b in instruction.serialize() {
-        interface.write(&[b])?
-    }
-    Ok(())
+///
+/// The instruction is written at once, so a half-duplex interface only switches direction once for the whole packet.
+pub(crate) fn write_instruction<I: ::Interface, T: Instruction>(interface: &mut I, instruction: &T) -> Result<(), CommunicationError> {
+    interface.transmit(packet(instruction).as_slice())
+}
+
+/// Returns `true` if the serialized instruction, with byte stuffing, fits in a `transaction::Packet`
+fn fits_packet<T: Instruction>(instruction: &T) -> bool {
+    instruction.serialize().count() <= ::transaction::MAX_PACKET_LENGTH
+}
+
+/// Serialize the instruction into a packet for a `Transaction`
+pub(crate) fn packet<T: Instruction>(instruction: &T) -> ::transaction::Packet {
+    ::transaction::Packet::from_bytes(instruction.serialize())
 }
 
 /// Read a status from the interface
 ///
 /// If no instructions have been sent, there will not be any status to read
-pub(crate) fn read_status<I: ::Interface, T: Status>(interface: &mut I) -> Result<T, Error> {
-    let mut header = [0u8; 9];
-    interface.read(&mut header)?;
-    
-    let mut deserializer = Deserializer::<T>::new()
-        .deserialize_header(header)?;
+pub(crate) fn read_status<I: ::Interface, T: Status>(interface: &mut I) -> Result<Response<T>, Error> {
+    let mut decoder = StatusDecoder::<T>::new();
+    let mut data = [0u8; 10];
+    while decoder.wanted() > 0 {
+        let wanted = ::lib::cmp::min(decoder.wanted(), data.len());
+        interface.receive(&mut data[..wanted])?;
+        decoder.feed(&data[..wanted])?;
+    }
+    decoder.finish()
+}
 
-    let mut body = [0u8; 10];
+/// Incremental decoding of a status packet
+///
+/// Received bytes are fed as they arrive, the header is validated as soon as it is complete
+/// and the body is deserialized by the `BodyDeserializer`.
+#[derive(Debug, Clone)]
+pub(crate) struct StatusDecoder<T: Status> {
+    header: [u8; 9],
+    received: usize,
+    body: Option<BodyDeserializer<T>>,
+}
 
-    loop {
-        let remaining_bytes = deserializer.remaining_bytes() as usize;
-        if remaining_bytes > 10 {
-            interface.read(&mut body)?;
-            deserializer.deserialize(&body)?;
-        } else {
-            interface.read(&mut body[..remaining_bytes])?;
-            deserializer.deserialize(&body[..remaining_bytes])?;
-            break;
+impl<T: Status> StatusDecoder<T> {
+    pub fn new() -> Self {
+        StatusDecoder {
+            header: [0u8; 9],
+            received: 0,
+            body: None,
+        }
+    }
+
+    /// The number of bytes still missing from the packet, as far as it is known yet
+    pub fn wanted(&self) -> usize {
+        match self.body {
+            None => self.header.len() - self.received,
+            Some(ref body) => body.remaining_bytes() as usize,
+        }
+    }
+
+    /// Feed received bytes, at most `wanted()` of them
+    pub fn feed(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.len() > self.wanted() {
+            return Err(Error::Format(FormatError::Length));
+        }
+        match self.body {
+            None => {
+                self.header[self.received..self.received + data.len()].copy_from_slice(data);
+                self.received += data.len();
+                if self.received == self.header.len() {
+                    self.body = Some(Deserializer::new().deserialize_header(self.header)?);
+                }
+            },
+            Some(ref mut body) => {
+                body.deserialize(data)?;
+            },
         }
+        Ok(())
+    }
+
+    /// Deserialize the complete packet
+    pub fn finish(self) -> Result<Response<T>, Error> {
+        match self.body {
+            Some(body) => body.build(),
+            None => Err(Error::Unfinished),
+        }
+    }
+}
+
+/// Read the status following an instruction, as far as `reply` expects one
+///
+/// Returns `Ok(None)` if no status was expected, or an optional status did not arrive.
+pub(crate) fn read_reply<I: ::Interface, T: Status>(interface: &mut I, reply: Reply) -> Result<Option<Response<T>>, Error> {
+    match reply {
+        Reply::Never => Ok(None),
+        Reply::Maybe => match read_status(interface) {
+            Err(Error::Communication(CommunicationError::TimedOut)) => Ok(None),
+            response => response.map(Some),
+        },
+        Reply::Always => read_status(interface).map(Some),
+    }
+}
+
+/// Read `data.len()` bytes, starting at `address`, from the servo with the given `id`
+///
+/// This bypasses the typed registers and is useful for accessing registers not yet modeled by this crate.
+/// The interface must already be configured with the baud rate of the servo.
+///
+/// Returns the alert flag of the status packet.
+/// Returns a `FormatError::Length` if `data.len()` is larger than `MAX_DATA_LENGTH`.
+pub fn read_raw<I: ::Interface>(interface: &mut I, id: ServoID, address: u16, data: &mut [u8]) -> Result<bool, Error> {
+    if data.len() > MAX_DATA_LENGTH {
+        return Err(Error::Format(FormatError::Length));
     }
     
-    Ok(deserializer.build()?)
+    let read = instruction::ReadRaw::new(PacketID::from(id), address, data.len() as u16);
+    write_instruction(interface, &read)?;
+    let response = read_status::<I, instruction::ReadRawResponse>(interface)?;
+    if response.status.length != data.len() {
+        return Err(Error::Format(FormatError::Length));
+    }
+    data.copy_from_slice(&response.status.data[..response.status.length]);
+    Ok(response.alert)
+}
+
+/// Write `data` to the servo with the given `id`, starting at `address`
+///
+/// This bypasses the typed registers and is useful for accessing registers not yet modeled by this crate.
+/// The interface must already be configured with the baud rate of the servo, and the servo must answer writes.
+///
+/// Returns the alert flag of the status packet.
+/// Returns a `FormatError::Length` if `data.len()` is larger than `MAX_DATA_LENGTH`.
+pub fn write_raw<I: ::Interface>(interface: &mut I, id: ServoID, address: u16, data: &[u8]) -> Result<bool, Error> {
+    Ok(write_raw_with_reply(interface, id, address, data, Reply::Always)?.unwrap_or(false))
+}
+
+/// Like `write_raw`, for a servo that returns the status to the write as described by `reply`
+///
+/// Returns the alert flag of the status packet, if one was received.
+pub(crate) fn write_raw_with_reply<I: ::Interface>(interface: &mut I, id: ServoID, address: u16, data: &[u8], reply: Reply) -> Result<Option<bool>, Error> {
+    if data.len() > MAX_DATA_LENGTH {
+        return Err(Error::Format(FormatError::Length));
+    }
+    
+    let write = instruction::WriteRaw::new(PacketID::from(id), address, data);
+    write_instruction(interface, &write)?;
+    Ok(read_reply::<I, instruction::WriteResponse>(interface, reply)?.map(|response| response.alert))
+}
+
+/// Write to several servos with one instruction, starting at `address`
+///
+/// Every entry gives the ID of a servo and the bytes to write to it. The servos do not answer a sync write.
+/// The interface must already be configured with the baud rate of the servos, it is flushed before the instruction is sent.
+/// Returns a `FormatError::Length` if the entries are not of the same length, if any is larger than `MAX_DATA_LENGTH`,
+/// or if the instruction is too long to send.
+pub fn sync_write<I: ::Interface>(interface: &mut I, address: u16, data: &[(ServoID, &[u8])]) -> Result<(), Error> {
+    let length = data.first().map_or(0, |&(_, data)| data.len());
+    if length > MAX_DATA_LENGTH || !data.iter().all(|&(_, data)| data.len() == length) {
+        return Err(Error::Format(FormatError::Length));
+    }
+
+    let write = instruction::SyncWrite::new(address, length as u16, data);
+    if !fits_packet(&write) {
+        return Err(Error::Format(FormatError::Length));
+    }
+    interface.flush();
+    write_instruction(interface, &write)?;
+    Ok(())
+}
+
+/// Read from several servos with one instruction, starting at `address`
+///
+/// `data` is split in one chunk of equal length for every servo in `ids`, and the chunks are filled in the same order.
+/// The interface must already be configured with the baud rate of the servos, it is flushed before the instruction is sent.
+///
+/// Returns true if any of the status packets had the alert flag set.
+/// Returns a `FormatError::Length` if `data` can not be split in equal chunks, if the chunks are larger than `MAX_DATA_LENGTH`,
+/// or if the instruction is too long to send.
+pub fn sync_read<I: ::Interface>(interface: &mut I, address: u16, ids: &[ServoID], data: &mut [u8]) -> Result<bool, Error> {
+    if data.is_empty() {
+        return Ok(false);
+    }
+    let length = data.len() / ids.len().max(1);
+    if length * ids.len() != data.len() || length > MAX_DATA_LENGTH {
+        return Err(Error::Format(FormatError::Length));
+    }
+
+    let read = instruction::SyncRead::new(address, length as u16, ids);
+    if !fits_packet(&read) {
+        return Err(Error::Format(FormatError::Length));
+    }
+    interface.flush();
+    write_instruction(interface, &read)?;
+    let mut alert = false;
+    for (id, chunk) in ids.iter().zip(data.chunks_mut(length)) {
+        let response = read_status::<I, instruction::ReadRawResponse>(interface)?;
+        if response.status.id != *id {
+            return Err(Error::Format(FormatError::ID));
+        }
+        if response.status.length != length {
+            return Err(Error::Format(FormatError::Length));
+        }
+        chunk.copy_from_slice(&response.status.data[..length]);
+        alert |= response.alert;
+    }
+    Ok(alert)
 }
 
 /// Enumerate all protocol 2 servos connected to the interface
@@ -57,19 +235,20 @@ pub(crate) fn read_status<I: ::Interface, T: Status>(interface: &mut I) -> Resul
 #[cfg(feature="std")]
 pub fn enumerate<I: ::Interface>(interface: &mut I) -> Result<Vec<ServoInfo>, CommunicationError> {
     let mut servos = Vec::new();
+    interface.set_timeout(::DEFAULT_TIMEOUT).map_err(::communication_error)?;
 
     for b in BaudRate::variants() {
 
-        if let Err(_) = interface.set_baud_rate(*b) {
+        if interface.set_baud_rate(*b).is_err() {
             warn!(target: "protocol2", "not able to enumerate devices on baudrate: {}", u32::from(*b));
         }
 
         interface.flush();
         let ping = ::protocol2::instruction::Ping::new(::protocol2::PacketID::Broadcast);
-        write_instruction(interface, ping)?;
+        write_instruction(interface, &ping)?;
 
         loop {
-            match read_status::<I, instruction::Pong>(interface) {
+            match read_status::<I, instruction::Pong>(interface).map(|r| r.status) {
                 Ok(pong) => servos.push(
                     ServoInfo{
                         baud_rate: *b,
@@ -89,22 +268,50 @@ pub fn enumerate<I: ::Interface>(interface: &mut I) -> Result<Vec<ServoInfo>, Co
     Ok(servos)
 }
 
+/// Ping the servo with the given `id`, `None` if no servo answers
+///
+/// The interface must already be configured with the baud rate and the timeout for the probe.
+#[cfg(feature="std")]
+pub(crate) fn probe<I: ::Interface>(interface: &mut I, id: ServoID, baud_rate: BaudRate) -> Result<Option<ServoInfo>, CommunicationError> {
+    interface.flush();
+    write_instruction(interface, &instruction::Ping::new(PacketID::from(id)))?;
+    match read_status::<I, instruction::Pong>(interface).map(|r| r.status) {
+        Ok(pong) if pong.id == id => Ok(Some(ServoInfo{
+            baud_rate: baud_rate,
+            model_number: pong.model_number,
+            fw_version: pong.fw_version,
+            id: pong.id,
+        })),
+        Err(Error::Communication(CommunicationError::TimedOut)) => Ok(None),
+        Err(Error::Communication(e)) => Err(e),
+        response => {
+            warn!(target: "protocol2", "received: {:?} when pinging id: {} on baud: {}", response, u8::from(id), u32::from(baud_rate));
+            Ok(None)
+        },
+    }
+}
+
 /// Connect genericly to a protocol 2 servo
 ///
 /// Only offers basic functionality. If you need more functionality use the connect method of the correct servo type instead.
 #[cfg(feature="std")]
-pub fn connect<I: Interface + 'static>(_interface: &mut I, info: ServoInfo) -> Result<Box<::Servo<I>>, CommunicationError>{
+pub fn connect<I: Interface + 'static>(_interface: &mut I, info: ServoInfo) -> Result<Box<dyn Servo<I>>, ::Error>{
     match info.model_number {
         ::pro::M4210S260R::<I>::MODEL_NUMBER => Ok(Box::new(::pro::M4210S260R::<I>::new(info.id, info.baud_rate))),
-        _ => unimplemented!(),
+        _ => Ok(Box::new(::dynamic::DynamicServo::new(::ServoInfo::Protocol2(info))?)),
     }
 }
 
 macro_rules! protocol2_servo {
-    ($name:ident, $write:path, $read:path, $model_number:expr) => {
+    ($name:ident, $write:path, $read:path, $model_number:expr, $status_return_level:path, $hardware_error_status:path) => {
         pub struct $name<I: ::Interface> {
             id: ::protocol2::ServoID,
             baudrate: ::BaudRate,
+            timeout: ::lib::time::Duration,
+            alert: bool,
+            status_return_level: ::StatusReturnLevel,
+            retry_policy: ::retry::RetryPolicy,
+            retry_stats: ::retry::RetryStats,
             interface: ::lib::marker::PhantomData<I>,
         }
 
@@ -116,18 +323,129 @@ macro_rules! protocol2_servo {
                 $name{
                     id: id,
                     baudrate: baudrate,
+                    timeout: ::DEFAULT_TIMEOUT,
+                    alert: false,
+                    status_return_level: ::StatusReturnLevel::default(),
+                    retry_policy: ::retry::RetryPolicy::default(),
+                    retry_stats: ::retry::RetryStats::default(),
                     interface: ::lib::marker::PhantomData{},
                 }
             }
+
+            /// Set the time to wait for a status from the servo.
+            ///
+            /// Defaults to `DEFAULT_TIMEOUT`. Use short timeouts for fast polling and longer ones for e.g. EEPROM writes.
+            pub fn set_timeout(&mut self, timeout: ::lib::time::Duration) {
+                self.timeout = timeout;
+            }
+
+            pub fn timeout(&self) -> ::lib::time::Duration {
+                self.timeout
+            }
+
+            /// The status return level the servo is assumed to have
+            pub fn status_return_level(&self) -> ::StatusReturnLevel {
+                self.status_return_level
+            }
+
+            /// Configure the status return level the servo has, without communicating with it.
+            ///
+            /// Writes only wait for a status when the level is `All`.
+            /// Reads fail with `Error::NotAnswered` right away when the servo only answers pings, except `read_status_return_level`.
+            pub fn assume_status_return_level(&mut self, status_return_level: ::StatusReturnLevel) {
+                self.status_return_level = status_return_level;
+            }
+
+            /// Read the status return level from the servo and use it from now on.
+            pub fn read_status_return_level(&mut self, interface: &mut I) -> Result<::StatusReturnLevel, ::protocol2::Error> {
+                let address = <$status_return_level as ::protocol2::Register>::ADDRESS;
+                let mut data = [0u8];
+                // Sent whatever level is assumed, the assumption may be wrong
+                self.read_raw_unchecked(interface, address, &mut data)?;
+                self.status_return_level = ::StatusReturnLevel::from(data[0]);
+                Ok(self.status_return_level)
+            }
+
+            /// Change the status return level of the servo.
+            ///
+            /// A missing status for the write is accepted when either the old or the new level does not answer writes.
+            /// If the new level answers reads, the level is read back from the servo.
+            pub fn set_status_return_level(&mut self, interface: &mut I, status_return_level: ::StatusReturnLevel) -> Result<(), ::protocol2::Error> {
+                let (id, baudrate, timeout, address) = (self.id, self.baudrate, self.timeout, <$status_return_level as ::protocol2::Register>::ADDRESS);
+                let reply = self.status_return_level.change_reply(status_return_level);
+                let alert = self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol2::write_raw_with_reply(interface, id, address, &[u8::from(status_return_level)], reply)
+                })?;
+                self.alert = alert.unwrap_or(self.alert);
+                self.status_return_level = status_return_level;
+                if status_return_level.replies_to_read() {
+                    self.read_status_return_level(interface)?;
+                }
+                Ok(())
+            }
+
+            /// Set the policy used for retrying failed transactions with this servo.
+            pub fn set_retry_policy(&mut self, retry_policy: ::retry::RetryPolicy) {
+                self.retry_policy = retry_policy;
+            }
+
+            pub fn retry_policy(&self) -> ::retry::RetryPolicy {
+                self.retry_policy
+            }
+
+            /// Retry counters for the transactions with this servo
+            pub fn retry_stats(&self) -> ::retry::RetryStats {
+                self.retry_stats
+            }
+
+            pub fn reset_retry_stats(&mut self) {
+                self.retry_stats = ::retry::RetryStats::default();
+            }
+
+            /// The alert flag of the last status received from the servo
+            ///
+            /// Writes are not answered below `StatusReturnLevel::All`, and leave the flag as it was.
+            ///
+            /// The servo sets the alert flag when a hardware error has occurred, `hardware_error` tells which.
+            pub fn alert(&self) -> bool {
+                self.alert
+            }
+
+            /// Read the Hardware Error Status register, explaining why the alert flag is set.
+            pub fn hardware_error(&mut self, interface: &mut I) -> Result<::protocol2::HardwareError, ::protocol2::Error> {
+                // The error bits are all in the first byte
+                let mut data = [0u8];
+                self.read_raw(interface, <$hardware_error_status as ::protocol2::Register>::ADDRESS, &mut data)?;
+                Ok(::protocol2::HardwareError::from(data[0]))
+            }
             
+            /// Fail a read right away if the servo does not answer it, rather than waiting for the timeout
+            fn check_replies_to_read(&self) -> Result<(), ::protocol2::Error> {
+                if self.status_return_level.replies_to_read() {
+                    Ok(())
+                } else {
+                    Err(::protocol2::Error::NotAnswered)
+                }
+            }
+
+            /// Convert a protocol error into an `Error` with the ID of this servo and the failed instruction
+            fn context(&self, instruction: ::Instruction) -> impl Fn(::protocol2::Error) -> ::Error {
+                let id = u8::from(self.id);
+                move |e| ::Error::from(e).with_id(id).with_instruction(instruction)
+            }
+
             /// Ping the servo, returning `Ok(ServoInfo)` if it exists.
             pub fn ping(&mut self, interface: &mut I) -> Result<::protocol2::ServoInfo, ::protocol2::Error> {
-                interface.set_baud_rate(self.baudrate)?;
-                interface.flush();
-                
-                let ping = ::protocol2::instruction::Ping::new(::protocol2::PacketID::from(self.id));
-                ::protocol2::write_instruction(interface, ping)?;
-                let pong = ::protocol2::read_status::<I, ::protocol2::instruction::Pong>(interface)?;
+                let (id, baudrate, timeout) = (self.id, self.baudrate, self.timeout);
+                let ping = ::protocol2::instruction::Ping::new(::protocol2::PacketID::from(id));
+                let response = self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol2::write_instruction(interface, &ping)?;
+                    ::protocol2::read_status::<I, ::protocol2::instruction::Pong>(interface)
+                })?;
+                self.alert = response.alert;
+                let pong = response.status;
                 Ok(
                     ::protocol2::ServoInfo{
                         baud_rate: self.baudrate,
@@ -140,52 +458,137 @@ macro_rules! protocol2_servo {
 
             /// Write the given data `register` to the servo.
             pub fn write<W: $write>(&mut self, interface: &mut I, register: W) -> Result<(), ::protocol2::Error> {
-                interface.set_baud_rate(self.baudrate)?;
-                let write = ::protocol2::instruction::Write::new(::protocol2::PacketID::from(self.id), register);
-                ::protocol2::write_instruction(interface, write)?;
-                ::protocol2::read_status::<I, ::protocol2::instruction::WriteResponse>(interface)?;
+                let (id, baudrate, timeout) = (self.id, self.baudrate, self.timeout);
+                let write = ::protocol2::instruction::Write::new(::protocol2::PacketID::from(id), register);
+                let reply = self.status_return_level.write_reply();
+                let response = self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol2::write_instruction(interface, &write)?;
+                    ::protocol2::read_reply::<I, ::protocol2::instruction::WriteResponse>(interface, reply)
+                })?;
+                if let Some(response) = response {
+                    self.alert = response.alert;
+                }
                 Ok(())
             }
 
             /// Read data from a register
             pub fn read<R: $read>(&mut self, interface: &mut I) -> Result<R, ::protocol2::Error> {
-                interface.set_baud_rate(self.baudrate)?;
-                interface.flush();
-                
-                let read = ::protocol2::instruction::Read::<R>::new(::protocol2::PacketID::from(self.id));
-                ::protocol2::write_instruction(interface, read)?;
-                Ok(::protocol2::read_status::<I, ::protocol2::instruction::ReadResponse<R>>(interface)?.value)
+                self.check_replies_to_read()?;
+                let (id, baudrate, timeout) = (self.id, self.baudrate, self.timeout);
+                let read = ::protocol2::instruction::Read::<R>::new(::protocol2::PacketID::from(id));
+                let response = self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol2::write_instruction(interface, &read)?;
+                    ::protocol2::read_status::<I, ::protocol2::instruction::ReadResponse<R>>(interface)
+                })?;
+                self.alert = response.alert;
+                Ok(response.status.value)
+            }
+
+            /// Read `data.len()` bytes starting at `address`, bypassing the typed registers.
+            pub fn read_raw(&mut self, interface: &mut I, address: u16, data: &mut [u8]) -> Result<(), ::protocol2::Error> {
+                self.check_replies_to_read()?;
+                self.read_raw_unchecked(interface, address, data)
+            }
+
+            fn read_raw_unchecked(&mut self, interface: &mut I, address: u16, data: &mut [u8]) -> Result<(), ::protocol2::Error> {
+                let (id, baudrate, timeout) = (self.id, self.baudrate, self.timeout);
+                self.alert = self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol2::read_raw(interface, id, address, &mut *data)
+                })?;
+                Ok(())
+            }
+
+            /// Write `data` starting at `address`, bypassing the typed registers.
+            pub fn write_raw(&mut self, interface: &mut I, address: u16, data: &[u8]) -> Result<(), ::protocol2::Error> {
+                let (id, baudrate, timeout) = (self.id, self.baudrate, self.timeout);
+                let reply = self.status_return_level.write_reply();
+                let alert = self.retry_policy.run(&mut self.retry_stats, || {
+                    ::InterfaceExt::begin_transaction(interface, baudrate, timeout)?;
+                    ::protocol2::write_raw_with_reply(interface, id, address, data, reply)
+                })?;
+                self.alert = alert.unwrap_or(self.alert);
+                Ok(())
+            }
+        }
+
+        impl<'a, I: ::Interface> ::bus::Handle<'a, I, $name<::bus::Bus<I>>> {
+            /// See `read_status_return_level` of the servo.
+            pub fn read_status_return_level(&mut self) -> Result<::StatusReturnLevel, ::protocol2::Error> {
+                self.servo.read_status_return_level(self.bus)
+            }
+
+            /// See `set_status_return_level` of the servo.
+            pub fn set_status_return_level(&mut self, status_return_level: ::StatusReturnLevel) -> Result<(), ::protocol2::Error> {
+                self.servo.set_status_return_level(self.bus, status_return_level)
+            }
+
+            /// Read the Hardware Error Status register, explaining why the alert flag is set.
+            pub fn hardware_error(&mut self) -> Result<::protocol2::HardwareError, ::protocol2::Error> {
+                self.servo.hardware_error(self.bus)
+            }
+
+            /// Ping the servo, returning `Ok(ServoInfo)` if it exists.
+            pub fn ping(&mut self) -> Result<::protocol2::ServoInfo, ::protocol2::Error> {
+                self.servo.ping(self.bus)
+            }
+
+            /// Write the given data `register` to the servo.
+            pub fn write<W: $write>(&mut self, register: W) -> Result<(), ::protocol2::Error> {
+                self.servo.write(self.bus, register)
+            }
+
+            /// Read data from a register
+            pub fn read<R: $read>(&mut self) -> Result<R, ::protocol2::Error> {
+                self.servo.read(self.bus)
+            }
+
+            /// Read `data.len()` bytes starting at `address`, bypassing the typed registers.
+            pub fn read_raw(&mut self, address: u16, data: &mut [u8]) -> Result<(), ::protocol2::Error> {
+                self.servo.read_raw(self.bus, address, data)
+            }
+
+            /// Write `data` starting at `address`, bypassing the typed registers.
+            pub fn write_raw(&mut self, address: u16, data: &[u8]) -> Result<(), ::protocol2::Error> {
+                self.servo.write_raw(self.bus, address, data)
             }
         }
     };
 }
 
+/// The maximum number of data bytes that can be transferred in a single instruction
+pub const MAX_DATA_LENGTH: usize = 128;
+
 pub trait Register {
     const SIZE: u16;
     const ADDRESS: u16;
 }
     
 pub trait ReadRegister: Register {
-    fn deserialize(&[u8]) -> Self;
+    fn deserialize(data: &[u8]) -> Self;
 }
 
 pub trait WriteRegister: Register {
-    // TODO: change 4 to Self::SIZE when const generics land
-    fn serialize(&self) -> [u8; 4];
+    /// Serialize the register into `data`, which is exactly `Self::SIZE` bytes long.
+    fn serialize(&self, data: &mut [u8]);
 }
 
 pub(crate) trait Instruction {
-    const PARAMETERS: u16;
     const INSTRUCTION_VALUE: u8;
 
     fn id(&self) -> PacketID;
+
+    fn parameters(&self) -> u16;
     
     fn parameter(&self, index: usize) -> u8;
 
     fn serialize<'a>(&'a self) -> Serializer<'a, Self> where Self: Sized {
         let serializer = Serializer{
             pos: 0,
-            length: 10 + Self::PARAMETERS,
+            parameters: self.parameters() as usize,
+            length: 10 + self.parameters(),
             crc: crc::CRC::new(),
             bit_stuffer: BitStuffer::new(),
             instruction: self,
@@ -198,6 +601,7 @@ pub(crate) trait Instruction {
 
         Serializer{
             pos: 0,
+            parameters: self.parameters() as usize,
             length: length,
             crc: crc::CRC::new(),
             bit_stuffer: BitStuffer::new(),
@@ -207,14 +611,20 @@ pub(crate) trait Instruction {
 }
 
 pub(crate) trait Status {
-    const PARAMETERS: u16;
+    fn deserialize(id: ServoID, parameters: &[u8]) -> Result<Self, FormatError> where Self: Sized;
+}
 
-    fn deserialize(id: ServoID, parameters: &[u8]) -> Self;
+/// A deserialized status together with the alert flag of the status packet
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Response<T: Status> {
+    pub alert: bool,
+    pub status: T,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub(crate) struct Serializer<'a, T: Instruction + 'a> {
     pos: usize,
+    parameters: usize,
     length: u16,
     crc: crc::CRC,
     bit_stuffer: BitStuffer,
@@ -225,11 +635,11 @@ impl<'a, T: Instruction + 'a> ::lib::iter::Iterator for Serializer<'a, T> {
     type Item = u8;
     
     fn next(&mut self) -> Option<u8> {
-        let should_stuff = self.bit_stuffer.stuff_next() && self.pos < 9+T::PARAMETERS as usize;
+        let should_stuff = self.bit_stuffer.stuff_next() && self.pos < 9+self.parameters;
         let next_byte = if should_stuff {
             Some(0xfd)
         } else {
-            let next_byte = match self.pos {
+            match self.pos {
                 0 => Some(0xff),
                 1 => Some(0xff),
                 2 => Some(0xfd),
@@ -238,17 +648,14 @@ impl<'a, T: Instruction + 'a> ::lib::iter::Iterator for Serializer<'a, T> {
                 5 => Some(self.length as u8),
                 6 => Some((self.length >> 8) as u8),
                 7 => Some(T::INSTRUCTION_VALUE),
-                x if x < 8+T::PARAMETERS as usize => Some(self.instruction.parameter(x-8)),
-                x if x == 8+T::PARAMETERS as usize => Some(u16::from(self.crc) as u8),
-                x if x == 9+T::PARAMETERS as usize => Some((u16::from(self.crc) >> 8) as u8),
+                x if x < 8+self.parameters => Some(self.instruction.parameter(x-8)),
+                x if x == 8+self.parameters => Some(u16::from(self.crc) as u8),
+                x if x == 9+self.parameters => Some((u16::from(self.crc) >> 8) as u8),
                 _ => None,
-            };
-            
-
-            next_byte
+            }
         };
 
-        if self.pos < 8+T::PARAMETERS as usize {
+        if self.pos < 8+self.parameters {
             self.bit_stuffer = self.bit_stuffer.add_byte(next_byte.unwrap()).unwrap();
             self.crc.add(&[next_byte.unwrap()]);
         }
@@ -285,8 +692,11 @@ impl<T: Status> Deserializer<T> {
         if data[2] != 0xfd {return Err(FormatError::Header)};
         if data[3] != 0x00 {return Err(FormatError::Header)};
         if data[7] != 0x55 {return Err(FormatError::Instruction)};
+        if data[4] > 252 {return Err(FormatError::ID)};
 
+        // The length covers the instruction, the error and the CRC at least
         let length = data[5] as u16 | (data[6] as u16) << 8;
+        if length < 4 {return Err(FormatError::Length)};
         
         let mut crc = crc::CRC::new();
         crc.add(&data);
@@ -305,7 +715,7 @@ impl<T: Status> Deserializer<T> {
             bit_stuffer: bit_stuffer,
             alert: data[8].get_bit(7),
             processing_error: ProcessingError::decode(data[8].get_bits(0..7))?,
-            parameters: [0u8; 6],
+            parameters: [0u8; MAX_DATA_LENGTH],
             phantom: ::lib::marker::PhantomData{},
         })
     }
@@ -321,7 +731,7 @@ pub(crate) struct BodyDeserializer<T: Status> {
     bit_stuffer: BitStuffer,
     alert: bool,
     processing_error: Option<ProcessingError>,
-    parameters: [u8; 6],
+    parameters: [u8; MAX_DATA_LENGTH],
     phantom: ::lib::marker::PhantomData<T>,
 }
 
@@ -335,13 +745,16 @@ impl<T: Status> BodyDeserializer<T> {
         self.remaining_bytes
     }
     
-    pub fn build(self) -> Result<T, Error> {
+    pub fn build(self) -> Result<Response<T>, Error> {
         if !self.is_finished() {
             Err(Error::Unfinished)
         } else if let Some(error) = self.processing_error {
             Err(Error::Processing(error))
         } else {
-            Ok(T::deserialize(self.id, &self.parameters[..T::PARAMETERS as usize]))
+            Ok(Response {
+                alert: self.alert,
+                status: T::deserialize(self.id, &self.parameters[..self.parameter_index])?,
+            })
         }
     }
     
@@ -351,6 +764,9 @@ impl<T: Status> BodyDeserializer<T> {
                 self.bit_stuffer = self.bit_stuffer.add_byte(*b)?;
                 self.remaining_bytes -= 1;
             } else if self.remaining_bytes > 2 {
+                if self.parameter_index == MAX_DATA_LENGTH {
+                    return Err(FormatError::Length);
+                }
                 self.bit_stuffer = self.bit_stuffer.add_byte(*b)?;
                 self.crc_calc.add(&[*b]);
                 self.parameters[self.parameter_index] = *b;
@@ -394,21 +810,25 @@ impl From<::CommunicationError> for Error {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Error {
     Unfinished,
     Communication(::CommunicationError),
     Format(FormatError),
     Processing(ProcessingError),
+
+    /// The servo does not answer the instruction at its status return level, so it was not sent
+    NotAnswered,
 }
 
 impl From<::protocol2::Error> for ::Error {
     fn from(e: ::protocol2::Error) -> ::Error {
         match e {
-            ::protocol2::Error::Unfinished => ::Error::Unfinished,
-            ::protocol2::Error::Communication(ce) => ::Error::Communication(ce),
-            ::protocol2::Error::Format(_) => ::Error::Format,
-            ::protocol2::Error::Processing(_) => ::Error::Processing,
+            ::protocol2::Error::Unfinished => ::Error::new(::ErrorKind::Unfinished),
+            ::protocol2::Error::Communication(ce) => ::Error::new(::ErrorKind::Communication(ce)),
+            ::protocol2::Error::Format(fe) => ::Error::new(::ErrorKind::Format(::FormatError::Protocol2(fe))),
+            ::protocol2::Error::Processing(pe) => ::Error::new(::ErrorKind::Processing(::ProcessingError::Protocol2(pe))),
+            ::protocol2::Error::NotAnswered => ::Error::new(::ErrorKind::NotAnswered),
         }
     }
 }
@@ -437,6 +857,36 @@ pub enum ProcessingError {
     AccessError = 0x07,
 }
 
+impl ::lib::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut ::lib::fmt::Formatter) -> ::lib::fmt::Result {
+        match *self {
+            FormatError::Header => write!(f, "invalid header"),
+            FormatError::ID => write!(f, "invalid or unexpected servo ID"),
+            FormatError::Length => write!(f, "invalid length"),
+            FormatError::Instruction => write!(f, "not a status packet"),
+            FormatError::InvalidError(e) => write!(f, "invalid error byte 0x{:02x}", e),
+            FormatError::CRC => write!(f, "CRC mismatch"),
+            FormatError::StuffByte => write!(f, "missing stuff byte"),
+            FormatError::NotFinished => write!(f, "packet not finished"),
+        }
+    }
+}
+
+impl ::lib::fmt::Display for ProcessingError {
+    fn fmt(&self, f: &mut ::lib::fmt::Formatter) -> ::lib::fmt::Result {
+        let description = match *self {
+            ProcessingError::ResultFail => "failed to process the instruction",
+            ProcessingError::InstructionError => "undefined instruction or action without reg write",
+            ProcessingError::CRCError => "CRC of the instruction packet incorrect",
+            ProcessingError::DataRangeError => "data out of range of the register",
+            ProcessingError::DataLengthError => "data shorter than the register",
+            ProcessingError::DataLimitError => "data out of the limits of the register",
+            ProcessingError::AccessError => "register not accessible",
+        };
+        write!(f, "{}", description)
+    }
+}
+
 impl From<FormatError> for Error {
     fn from(e: FormatError) -> Error {
         Error::Format(e)
@@ -465,6 +915,79 @@ impl From<ProcessingError> for u8 {
     }
 }
 
+/// The content of the Hardware Error Status register
+///
+/// When any of these errors occur the servo sets the alert flag in its status packets and usually disables torque.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct HardwareError(u8);
+
+impl From<u8> for HardwareError {
+    fn from(v: u8) -> HardwareError {
+        HardwareError(v)
+    }
+}
+
+impl From<HardwareError> for u8 {
+    fn from(e: HardwareError) -> u8 {
+        e.0
+    }
+}
+
+impl HardwareError {
+    /// Returns `true` if no hardware error is flagged
+    pub fn is_ok(&self) -> bool {
+        !(self.input_voltage_error() || self.overheating_error() || self.motor_encoder_error() || self.electrical_shock_error() || self.overload_error())
+    }
+
+    pub fn input_voltage_error(&self) -> bool {
+        self.0.get_bit(0)
+    }
+
+    pub fn overheating_error(&self) -> bool {
+        self.0.get_bit(2)
+    }
+
+    pub fn motor_encoder_error(&self) -> bool {
+        self.0.get_bit(3)
+    }
+
+    pub fn electrical_shock_error(&self) -> bool {
+        self.0.get_bit(4)
+    }
+
+    pub fn overload_error(&self) -> bool {
+        self.0.get_bit(5)
+    }
+}
+
+impl ::lib::fmt::Debug for HardwareError {
+    fn fmt(&self, f: &mut ::lib::fmt::Formatter) -> ::lib::fmt::Result {
+        write!(f, "HardwareError({:#04x}: {})", self.0, self)
+    }
+}
+
+impl ::lib::fmt::Display for HardwareError {
+    fn fmt(&self, f: &mut ::lib::fmt::Formatter) -> ::lib::fmt::Result {
+        if self.is_ok() {
+            return write!(f, "no hardware error");
+        }
+        let errors = [
+            (self.input_voltage_error(), "input voltage out of range"),
+            (self.overheating_error(), "overheating"),
+            (self.motor_encoder_error(), "motor encoder malfunction"),
+            (self.electrical_shock_error(), "electrical shock or insufficient power"),
+            (self.overload_error(), "overload"),
+        ];
+        let mut first = true;
+        for &(_, description) in errors.iter().filter(|e| e.0) {
+            if !first {write!(f, ", ")?;}
+            write!(f, "{}", description)?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct ServoID(u8);
 
@@ -512,3 +1035,66 @@ impl From<ServoID> for u8 {
         id.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hardware_error() {
+        assert!(HardwareError::from(0x00).is_ok());
+        assert!(HardwareError::from(0x02).is_ok());
+
+        let error = HardwareError::from(0x24);
+        assert!(!error.is_ok());
+        assert!(error.overheating_error());
+        assert!(error.overload_error());
+        assert!(!error.input_voltage_error());
+        assert!(!error.motor_encoder_error());
+        assert!(!error.electrical_shock_error());
+    }
+
+    struct SilentInterface;
+
+    impl ::Interface for SilentInterface {
+        type Error = CommunicationError;
+
+        fn set_baud_rate(&mut self, _b: ::BaudRate) -> Result<(), CommunicationError> {Ok(())}
+        fn flush(&mut self) {}
+        fn read(&mut self, _data: &mut [u8]) -> Result<(), CommunicationError> {Err(CommunicationError::TimedOut)}
+        fn write(&mut self, _data: &[u8]) -> Result<(), CommunicationError> {Ok(())}
+    }
+
+    #[test]
+    fn test_sync_data_length() {
+        let ids = [ServoID::new(1), ServoID::new(2)];
+        assert_eq!(sync_write(&mut SilentInterface, 0, &[(ids[0], &[0, 0]), (ids[1], &[0])]), Err(Error::Format(FormatError::Length)));
+        assert_eq!(sync_write(&mut SilentInterface, 0, &[(ids[0], &[0u8; MAX_DATA_LENGTH + 1])]), Err(Error::Format(FormatError::Length)));
+        assert_eq!(sync_read(&mut SilentInterface, 0, &ids, &mut [0u8; 3]), Err(Error::Format(FormatError::Length)));
+        assert_eq!(sync_read(&mut SilentInterface, 0, &[], &mut [0u8; 2]), Err(Error::Format(FormatError::Length)));
+        assert_eq!(sync_read(&mut SilentInterface, 0, &ids[..1], &mut [0u8; MAX_DATA_LENGTH + 1]), Err(Error::Format(FormatError::Length)));
+        assert_eq!(sync_read(&mut SilentInterface, 0, &ids, &mut []), Ok(false));
+    }
+
+    #[test]
+    fn test_raw_data_length() {
+        let mut data = [0u8; MAX_DATA_LENGTH + 1];
+        assert_eq!(read_raw(&mut SilentInterface, ServoID::new(1), 0, &mut data), Err(Error::Format(FormatError::Length)));
+        assert_eq!(write_raw(&mut SilentInterface, ServoID::new(1), 0, &data), Err(Error::Format(FormatError::Length)));
+    }
+
+    #[cfg(feature="std")]
+    #[test]
+    fn test_hardware_error_display() {
+        assert_eq!(HardwareError::from(0x00).to_string(), "no hardware error");
+        assert_eq!(HardwareError::from(0x24).to_string(), "overheating, overload");
+        assert_eq!(HardwareError::from(0x19).to_string(), "input voltage out of range, motor encoder malfunction, electrical shock or insufficient power");
+    }
+
+    #[cfg(feature="std")]
+    #[test]
+    fn test_error_display() {
+        assert_eq!(FormatError::InvalidError(0x7f).to_string(), "invalid error byte 0x7f");
+        assert_eq!(ProcessingError::AccessError.to_string(), "register not accessible");
+    }
+}
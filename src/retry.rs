@@ -0,0 +1,197 @@
+//! Retrying transactions that failed because of transient bus errors
+//!
+//! A single corrupted byte or a lost status packet should not fail a control loop.
+//! Servos can be configured with a `RetryPolicy` and count their retries in `RetryStats`, to monitor link quality.
+
+use lib::time::Duration;
+
+use ErrorKind;
+use CommunicationError;
+use ProcessingError;
+
+/// Delay between attempts
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Backoff {
+    /// Retry immediately
+    None,
+
+    /// Wait the same time before every retry
+    Constant(Duration),
+
+    /// Wait `initial` before the first retry and double the wait for every following retry, up to `max`
+    Exponential {
+        initial: Duration,
+        max: Duration,
+    },
+}
+
+/// When and how often a failed transaction is retried
+///
+/// Backoff delays are only applied with the `std` feature, without it retries happen immediately.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one
+    pub max_attempts: u32,
+
+    pub backoff: Backoff,
+
+    /// Retry when the interface times out waiting for a status
+    pub retry_on_timeout: bool,
+
+    /// Retry when a packet was corrupted on the bus
+    ///
+    /// This covers malformed statuses, and servos reporting a checksum error for the instruction.
+    pub retry_on_corruption: bool,
+}
+
+impl RetryPolicy {
+    /// Never retry, this is the default
+    pub fn never() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 1,
+            backoff: Backoff::None,
+            retry_on_timeout: false,
+            retry_on_corruption: false,
+        }
+    }
+
+    /// Retry timeouts and corrupted packets, without backoff, until `max_attempts` attempts are made.
+    pub fn new(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: max_attempts,
+            backoff: Backoff::None,
+            retry_on_timeout: true,
+            retry_on_corruption: true,
+        }
+    }
+
+    /// Returns `true` if a transaction failing with `kind` should be retried
+    pub fn is_retryable(&self, kind: ErrorKind) -> bool {
+        match kind {
+            ErrorKind::Communication(CommunicationError::TimedOut) => self.retry_on_timeout,
+            ErrorKind::Unfinished | ErrorKind::Format(_) => self.retry_on_corruption,
+            ErrorKind::Processing(ProcessingError::Protocol1(e)) => self.retry_on_corruption && e.checksum_error(),
+            ErrorKind::Processing(ProcessingError::Protocol2(::protocol2::ProcessingError::CRCError)) => self.retry_on_corruption,
+            _ => false,
+        }
+    }
+
+    /// The delay before retry number `retry`, starting at 1
+    ///
+    /// Retry 0 gets the same delay as retry 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        match self.backoff {
+            Backoff::None => Duration::from_secs(0),
+            Backoff::Constant(delay) => delay,
+            Backoff::Exponential{initial, max} => {
+                let factor = 1u32.checked_shl(retry.saturating_sub(1)).unwrap_or(u32::MAX);
+                match initial.checked_mul(factor) {
+                    Some(delay) if delay < max => delay,
+                    _ => max,
+                }
+            },
+        }
+    }
+
+    /// Run `transaction` until it succeeds, fails with an error that is not retryable or runs out of attempts
+    pub(crate) fn run<T, E, F>(&self, stats: &mut RetryStats, mut transaction: F) -> Result<T, E>
+        where E: Into<::Error> + Clone,
+              F: FnMut() -> Result<T, E> {
+        stats.transactions = stats.transactions.wrapping_add(1);
+        let mut attempt = 1;
+        loop {
+            match transaction() {
+                Ok(v) => return Ok(v),
+                Err(e) if attempt < self.max_attempts && self.is_retryable(e.clone().into().kind()) => {
+                    stats.retries = stats.retries.wrapping_add(1);
+                    sleep(self.delay(attempt));
+                    attempt += 1;
+                },
+                Err(e) => {
+                    stats.failures = stats.failures.wrapping_add(1);
+                    return Err(e);
+                },
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::never()
+    }
+}
+
+#[cfg(feature="std")]
+fn sleep(delay: Duration) {
+    if delay > Duration::from_secs(0) {
+        ::std::thread::sleep(delay);
+    }
+}
+
+#[cfg(not(feature="std"))]
+fn sleep(_delay: Duration) {}
+
+/// Counters for monitoring link quality
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct RetryStats {
+    /// Transactions started
+    pub transactions: u32,
+
+    /// Attempts that failed and were retried
+    pub retries: u32,
+
+    /// Transactions that failed after all retries
+    pub failures: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable() {
+        let policy = RetryPolicy::new(3);
+        assert!(policy.is_retryable(ErrorKind::Communication(CommunicationError::TimedOut)));
+        assert!(policy.is_retryable(ErrorKind::Format(::FormatError::Protocol2(::protocol2::FormatError::CRC))));
+        assert!(policy.is_retryable(ErrorKind::Processing(ProcessingError::Protocol1(::protocol1::ProcessingError::CHECKSUM))));
+        assert!(!policy.is_retryable(ErrorKind::Processing(ProcessingError::Protocol1(::protocol1::ProcessingError::RANGE))));
+        assert!(!policy.is_retryable(ErrorKind::Communication(CommunicationError::Other)));
+        assert!(!RetryPolicy::never().is_retryable(ErrorKind::Communication(CommunicationError::TimedOut)));
+    }
+
+    #[test]
+    fn test_run() {
+        let policy = RetryPolicy::new(3);
+        let mut stats = RetryStats::default();
+
+        let mut attempts = 0;
+        let result = policy.run(&mut stats, || {
+            attempts += 1;
+            if attempts < 3 {Err(CommunicationError::TimedOut)} else {Ok(attempts)}
+        });
+        assert_eq!(result, Ok(3));
+
+        let result: Result<(), CommunicationError> = policy.run(&mut stats, || Err(CommunicationError::TimedOut));
+        assert_eq!(result, Err(CommunicationError::TimedOut));
+
+        let result: Result<(), CommunicationError> = policy.run(&mut stats, || Err(CommunicationError::Other));
+        assert_eq!(result, Err(CommunicationError::Other));
+
+        assert_eq!(stats, RetryStats{transactions: 3, retries: 4, failures: 2});
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            backoff: Backoff::Exponential{initial: Duration::from_millis(1), max: Duration::from_millis(5)},
+            ..RetryPolicy::new(5)
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(1));
+        assert_eq!(policy.delay(1), Duration::from_millis(1));
+        assert_eq!(policy.delay(2), Duration::from_millis(2));
+        assert_eq!(policy.delay(3), Duration::from_millis(4));
+        assert_eq!(policy.delay(4), Duration::from_millis(5));
+        assert_eq!(policy.delay(40), Duration::from_millis(5));
+    }
+}
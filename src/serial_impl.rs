@@ -13,7 +13,7 @@ impl From<serialport::Error> for CommunicationError {
     fn from(e: serialport::Error) -> CommunicationError {
         match e {
             serialport::Error{kind: serialport::ErrorKind::Io(std::io::ErrorKind::TimedOut), ..} => CommunicationError::TimedOut,
-            _ => CommunicationError::Other,
+            e => CommunicationError::Interface(::InterfaceError::new(e)),
         }
     }
 }
@@ -35,7 +35,9 @@ impl From<BaudRate> for serialport::BaudRate {
     }
 }
 
-impl Interface for std::boxed::Box<serialport::SerialPort> {
+impl Interface for std::boxed::Box<dyn serialport::SerialPort> {
+    type Error = CommunicationError;
+
     fn set_baud_rate(&mut self, b: BaudRate) -> Result<(), CommunicationError> {
         match serialport::SerialPort::set_baud_rate(self.deref_mut(), serialport::BaudRate::from(b)) {
             Ok(_) => Ok(()),
@@ -43,18 +45,23 @@ impl Interface for std::boxed::Box<serialport::SerialPort> {
         }
     }
 
+    fn set_timeout(&mut self, timeout: std::time::Duration) -> Result<(), CommunicationError> {
+        if serialport::SerialPort::timeout(&**self) != timeout {
+            serialport::SerialPort::set_timeout(self.deref_mut(), timeout)?;
+        }
+        Ok(())
+    }
+
     fn flush(&mut self) {
         let mut buf = Vec::new();
         let _res = self.read_to_end(&mut buf);
     }
     
     fn read(&mut self, data: &mut [u8]) -> Result<(), CommunicationError> {
-        self.set_timeout(std::time::Duration::new(0, 100000000))?;
         Ok(std::io::Read::read_exact(self, data)?)
     }
 
     fn write(&mut self, data: &[u8]) -> Result<(), CommunicationError> {
-        self.set_timeout(std::time::Duration::new(0, 100000000))?;
         Ok(std::io::Write::write_all(self, data)?)
     }
 }
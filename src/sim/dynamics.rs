@@ -0,0 +1,381 @@
+//! A simple motion model of the actuator of a virtual servo
+//!
+//! The position controller is emulated from the gain registers as a velocity command,
+//! which the motor follows with a first order response while the current stays within the current and torque limits.
+//! The winding is heated by the current and cooled towards the ambient temperature.
+
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use Protocol;
+use descriptor::RegisterDescriptor;
+use super::VirtualServo;
+
+/// The longest step of the integration, longer steps are split up
+const MAX_STEP: f32 = 0.001;
+
+/// The velocity a servo is considered moving at, in rad/s
+const MOVING_THRESHOLD: f32 = 0.01;
+
+/// Units of the registers that have none in their model file, from the Robotis e-manual of the Pro series
+const DEFAULT_VELOCITY_UNIT: f32 = 0.00199234;
+const DEFAULT_CURRENT_UNIT: f32 = 33000.0 / 2048.0;
+
+/// The overheating bit of the Hardware Error Status register
+const OVERHEATING_ERROR: i64 = 0x04;
+
+/// Physical parameters of the actuator of a virtual servo
+///
+/// The default parameters resemble a 12 V servo in the size of an MX-28.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Dynamics {
+    /// Time constant of the velocity response, in seconds
+    pub time_constant: f32,
+    /// No load velocity, in rad/s
+    pub max_velocity: f32,
+    /// Stall current, in A
+    pub max_current: f32,
+    /// Current drawn by the load on the output shaft, in A
+    pub load_current: f32,
+    /// Velocity command in rad/s per radian of position error, for a position P gain of one
+    pub position_gain: f32,
+    /// Resistance of the winding, in ohm
+    pub winding_resistance: f32,
+    /// Temperature rise per dissipated watt, in °C/W
+    pub thermal_resistance: f32,
+    /// Time constant of the temperature, in seconds
+    pub thermal_time_constant: f32,
+    /// Temperature of the surroundings, in °C
+    pub ambient_temperature: f32,
+}
+
+impl Default for Dynamics {
+    fn default() -> Self {
+        Dynamics {
+            time_constant: 0.05,
+            max_velocity: 5.8,
+            max_current: 1.4,
+            load_current: 0.0,
+            position_gain: 2.0,
+            winding_resistance: 8.0,
+            thermal_resistance: 6.0,
+            thermal_time_constant: 120.0,
+            ambient_temperature: 25.0,
+        }
+    }
+}
+
+/// The state of the motion model
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) struct Motion {
+    /// In radians
+    position: f32,
+    /// In rad/s
+    velocity: f32,
+    /// In A
+    current: f32,
+    /// In °C
+    temperature: f32,
+    /// Integral of the position error, in radian seconds
+    integral: f32,
+    /// The raw value last written to the Present Position register, any other value is adopted as a new position
+    present_position: Option<i64>,
+}
+
+impl Motion {
+    pub fn new(dynamics: &Dynamics) -> Self {
+        Motion {
+            position: 0.0,
+            velocity: 0.0,
+            current: 0.0,
+            temperature: dynamics.ambient_temperature,
+            integral: 0.0,
+            present_position: None,
+        }
+    }
+}
+
+/// Factory defaults for the registers the motion model depends on, a zeroed memory would not move
+pub(crate) fn reset(servo: &mut VirtualServo) {
+    for register in servo.table.registers {
+        let value = match register.name {
+            "Max Torque" | "Torque Limit" | "Current Limit" | "PWM Limit" | "Velocity Limit" | "CCW Angle Limit" | "Max Position Limit" | "Temperature Limit" | "Max Voltage Limit" => register.max,
+            "CW Angle Limit" | "Min Position Limit" | "Min Voltage Limit" => register.min,
+            "P Gain" => Some(32),
+            "Position P Gain" => Some(800),
+            "Present Voltage" | "Present Input Voltage" => Some(120),
+            _ => None,
+        };
+        if let Some(value) = value {
+            servo.set_register(register.name, value);
+        }
+    }
+    if let Some(position) = servo.table.position {
+        servo.set_register("Goal Position", position.value_of_zero_radian_position);
+    }
+    servo.motion = Motion::new(&servo.dynamics);
+    servo.write_motion();
+}
+
+/// The number in the unit of a register, e.g. 0.229 for "0.229 rpm"
+fn unit(register: &RegisterDescriptor) -> Option<f32> {
+    register.unit.and_then(|unit| unit.split(' ').next()).and_then(|scale| scale.parse().ok())
+}
+
+fn rpm(radians_per_second: f32) -> f32 {
+    radians_per_second * 60.0 / (2.0 * PI)
+}
+
+fn clamp(value: f32, limit: f32) -> f32 {
+    value.max(-limit).min(limit)
+}
+
+impl VirtualServo {
+    /// The descriptor and value of the register `name`
+    fn described_register(&self, name: &str) -> Option<(&'static RegisterDescriptor, i64)> {
+        self.table.register(name).map(|register| (register, self.register(name).expect("the register exists")))
+    }
+
+    /// The P, I and D gains of the position controller
+    fn gains(&self) -> (f32, f32, f32) {
+        let gain = |name: &str| self.register(name).unwrap_or(0) as f32;
+        match self.protocol() {
+            // Servos without gain registers, like the AX-12A, behave as with the default P gain
+            Protocol::Protocol1 if self.table.register("P Gain").is_none() => (4.0, 0.0, 0.0),
+            Protocol::Protocol1 => (gain("P Gain") / 8.0, gain("I Gain") * 1000.0 / 2048.0, gain("D Gain") * 4.0 / 1000.0),
+            Protocol::Protocol2 => (gain("Position P Gain") / 128.0, gain("Position I Gain") / 65536.0, gain("Position D Gain") / 16.0),
+        }
+    }
+
+    /// The velocity limit in rad/s, where zero means no limit
+    fn velocity_limit(&self) -> f32 {
+        let max = self.dynamics.max_velocity;
+        let limit = |register: &RegisterDescriptor, value: i64| if value == 0 {
+            max
+        } else {
+            (value as f32 * unit(register).unwrap_or(DEFAULT_VELOCITY_UNIT) * 2.0 * PI / 60.0).min(max)
+        };
+        match self.protocol() {
+            Protocol::Protocol1 => self.described_register("Moving Speed").map(|(r, v)| limit(r, v)).unwrap_or(max),
+            Protocol::Protocol2 => {
+                let profile = self.described_register("Profile Velocity").map(|(r, v)| limit(r, v)).unwrap_or(max);
+                let velocity = self.described_register("Velocity Limit").map(|(r, v)| limit(r, v)).unwrap_or(max);
+                profile.min(velocity)
+            },
+        }
+    }
+
+    /// The current limit in A
+    fn current_limit(&self) -> f32 {
+        let max = self.dynamics.max_current;
+        ["Max Torque", "Torque Limit", "Current Limit"].iter()
+            .filter_map(|name| self.described_register(name))
+            .filter_map(|(register, value)| match register.unit {
+                Some(suffix) if suffix.ends_with('%') => Some(max * value as f32 / 1023.0),
+                Some(suffix) if suffix.ends_with("mA") => unit(register).map(|scale| value as f32 * scale / 1000.0),
+                _ => None,
+            })
+            .fold(max, f32::min)
+    }
+
+    /// Advance the motion model by `dt` and update the present registers
+    ///
+    /// Called by `SimulatedBus` for the time it takes to transfer every instruction and status.
+    pub fn step(&mut self, dt: Duration) {
+        self.read_motion();
+        let mut remaining = dt.as_secs_f32();
+        while remaining > 0.0 {
+            let dt = remaining.min(MAX_STEP);
+            self.integrate(dt);
+            remaining -= dt;
+        }
+        self.write_motion();
+    }
+
+    fn integrate(&mut self, dt: f32) {
+        let dynamics = self.dynamics;
+        let enabled = self.register("Torque Enable").unwrap_or(0) != 0;
+        let max_acceleration = dynamics.max_velocity / dynamics.time_constant;
+
+        let (acceleration, current) = if enabled {
+            let goal = match (self.table.position, self.register("Goal Position")) {
+                (Some(position), Some(goal)) => position.to_radians(goal),
+                _ => self.motion.position,
+            };
+            let error = goal - self.motion.position;
+            let (p, i, d) = self.gains();
+            self.motion.integral += error * dt;
+            let command = dynamics.position_gain * (p * error + i * self.motion.integral - d * self.motion.velocity);
+            let command = clamp(command, self.velocity_limit());
+
+            // The current needed for the acceleration towards the commanded velocity, within the current limit
+            let current = dynamics.load_current + (command - self.motion.velocity) / dynamics.time_constant / max_acceleration * dynamics.max_current;
+            let current = clamp(current, self.current_limit());
+            ((current - dynamics.load_current) / dynamics.max_current * max_acceleration, current)
+        } else {
+            // The motor is free running and coasts to a stop
+            self.motion.integral = 0.0;
+            (-self.motion.velocity / dynamics.time_constant, 0.0)
+        };
+
+        self.motion.current = current;
+        self.motion.velocity = clamp(self.motion.velocity + acceleration * dt, dynamics.max_velocity);
+        self.motion.position += self.motion.velocity * dt;
+        if let Some(position) = self.table.position {
+            if self.motion.position < position.min_radian || self.motion.position > position.max_radian {
+                self.motion.position = self.motion.position.max(position.min_radian).min(position.max_radian);
+                self.motion.velocity = 0.0;
+            }
+        }
+
+        let heat = current * current * dynamics.winding_resistance * dynamics.thermal_resistance;
+        self.motion.temperature += (dynamics.ambient_temperature + heat - self.motion.temperature) * dt / dynamics.thermal_time_constant;
+
+        // Overheating shuts the servo down
+        if let Some(limit) = self.register("Temperature Limit") {
+            if enabled && limit > 0 && self.motion.temperature > limit as f32 {
+                self.set_register("Torque Enable", 0);
+                let error = self.register("Hardware Error Status").unwrap_or(0);
+                self.set_register("Hardware Error Status", error | OVERHEATING_ERROR);
+            }
+        }
+    }
+
+    /// Adopt a Present Position written to the memory since the last step
+    pub(crate) fn read_motion(&mut self) {
+        if let (Some(position), Some(present)) = (self.table.position, self.register("Present Position")) {
+            if self.motion.present_position != Some(present) {
+                self.motion.position = position.to_radians(present);
+                self.motion.velocity = 0.0;
+            }
+        }
+    }
+
+    /// Write the state of the motion model to the present registers
+    pub(crate) fn write_motion(&mut self) {
+        let motion = self.motion;
+        if let Some(position) = self.table.position {
+            let present = position.from_radians(motion.position);
+            self.set_register("Present Position", present);
+            self.motion.present_position = Some(present);
+        }
+
+        let moving = motion.velocity.abs() > MOVING_THRESHOLD;
+        self.set_register("Moving", moving as i64);
+        self.set_register("Present Temperature", motion.temperature.round() as i64);
+
+        match self.protocol() {
+            Protocol::Protocol1 => {
+                // Protocol 1 speed and load are a magnitude of ten bits, with the clockwise direction in bit ten
+                let direction = |value: f32| if value < 0.0 {0x400} else {0};
+                if let Some((register, _)) = self.described_register("Present Speed") {
+                    let speed = (rpm(motion.velocity).abs() / unit(register).unwrap_or(1.0)).round().min(1023.0) as i64;
+                    self.set_register(register.name, speed | direction(motion.velocity));
+                }
+                let load = (motion.current.abs() / self.dynamics.max_current * 1023.0).round().min(1023.0) as i64;
+                self.set_register("Present Load", load | direction(motion.current));
+            },
+            Protocol::Protocol2 => {
+                if let Some((register, _)) = self.described_register("Present Velocity") {
+                    let velocity = rpm(motion.velocity) / unit(register).unwrap_or(DEFAULT_VELOCITY_UNIT);
+                    self.set_register(register.name, velocity.round() as i64);
+                }
+                if let Some((register, _)) = self.described_register("Present Current") {
+                    let current = motion.current * 1000.0 / unit(register).unwrap_or(DEFAULT_CURRENT_UNIT);
+                    self.set_register(register.name, current.round() as i64);
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use BaudRate;
+    use sim::SimulatedBus;
+
+    #[test]
+    fn test_move_to_goal() {
+        let mut servo = VirtualServo::from_model(Protocol::Protocol1, 0x001D, 1, BaudRate::Baud1000000).unwrap();
+        assert_eq!(servo.register("Present Position"), Some(2048));
+        assert_eq!(servo.register("Present Temperature"), Some(25));
+
+        // Nothing moves without torque
+        servo.set_register("Goal Position", 3072);
+        servo.step(Duration::from_millis(100));
+        assert_eq!(servo.register("Present Position"), Some(2048));
+
+        servo.set_register("Torque Enable", 1);
+        servo.step(Duration::from_millis(100));
+        let position = servo.register("Present Position").unwrap();
+        assert!(position > 2048 && position < 3072);
+        assert!(servo.register("Present Speed").unwrap() > 0);
+        assert!(servo.register("Present Load").unwrap() & 0x3ff > 0);
+        assert_eq!(servo.register("Moving"), Some(1));
+
+        // Moving back is clockwise
+        servo.set_register("Goal Position", 1024);
+        servo.step(Duration::from_millis(200));
+        assert!(servo.register("Present Speed").unwrap() & 0x400 != 0);
+
+        servo.step(Duration::from_secs(3));
+        assert!((servo.register("Present Position").unwrap() - 1024).abs() <= 2);
+        assert_eq!(servo.register("Moving"), Some(0));
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut servo = VirtualServo::from_model(Protocol::Protocol2, 1020, 1, BaudRate::Baud57600).unwrap();
+        servo.set_register("Torque Enable", 1);
+        servo.set_register("Goal Position", 4095);
+        servo.set_register("Profile Velocity", 100);
+        servo.step(Duration::from_millis(500));
+        assert_eq!(servo.register("Present Velocity"), Some(100));
+
+        // Reversing draws current up to the current limit
+        servo.set_register("Goal Position", 0);
+        servo.step(Duration::from_millis(1));
+        assert!(servo.register("Present Current").unwrap() < -100);
+        servo.set_register("Current Limit", 100);
+        servo.step(Duration::from_millis(1));
+        assert_eq!(servo.register("Present Current"), Some(-100));
+    }
+
+    #[test]
+    fn test_overheating() {
+        let mut servo = VirtualServo::from_model(Protocol::Protocol2, 1020, 1, BaudRate::Baud57600).unwrap();
+        servo.set_dynamics(Dynamics{load_current: 1.0, ..Dynamics::default()});
+        servo.set_register("Temperature Limit", 50);
+        servo.set_register("Torque Enable", 1);
+
+        servo.step(Duration::from_secs(30));
+        let temperature = servo.register("Present Temperature").unwrap();
+        assert!(temperature > 25 && temperature < 50);
+        assert_eq!(servo.register("Hardware Error Status"), Some(0));
+
+        servo.step(Duration::from_secs(300));
+        assert_eq!(servo.register("Torque Enable"), Some(0));
+        assert_eq!(servo.register("Hardware Error Status"), Some(OVERHEATING_ERROR));
+    }
+
+    #[test]
+    fn test_get_position() {
+        let mut bus = SimulatedBus::new();
+        bus.add(VirtualServo::from_model(Protocol::Protocol2, 1020, 1, BaudRate::Baud57600).unwrap());
+        let info = ::enumerate(&mut bus).unwrap().remove(0);
+        let mut servo = ::connect(&mut bus, info).unwrap();
+
+        servo.set_enable_torque(&mut bus, true).unwrap();
+        servo.set_position(&mut bus, 1.0).unwrap();
+        let mut trajectory = Vec::new();
+        for _ in 0..10 {
+            bus.advance(Duration::from_millis(50));
+            trajectory.push(servo.get_position(&mut bus).unwrap());
+        }
+        // The servo accelerates towards the goal, overshoots slightly and settles
+        assert!(trajectory[..4].windows(2).all(|w| w[0] < w[1]));
+        assert!(trajectory.iter().all(|p| *p < 1.1));
+        assert!((trajectory[9] - 1.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,726 @@
+//! An in-process simulated bus of virtual servos
+//!
+//! Requires the `std` feature. `SimulatedBus` implements `Interface` and answers instructions the way a bus of real servos would,
+//! so `enumerate`, `connect` and the servo types can be integration-tested without hardware.
+//!
+//! Every `VirtualServo` keeps a control table memory laid out after the control table descriptor of its model.
+//! Ping, Read, Write, Sync Write and Bulk Read are answered in both protocols, Sync Read and Bulk Write in protocol 2.
+//! The access rules of the control table are applied:
+//!
+//! - Read only registers can not be written.
+//! - Written values must be within the range of the register.
+//! - EEPROM is locked while Torque Enable is set (protocol 2), or while Lock is set (protocol 1).
+//!
+//! Violations are answered with the error codes of the protocol, `RANGE` for protocol 1 and `DataRangeError`/`AccessError` for protocol 2.
+//! As with real servos, protocol 1 servos do not answer broadcast pings, and the Baud Rate register does not change the baud rate of a virtual servo.
+//!
+//! Virtual servos move towards their Goal Position following a simple physical model, see `Dynamics`.
+//! Simulated time passes while instructions and statuses are transferred at the baud rate of the bus, and with `SimulatedBus::advance`.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use BaudRate;
+use CommunicationError;
+use Interface;
+use Protocol;
+use StatusReturnLevel;
+use descriptor::{self, Access, Area, ControlTable};
+use mock;
+
+mod dynamics;
+
+pub use self::dynamics::Dynamics;
+
+const BROADCAST_ID: u8 = 0xfe;
+
+const PING: u8 = 0x01;
+const READ: u8 = 0x02;
+const WRITE: u8 = 0x03;
+const SYNC_READ: u8 = 0x82;
+const SYNC_WRITE: u8 = 0x83;
+const BULK_READ: u8 = 0x92;
+const BULK_WRITE: u8 = 0x93;
+
+/// A reason for a servo to reject an instruction, mapped to the error codes of each protocol
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Fault {
+    Instruction,
+    Checksum,
+    Length,
+    Range,
+    Access,
+}
+
+/// A received instruction packet
+#[derive(Debug, Clone)]
+struct Packet {
+    protocol: Protocol,
+    id: u8,
+    instruction: u8,
+    parameters: Vec<u8>,
+    checksum_ok: bool,
+}
+
+/// Parse the instruction packet at the start of `data`
+///
+/// Returns the packet and the number of bytes it occupied, or `None` if the packet is not complete yet.
+/// Bytes before the first header are skipped. A malformed header is returned without a packet,
+/// occupying the bytes up to and including its `0xff 0xff`, so parsing resyncs on the next header like a servo does.
+fn parse_packet(data: &[u8]) -> Option<(Option<Packet>, usize)> {
+    let start = data.windows(2).position(|w| w == [0xff, 0xff])?;
+    let data = &data[start..];
+    if data.len() < 4 {
+        return None;
+    }
+
+    if data[2] == 0xfd && data[3] == 0x00 {
+        if data.len() < 7 {
+            return None;
+        }
+        let length = data[5] as usize | (data[6] as usize) << 8;
+        if length < 3 {
+            return Some((None, start + 2));
+        }
+        if data.len() < 7 + length {
+            return None;
+        }
+        let end = 7 + length;
+        let mut crc = ::protocol2::crc::CRC::new();
+        crc.add(&data[..end - 2]);
+        let checksum_ok = u16::from(crc) == (data[end - 2] as u16 | (data[end - 1] as u16) << 8);
+
+        // Remove the byte stuffing, 0xff 0xff 0xfd 0xfd is sent for 0xff 0xff 0xfd
+        let mut parameters: Vec<u8> = Vec::new();
+        for b in &data[8..end - 2] {
+            if *b == 0xfd && parameters.ends_with(&[0xff, 0xff, 0xfd]) {
+                continue;
+            }
+            parameters.push(*b);
+        }
+
+        Some((Some(Packet{protocol: Protocol::Protocol2, id: data[4], instruction: data[7], parameters: parameters, checksum_ok: checksum_ok}), start + end))
+    } else {
+        let length = data[3] as usize;
+        if data.len() < 4 + length {
+            return None;
+        }
+        if length < 2 {
+            return Some((Some(Packet{protocol: Protocol::Protocol1, id: data[2], instruction: 0, parameters: Vec::new(), checksum_ok: false}), start + 4 + length));
+        }
+        let end = 4 + length;
+        let checksum_ok = data[end - 1] == u8::from(::protocol1::checksum::Checksum::calc(&data[2..end - 1]));
+        Some((Some(Packet{protocol: Protocol::Protocol1, id: data[2], instruction: data[4], parameters: data[5..end - 1].to_vec(), checksum_ok: checksum_ok}), start + end))
+    }
+}
+
+/// A simulated servo with a control table memory
+#[derive(Debug, Clone)]
+pub struct VirtualServo {
+    table: &'static ControlTable,
+    memory: Vec<u8>,
+    baud_rate: BaudRate,
+    dynamics: Dynamics,
+    motion: dynamics::Motion,
+}
+
+impl VirtualServo {
+    /// Create a servo of the model described by `table`, listening on `id` at `baud_rate`
+    ///
+    /// The memory is zeroed, except for the model number, the ID, the status return level which answers all instructions,
+    /// and the limits, gains and positions needed for the servo to move.
+    pub fn new(table: &'static ControlTable, id: u8, baud_rate: BaudRate) -> Self {
+        let size = table.registers.iter().map(|r| (r.address + r.size) as usize).max().unwrap_or(0);
+        let mut servo = VirtualServo {
+            table: table,
+            memory: vec![0u8; size],
+            baud_rate: baud_rate,
+            dynamics: Dynamics::default(),
+            motion: dynamics::Motion::new(&Dynamics::default()),
+        };
+        dynamics::reset(&mut servo);
+        servo.set_register("Model Number", table.model_number as i64);
+        servo.set_register("ID", id as i64);
+        servo.set_register("Status Return Level", u8::from(StatusReturnLevel::All) as i64);
+        servo
+    }
+
+    /// Create a servo of a model with a known control table
+    pub fn from_model(protocol: Protocol, model_number: u16, id: u8, baud_rate: BaudRate) -> Option<Self> {
+        descriptor::find(protocol, model_number).map(|table| VirtualServo::new(table, id, baud_rate))
+    }
+
+    pub fn control_table(&self) -> &'static ControlTable {
+        self.table
+    }
+
+    pub fn protocol(&self) -> Protocol {
+        self.table.protocol
+    }
+
+    /// The ID, as stored in the ID register
+    pub fn id(&self) -> u8 {
+        self.register("ID").unwrap_or(0) as u8
+    }
+
+    pub fn baud_rate(&self) -> BaudRate {
+        self.baud_rate
+    }
+
+    pub fn set_baud_rate(&mut self, baud_rate: BaudRate) {
+        self.baud_rate = baud_rate;
+    }
+
+    pub fn dynamics(&self) -> &Dynamics {
+        &self.dynamics
+    }
+
+    /// Replace the physical parameters of the actuator, the servo is at rest at the ambient temperature afterwards
+    pub fn set_dynamics(&mut self, dynamics: Dynamics) {
+        self.dynamics = dynamics;
+        self.motion = dynamics::Motion::new(&dynamics);
+        self.read_motion();
+        self.write_motion();
+    }
+
+    /// The control table memory
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// The control table memory, for changing it without the access rules, as the servo itself would
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    /// The value of the register `name`, `None` if the model has no such register
+    pub fn register(&self, name: &str) -> Option<i64> {
+        let register = self.table.register(name)?;
+        let address = register.address as usize;
+        Some(register.decode(&self.memory[address..address + register.size as usize]))
+    }
+
+    /// Set the register `name` without the access rules, returns `false` if the model has no such register
+    pub fn set_register(&mut self, name: &str, value: i64) -> bool {
+        match self.table.register(name) {
+            Some(register) => {
+                let address = register.address as usize;
+                register.encode(value, &mut self.memory[address..address + register.size as usize]);
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn status_return_level(&self) -> StatusReturnLevel {
+        self.register("Status Return Level").map(|v| StatusReturnLevel::from(v as u8)).unwrap_or_default()
+    }
+
+    fn eeprom_locked(&self) -> bool {
+        let lock = match self.protocol() {
+            Protocol::Protocol1 => "Lock",
+            Protocol::Protocol2 => "Torque Enable",
+        };
+        self.register(lock).unwrap_or(0) != 0
+    }
+
+    fn read(&self, address: usize, length: usize) -> Result<Vec<u8>, Fault> {
+        if length == 0 || address + length > self.memory.len() {
+            return Err(Fault::Access);
+        }
+        Ok(self.memory[address..address + length].to_vec())
+    }
+
+    fn write(&mut self, address: usize, data: &[u8]) -> Result<(), Fault> {
+        if data.is_empty() || address + data.len() > self.memory.len() {
+            return Err(Fault::Access);
+        }
+        let end = address + data.len();
+        let mut memory = self.memory.clone();
+        memory[address..end].copy_from_slice(data);
+
+        for register in self.table.registers {
+            let (start, stop) = (register.address as usize, (register.address + register.size) as usize);
+            if stop <= address || start >= end {
+                continue;
+            }
+            if register.access == Access::Read {
+                return Err(Fault::Access);
+            }
+            if register.area == Area::Eeprom && self.eeprom_locked() {
+                return Err(Fault::Access);
+            }
+            if !register.is_valid(register.decode(&memory[start..stop])) {
+                return Err(Fault::Range);
+            }
+        }
+        self.memory = memory;
+        Ok(())
+    }
+
+    /// A status packet from this servo
+    fn status(&self, fault: Option<Fault>, parameters: &[u8]) -> Vec<u8> {
+        match self.protocol() {
+            Protocol::Protocol1 => {
+                let error = fault.map(|fault| match fault {
+                    Fault::Instruction => ::protocol1::ProcessingError::INSTRUCTION,
+                    Fault::Checksum => ::protocol1::ProcessingError::CHECKSUM,
+                    Fault::Length | Fault::Range | Fault::Access => ::protocol1::ProcessingError::RANGE,
+                });
+                mock::protocol1::error_status(::protocol1::ServoID::new(self.id()), error, parameters)
+            },
+            Protocol::Protocol2 => {
+                let error = fault.map(|fault| match fault {
+                    Fault::Instruction => ::protocol2::ProcessingError::InstructionError,
+                    Fault::Checksum => ::protocol2::ProcessingError::CRCError,
+                    Fault::Length => ::protocol2::ProcessingError::DataLengthError,
+                    Fault::Range => ::protocol2::ProcessingError::DataRangeError,
+                    Fault::Access => ::protocol2::ProcessingError::AccessError,
+                });
+                let alert = self.register("Hardware Error Status").unwrap_or(0) != 0;
+                mock::protocol2::error_status(::protocol2::ServoID::new(self.id()), alert, error, parameters)
+            },
+        }
+    }
+
+    /// Split the parameters of a read or write into address, length or data
+    fn address(&self, parameters: &[u8]) -> Option<(usize, usize)> {
+        match self.protocol() {
+            Protocol::Protocol1 if parameters.len() >= 2 => Some((parameters[0] as usize, 1)),
+            Protocol::Protocol2 if parameters.len() >= 3 => Some((parameters[0] as usize | (parameters[1] as usize) << 8, 2)),
+            _ => None,
+        }
+    }
+
+    fn read_parameters(&self, parameters: &[u8]) -> Result<(usize, usize), Fault> {
+        match (self.protocol(), parameters.len()) {
+            (Protocol::Protocol1, 2) => Ok((parameters[0] as usize, parameters[1] as usize)),
+            (Protocol::Protocol2, 4) => Ok((parameters[0] as usize | (parameters[1] as usize) << 8, parameters[2] as usize | (parameters[3] as usize) << 8)),
+            _ => Err(Fault::Length),
+        }
+    }
+
+    /// Answer the status of a read, if the status return level allows it
+    fn reply_read(&self, address: usize, length: usize) -> Option<Vec<u8>> {
+        if !self.status_return_level().replies_to_read() {
+            return None;
+        }
+        Some(match self.read(address, length) {
+            Ok(data) => self.status(None, &data),
+            Err(fault) => self.status(Some(fault), &[]),
+        })
+    }
+
+    /// Execute an instruction addressed to this servo, returning the status packet
+    fn execute(&mut self, packet: &Packet, broadcast: bool) -> Option<Vec<u8>> {
+        let reply = |status: Vec<u8>| if broadcast {None} else {Some(status)};
+        match packet.instruction {
+            PING => {
+                let parameters = match self.protocol() {
+                    Protocol::Protocol1 => Vec::new(),
+                    Protocol::Protocol2 => {
+                        let model_number = self.table.model_number;
+                        vec![model_number as u8, (model_number >> 8) as u8, self.register("Firmware Version").unwrap_or(0) as u8]
+                    },
+                };
+                Some(self.status(None, &parameters))
+            },
+            READ => {
+                if broadcast {
+                    return None;
+                }
+                match self.read_parameters(&packet.parameters) {
+                    Ok((address, length)) => self.reply_read(address, length),
+                    Err(fault) => reply(self.status(Some(fault), &[])),
+                }
+            },
+            WRITE => {
+                let result = match self.address(&packet.parameters) {
+                    Some((address, offset)) => self.write(address, &packet.parameters[offset..]),
+                    None => Err(Fault::Length),
+                };
+                if !self.status_return_level().replies_to_write() {
+                    return None;
+                }
+                reply(self.status(result.err(), &[]))
+            },
+            _ => reply(self.status(Some(Fault::Instruction), &[])),
+        }
+    }
+}
+
+/// A simulated bus of virtual servos
+///
+/// Servos only understand instructions of their own protocol written with their own baud rate.
+/// Statuses are available as soon as the instruction is written, but reading them advances the simulated time.
+#[derive(Debug, Default)]
+pub struct SimulatedBus {
+    servos: Vec<VirtualServo>,
+    baud_rate: Option<BaudRate>,
+    timeout: Option<Duration>,
+    written: Vec<u8>,
+    received: VecDeque<u8>,
+    elapsed: Duration,
+}
+
+impl SimulatedBus {
+    pub fn new() -> Self {
+        SimulatedBus::default()
+    }
+
+    /// Connect `servo` to the bus
+    pub fn add(&mut self, servo: VirtualServo) {
+        self.servos.push(servo);
+    }
+
+    pub fn servos(&self) -> &[VirtualServo] {
+        &self.servos
+    }
+
+    /// The servo with the given protocol and ID
+    pub fn servo(&self, protocol: Protocol, id: u8) -> Option<&VirtualServo> {
+        self.servos.iter().find(|s| s.protocol() == protocol && s.id() == id)
+    }
+
+    pub fn servo_mut(&mut self, protocol: Protocol, id: u8) -> Option<&mut VirtualServo> {
+        self.servos.iter_mut().find(|s| s.protocol() == protocol && s.id() == id)
+    }
+
+    /// The baud rate last set on the interface
+    pub fn baud_rate(&self) -> Option<BaudRate> {
+        self.baud_rate
+    }
+
+    /// The timeout last set on the interface
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The number of status bytes waiting to be read
+    pub fn pending(&self) -> usize {
+        self.received.len()
+    }
+
+    /// The simulated time passed since the bus was created
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Let simulated time pass, moving the servos
+    pub fn advance(&mut self, dt: Duration) {
+        self.elapsed += dt;
+        for servo in &mut self.servos {
+            servo.step(dt);
+        }
+    }
+
+    /// Let the time it takes to transfer `bytes` at the baud rate of the bus pass
+    fn transfer(&mut self, bytes: usize) {
+        if let Some(baud_rate) = self.baud_rate {
+            // Every byte is sent with a start and a stop bit
+            let nanos = bytes as u64 * 10 * 1_000_000_000 / u64::from(u32::from(baud_rate));
+            self.advance(Duration::from_nanos(nanos));
+        }
+    }
+
+    /// The index of the servo listening to `id` for `packet`
+    fn listening(&self, protocol: Protocol, id: u8) -> Option<usize> {
+        self.servos.iter().position(|s| s.protocol() == protocol && Some(s.baud_rate) == self.baud_rate && s.id() == id)
+    }
+
+    fn respond(&mut self, status: Option<Vec<u8>>) {
+        if let Some(status) = status {
+            self.received.extend(status);
+        }
+    }
+
+    /// Let the servos act on an instruction packet
+    fn dispatch(&mut self, packet: Packet) {
+        let protocol = packet.protocol;
+
+        if !packet.checksum_ok {
+            // Servos answer a corrupted packet with the checksum error only if they are sure it was meant for them
+            if let Some(servo) = self.listening(protocol, packet.id) {
+                let status = self.servos[servo].status(Some(Fault::Checksum), &[]);
+                self.respond(Some(status));
+            }
+            return;
+        }
+
+        if packet.id != BROADCAST_ID {
+            if let Some(servo) = self.listening(protocol, packet.id) {
+                let status = self.servos[servo].execute(&packet, false);
+                self.respond(status);
+            }
+            return;
+        }
+
+        let address_size = match protocol {
+            Protocol::Protocol1 => 1,
+            Protocol::Protocol2 => 2,
+        };
+        let parameters = &packet.parameters;
+        let field = |data: &[u8]| -> usize {data.iter().rev().fold(0, |v, b| v << 8 | *b as usize)};
+
+        match packet.instruction {
+            PING => {
+                if protocol == Protocol::Protocol2 {
+                    let mut ids: Vec<u8> = self.servos.iter()
+                        .filter(|s| s.protocol() == protocol && Some(s.baud_rate) == self.baud_rate)
+                        .map(|s| s.id())
+                        .collect();
+                    ids.sort();
+                    for id in ids {
+                        if let Some(servo) = self.listening(protocol, id) {
+                            let status = self.servos[servo].execute(&packet, false);
+                            self.respond(status);
+                        }
+                    }
+                }
+            },
+            SYNC_WRITE if parameters.len() >= 2 * address_size => {
+                let address = field(&parameters[..address_size]);
+                let length = field(&parameters[address_size..2 * address_size]);
+                for entry in parameters[2 * address_size..].chunks(1 + length) {
+                    if entry.len() != 1 + length {
+                        break;
+                    }
+                    if let Some(servo) = self.listening(protocol, entry[0]) {
+                        let _result = self.servos[servo].write(address, &entry[1..]);
+                    }
+                }
+            },
+            SYNC_READ if protocol == Protocol::Protocol2 && parameters.len() >= 4 => {
+                let (address, length) = (field(&parameters[..2]), field(&parameters[2..4]));
+                for id in &parameters[4..] {
+                    if let Some(servo) = self.listening(protocol, *id) {
+                        let status = self.servos[servo].reply_read(address, length);
+                        self.respond(status);
+                    }
+                }
+            },
+            BULK_READ => {
+                // Protocol 1 lists length, ID and address after a reserved byte, protocol 2 lists ID, address and length
+                let entries = match protocol {
+                    Protocol::Protocol1 if !parameters.is_empty() => parameters[1..].chunks(3)
+                        .filter(|e| e.len() == 3)
+                        .map(|e| (e[1], e[2] as usize, e[0] as usize))
+                        .collect(),
+                    Protocol::Protocol2 => parameters.chunks(5)
+                        .filter(|e| e.len() == 5)
+                        .map(|e| (e[0], field(&e[1..3]), field(&e[3..5])))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                for (id, address, length) in entries {
+                    if let Some(servo) = self.listening(protocol, id) {
+                        let status = self.servos[servo].reply_read(address, length);
+                        self.respond(status);
+                    }
+                }
+            },
+            BULK_WRITE if protocol == Protocol::Protocol2 => {
+                let mut rest = &parameters[..];
+                while rest.len() >= 5 {
+                    let (id, address, length) = (rest[0], field(&rest[1..3]), field(&rest[3..5]));
+                    if rest.len() < 5 + length {
+                        break;
+                    }
+                    if let Some(servo) = self.listening(protocol, id) {
+                        let _result = self.servos[servo].write(address, &rest[5..5 + length]);
+                    }
+                    rest = &rest[5 + length..];
+                }
+            },
+            _ => {
+                let servos: Vec<usize> = (0..self.servos.len())
+                    .filter(|i| self.servos[*i].protocol() == protocol && Some(self.servos[*i].baud_rate) == self.baud_rate)
+                    .collect();
+                for servo in servos {
+                    self.servos[servo].execute(&packet, true);
+                }
+            },
+        }
+    }
+}
+
+impl Interface for SimulatedBus {
+    type Error = CommunicationError;
+
+    fn set_baud_rate(&mut self, b: BaudRate) -> Result<(), CommunicationError> {
+        self.baud_rate = Some(b);
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), CommunicationError> {
+        self.timeout = Some(timeout);
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        self.received.clear();
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), CommunicationError> {
+        if self.received.len() < data.len() {
+            self.received.clear();
+            if let Some(timeout) = self.timeout {
+                self.advance(timeout);
+            }
+            return Err(CommunicationError::TimedOut);
+        }
+        for b in data.iter_mut() {
+            *b = self.received.pop_front().expect("the length was checked");
+        }
+        self.transfer(data.len());
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), CommunicationError> {
+        self.written.extend_from_slice(data);
+        self.transfer(data.len());
+        while let Some((packet, length)) = parse_packet(&self.written) {
+            self.written.drain(..length);
+            if let Some(packet) = packet {
+                self.dispatch(packet);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bus() -> SimulatedBus {
+        let mut bus = SimulatedBus::new();
+        bus.add(VirtualServo::from_model(Protocol::Protocol2, 1020, 1, BaudRate::Baud57600).unwrap());
+        bus.add(VirtualServo::from_model(Protocol::Protocol2, 0xA918, 2, BaudRate::Baud57600).unwrap());
+        bus.add(VirtualServo::from_model(Protocol::Protocol1, 0x001D, 3, BaudRate::Baud1000000).unwrap());
+        bus
+    }
+
+    #[test]
+    fn test_enumerate_and_connect() {
+        let mut bus = bus();
+        assert!(bus.servo_mut(Protocol::Protocol1, 3).unwrap().set_register("Firmware Version", 36));
+        let servos = ::enumerate(&mut bus).unwrap();
+        assert_eq!(servos.len(), 3);
+        match servos[0] {
+            ::ServoInfo::Protocol1(ref info) => {
+                assert_eq!((u8::from(info.id), info.model_number, info.fw_version), (3, 0x001D, 36));
+                assert_eq!(info.baud_rate, BaudRate::Baud1000000);
+            },
+            ref info => panic!("expected the protocol 1 servo first, found {:?}", info),
+        }
+
+        for info in servos {
+            let mut servo = ::connect(&mut bus, info).unwrap();
+            servo.set_enable_torque(&mut bus, true).unwrap();
+        }
+        assert_eq!(bus.servo(Protocol::Protocol2, 1).unwrap().register("Torque Enable"), Some(1));
+        assert_eq!(bus.servo(Protocol::Protocol2, 2).unwrap().register("Torque Enable"), Some(1));
+        assert_eq!(bus.servo(Protocol::Protocol1, 3).unwrap().register("Torque Enable"), Some(1));
+    }
+
+    #[test]
+    fn test_access_rules() {
+        let mut bus = bus();
+        let id = ::protocol2::ServoID::new(2);
+        let mut servo = ::pro::M4210S260R::new(id, BaudRate::Baud57600);
+
+        servo.write(&mut bus, ::pro::control_table::TorqueEnable::new(true)).unwrap();
+        assert_eq!(servo.write(&mut bus, ::pro::control_table::Id::new(5)), Err(::protocol2::Error::Processing(::protocol2::ProcessingError::AccessError)));
+        assert_eq!(servo.write_raw(&mut bus, 0, &[0, 0]), Err(::protocol2::Error::Processing(::protocol2::ProcessingError::AccessError)));
+        assert_eq!(servo.write(&mut bus, ::pro::control_table::Id::new(253)), Err(::protocol2::Error::Processing(::protocol2::ProcessingError::AccessError)));
+
+        servo.write(&mut bus, ::pro::control_table::TorqueEnable::new(false)).unwrap();
+        assert_eq!(servo.write(&mut bus, ::pro::control_table::Id::new(253)), Err(::protocol2::Error::Processing(::protocol2::ProcessingError::DataRangeError)));
+        servo.write(&mut bus, ::pro::control_table::Id::new(5)).unwrap();
+        assert!(bus.servo(Protocol::Protocol2, 5).is_some());
+
+        let mut servo = ::dynamixel::mx28::MX28::new(::protocol1::ServoID::new(3), BaudRate::Baud1000000);
+        let mut data = [0u8; 2];
+        servo.read_raw(&mut bus, 0, &mut data).unwrap();
+        assert_eq!(data, [0x1d, 0x00]);
+        assert_eq!(servo.write_raw(&mut bus, 0, &[0, 0]), Err(::protocol1::Error::Processing(::protocol1::ProcessingError::RANGE)));
+    }
+
+    #[test]
+    fn test_status_return_level() {
+        let mut bus = bus();
+        let mut servo = ::dynamixel::mx28::MX28::new(::protocol1::ServoID::new(3), BaudRate::Baud1000000);
+        servo.set_status_return_level(&mut bus, StatusReturnLevel::PingOnly).unwrap();
+        assert_eq!(bus.servo(Protocol::Protocol1, 3).unwrap().register("Status Return Level"), Some(0));
+
+        // Reads are not sent to a servo that does not answer them
+        let transactions = servo.retry_stats().transactions;
+        assert_eq!(servo.read_data::<::dynamixel::mx28::control_table::PresentPosition>(&mut bus), Err(::protocol1::Error::NotAnswered));
+        assert_eq!(servo.retry_stats().transactions, transactions);
+
+        // The level itself is always asked for, the assumed level may be wrong
+        assert_eq!(servo.read_status_return_level(&mut bus), Err(::protocol1::Error::Communication(::CommunicationError::TimedOut)));
+        bus.servo_mut(Protocol::Protocol1, 3).unwrap().set_register("Status Return Level", 1);
+        assert_eq!(servo.read_status_return_level(&mut bus), Ok(StatusReturnLevel::Read));
+
+        servo.set_status_return_level(&mut bus, StatusReturnLevel::All).unwrap();
+        assert_eq!(servo.read_status_return_level(&mut bus), Ok(StatusReturnLevel::All));
+    }
+
+    #[test]
+    fn test_sync_and_bulk() {
+        let mut bus = bus();
+        bus.set_baud_rate(BaudRate::Baud57600).unwrap();
+
+        // Sync write LED of the XM430, the unknown ID 7 is ignored
+        let led = bus.servo(Protocol::Protocol2, 1).unwrap().control_table().register("LED").unwrap().address as u8;
+        let sync_write = instruction(SYNC_WRITE, &[led, 0, 1, 0, 1, 1, 7, 1]);
+        let (packet, length) = parse_packet(&sync_write).unwrap();
+        let packet = packet.unwrap();
+        assert_eq!(length, sync_write.len());
+        assert_eq!(packet.parameters, vec![led, 0, 1, 0, 1, 1, 7, 1]);
+        bus.write(&sync_write).unwrap();
+        assert_eq!(bus.servo(Protocol::Protocol2, 1).unwrap().register("LED"), Some(1));
+
+        // Bulk read the model numbers of both protocol 2 servos
+        bus.write(&instruction(BULK_READ, &[1, 0, 0, 2, 0, 2, 0, 0, 2, 0])).unwrap();
+        let first = mock::protocol2::status(::protocol2::ServoID::new(1), &[0xfc, 0x03]);
+        let second = mock::protocol2::status(::protocol2::ServoID::new(2), &[0x18, 0xa9]);
+        let mut received = vec![0u8; first.len() + second.len()];
+        bus.read(&mut received).unwrap();
+        assert_eq!(received, [first, second].concat());
+
+        // Statuses left unread, here from a broadcast ping, are flushed before a sync read
+        bus.write(&instruction(PING, &[])).unwrap();
+        assert!(bus.pending() > 0);
+        let mut model_numbers = [0u8; 4];
+        ::protocol2::sync_read(&mut bus, 0, &[::protocol2::ServoID::new(1), ::protocol2::ServoID::new(2)], &mut model_numbers).unwrap();
+        assert_eq!(model_numbers, [0xfc, 0x03, 0x18, 0xa9]);
+    }
+
+    #[test]
+    fn test_resync_after_malformed_header() {
+        let mut bus = bus();
+        bus.set_baud_rate(BaudRate::Baud57600).unwrap();
+
+        // A protocol 2 header too short to hold an instruction
+        bus.write(&[0xff, 0xff, 0xfd, 0x00, 0x01, 0x00, 0x00]).unwrap();
+        assert_eq!(bus.pending(), 0);
+
+        let mut servo = ::pro::M4210S260R::new(::protocol2::ServoID::new(2), BaudRate::Baud57600);
+        assert!(servo.ping(&mut bus).is_ok());
+    }
+
+    /// A protocol 2 broadcast instruction packet, without byte stuffing
+    fn instruction(instruction: u8, parameters: &[u8]) -> Vec<u8> {
+        let length = parameters.len() + 3;
+        let mut packet = vec![0xff, 0xff, 0xfd, 0x00, BROADCAST_ID, length as u8, (length >> 8) as u8, instruction];
+        packet.extend_from_slice(parameters);
+        let mut crc = ::protocol2::crc::CRC::new();
+        crc.add(&packet);
+        let crc = u16::from(crc);
+        packet.extend_from_slice(&[crc as u8, (crc >> 8) as u8]);
+        packet
+    }
+}
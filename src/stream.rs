@@ -0,0 +1,235 @@
+//! Interfaces over `std::io` streams
+//!
+//! Requires the `std` feature. Useful for reaching servos through ser2net TCP bridges, Unix domain sockets and pseudo-terminals.
+//! Streams have no baud rate, `set_baud_rate` is accepted and ignored, the bridge must be configured with the baud rate of the servos.
+//!
+//! - `StreamInterface` works with streams that support read timeouts, such as `TcpStream` and `UnixStream`.
+//! - `ThreadedInterface` works with any stream, by reading on a separate thread. Use it for pseudo-terminals and pipes.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use BaudRate;
+use Interface;
+
+/// A stream with read timeouts
+pub trait TimeoutStream: Read + Write {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()>;
+    fn set_nonblocking(&mut self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl TimeoutStream for TcpStream {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+#[cfg(unix)]
+impl TimeoutStream for ::std::os::unix::net::UnixStream {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        ::std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> io::Result<()> {
+        ::std::os::unix::net::UnixStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+/// An `Interface` over a stream with read timeouts
+///
+/// The read timeout of the stream is only changed when the timeout of the transaction changes.
+pub struct StreamInterface<S> {
+    stream: S,
+    timeout: Option<Duration>,
+}
+
+/// An `Interface` for a TCP bridge, such as ser2net
+pub type TcpInterface = StreamInterface<TcpStream>;
+
+impl<S: TimeoutStream> StreamInterface<S> {
+    pub fn new(stream: S) -> Self {
+        StreamInterface {
+            stream: stream,
+            timeout: None,
+        }
+    }
+
+    /// Return the stream
+    pub fn release(self) -> S {
+        self.stream
+    }
+}
+
+impl StreamInterface<TcpStream> {
+    /// Connect to a TCP bridge, disabling Nagle's algorithm to not delay instructions
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(StreamInterface::new(stream))
+    }
+}
+
+impl<S: TimeoutStream> Interface for StreamInterface<S> {
+    type Error = io::Error;
+
+    fn set_baud_rate(&mut self, _b: BaudRate) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), io::Error> {
+        if self.timeout != Some(timeout) {
+            self.stream.set_read_timeout(Some(timeout))?;
+            self.timeout = Some(timeout);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        if self.stream.set_nonblocking(true).is_ok() {
+            let mut buf = [0u8; 64];
+            while let Ok(n) = self.stream.read(&mut buf) {
+                if n == 0 {break;}
+            }
+            let _res = self.stream.set_nonblocking(false);
+        }
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), io::Error> {
+        // Expired read timeouts are reported as `WouldBlock` on unix
+        self.stream.read_exact(data).map_err(|e| match e.kind() {
+            io::ErrorKind::WouldBlock => io::Error::new(io::ErrorKind::TimedOut, e),
+            _ => e,
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        self.stream.write_all(data)?;
+        self.stream.flush()
+    }
+}
+
+/// An `Interface` over any stream, reading on a separate thread
+///
+/// The reading thread exits when the stream is closed or the interface is dropped and the next byte arrives.
+pub struct ThreadedInterface<W> {
+    writer: W,
+    received: mpsc::Receiver<io::Result<u8>>,
+    timeout: Duration,
+}
+
+impl<W: Write> ThreadedInterface<W> {
+    /// Create an interface reading from `reader` and writing to `writer`, usually two handles to the same stream.
+    pub fn new<R: Read + Send + 'static>(mut reader: R, writer: W) -> Self {
+        let (sender, received) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            loop {
+                let sent = match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => buf[..n].iter().try_for_each(|b| sender.send(Ok(*b))),
+                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => Ok(()),
+                    Err(e) => {
+                        let _res = sender.send(Err(e));
+                        break;
+                    },
+                };
+                if sent.is_err() {
+                    break;
+                }
+            }
+        });
+        ThreadedInterface {
+            writer: writer,
+            received: received,
+            timeout: ::DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl<W: Write> Interface for ThreadedInterface<W> {
+    type Error = io::Error;
+
+    fn set_baud_rate(&mut self, _b: BaudRate) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> Result<(), io::Error> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        while let Ok(Ok(_)) = self.received.try_recv() {}
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), io::Error> {
+        for byte in data.iter_mut() {
+            *byte = match self.received.recv_timeout(self.timeout) {
+                Ok(byte) => byte?,
+                Err(mpsc::RecvTimeoutError::Timeout) => return Err(io::Error::new(io::ErrorKind::TimedOut, "no data received")),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream closed")),
+            };
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        self.writer.write_all(data)?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use CommunicationError;
+
+    #[test]
+    fn test_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let bridge = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut data = [0u8; 3];
+            stream.read_exact(&mut data).unwrap();
+            data.reverse();
+            stream.write_all(&data).unwrap();
+            stream.read_exact(&mut data[..1]).unwrap();
+        });
+
+        let mut interface = TcpInterface::connect(addr).unwrap();
+        interface.set_timeout(Duration::from_millis(50)).unwrap();
+        interface.flush();
+        interface.write(&[1, 2, 3]).unwrap();
+        let mut data = [0u8; 3];
+        interface.read(&mut data).unwrap();
+        assert_eq!(data, [3, 2, 1]);
+
+        let error = interface.read(&mut data).unwrap_err();
+        assert_eq!(CommunicationError::from(error), CommunicationError::TimedOut);
+
+        interface.write(&[0]).unwrap();
+        bridge.join().unwrap();
+    }
+
+    #[test]
+    fn test_threaded() {
+        let mut interface = ThreadedInterface::new(io::Cursor::new(vec![1, 2, 3]), Vec::new());
+        interface.set_timeout(Duration::from_millis(10)).unwrap();
+        interface.write(&[4, 5]).unwrap();
+        assert_eq!(interface.writer, vec![4, 5]);
+
+        let mut data = [0u8; 2];
+        interface.read(&mut data).unwrap();
+        assert_eq!(data, [1, 2]);
+        assert_eq!(interface.read(&mut data).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,467 @@
+//! Non-blocking transactions of an instruction and the status answering it
+//!
+//! A `Transaction` holds the serialized instruction and decodes the status incrementally, without touching an interface
+//! and without allocating. This makes it usable from interrupt and DMA callbacks on microcontrollers:
+//!
+//! 1. Create the transaction, e.g. with `transaction::protocol2::read`, and transmit `unwritten()`, reporting progress with `wrote(n)`.
+//! 2. `feed` received bytes as they arrive, `wanted()` tells how many bytes are still missing.
+//! 3. When `is_finished()` returns `true`, `finish()` returns the parsed status.
+//!    If the status does not arrive in time, `timed_out()` returns the result instead.
+//!
+//! A transaction is finished by the first malformed byte it is fed, `finish()` then returns that error.
+//!
+//! The async interfaces are driven by the same transactions.
+
+use lib::cmp;
+
+use CommunicationError;
+use Reply;
+
+/// Room for the largest instruction of either protocol, including protocol 2 byte stuffing
+pub(crate) const MAX_PACKET_LENGTH: usize = 2 * ::protocol2::MAX_DATA_LENGTH + 10;
+
+/// A serialized instruction
+#[derive(Clone)]
+pub(crate) struct Packet {
+    data: [u8; MAX_PACKET_LENGTH],
+    length: usize,
+}
+
+impl Packet {
+    /// A packet without bytes, for only receiving statuses
+    pub fn empty() -> Packet {
+        Packet {
+            data: [0u8; MAX_PACKET_LENGTH],
+            length: 0,
+        }
+    }
+
+    pub fn from_bytes<It: IntoIterator<Item=u8>>(bytes: It) -> Packet {
+        let mut packet = Packet::empty();
+        for b in bytes {
+            packet.data[packet.length] = b;
+            packet.length += 1;
+        }
+        packet
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data[..self.length]
+    }
+}
+
+/// Incremental decoding of a status packet
+pub trait Decode {
+    type Output;
+    type Error: From<CommunicationError> + Clone;
+
+    /// The number of bytes still missing from the packet, as far as it is known yet
+    fn wanted(&self) -> usize;
+
+    /// Feed received bytes, at most `wanted()` of them
+    fn feed(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+
+    fn finish(self) -> Result<Self::Output, Self::Error>;
+}
+
+impl<T: ::protocol1::Status> Decode for ::protocol1::StatusDecoder<T> {
+    type Output = T;
+    type Error = ::protocol1::Error;
+
+    fn wanted(&self) -> usize {
+        ::protocol1::StatusDecoder::wanted(self)
+    }
+
+    fn feed(&mut self, data: &[u8]) -> Result<(), ::protocol1::Error> {
+        ::protocol1::StatusDecoder::feed(self, data)
+    }
+
+    fn finish(self) -> Result<T, ::protocol1::Error> {
+        ::protocol1::StatusDecoder::finish(self)
+    }
+}
+
+impl<T: ::protocol2::Status> Decode for ::protocol2::StatusDecoder<T> {
+    type Output = ::protocol2::Response<T>;
+    type Error = ::protocol2::Error;
+
+    fn wanted(&self) -> usize {
+        ::protocol2::StatusDecoder::wanted(self)
+    }
+
+    fn feed(&mut self, data: &[u8]) -> Result<(), ::protocol2::Error> {
+        ::protocol2::StatusDecoder::feed(self, data)
+    }
+
+    fn finish(self) -> Result<::protocol2::Response<T>, ::protocol2::Error> {
+        ::protocol2::StatusDecoder::finish(self)
+    }
+}
+
+/// Writing an instruction and decoding the status answering it
+pub struct Transaction<D: Decode> {
+    packet: Packet,
+    written: usize,
+    decoder: Option<D>,
+    error: Option<D::Error>,
+    reply: Reply,
+}
+
+impl<D: Decode> Transaction<D> {
+    /// Write `packet` and decode the status as far as `reply` expects one
+    pub(crate) fn new(packet: Packet, decoder: D, reply: Reply) -> Self {
+        Transaction {
+            packet: packet,
+            written: 0,
+            decoder: if reply == Reply::Never {None} else {Some(decoder)},
+            error: None,
+            reply: reply,
+        }
+    }
+
+    /// Only decode a status, as when listening for more answers to a broadcast instruction
+    #[cfg(all(feature="async", feature="std"))]
+    pub(crate) fn receive(decoder: D) -> Self {
+        Transaction::new(Packet::empty(), decoder, Reply::Always)
+    }
+
+    /// The bytes not yet written
+    pub fn unwritten(&self) -> &[u8] {
+        &self.packet.as_slice()[self.written..]
+    }
+
+    /// Mark `n` bytes as written
+    pub fn wrote(&mut self, n: usize) {
+        self.written = cmp::min(self.written + n, self.packet.length);
+    }
+
+    /// The number of bytes to receive once the instruction is written, `0` when the status is complete
+    pub fn wanted(&self) -> usize {
+        match self.decoder {
+            Some(ref decoder) => decoder.wanted(),
+            None => 0,
+        }
+    }
+
+    /// Feed received bytes, returning how many of them belong to the status
+    ///
+    /// Bytes beyond the end of the status are not consumed. After an error, no more bytes are consumed.
+    pub fn feed(&mut self, data: &[u8]) -> Result<usize, D::Error> {
+        match self.decoder {
+            Some(ref mut decoder) => {
+                let consumed = cmp::min(data.len(), decoder.wanted());
+                if let Err(e) = decoder.feed(&data[..consumed]) {
+                    // The decoder can not be trusted after an error, e.g. with the length of a corrupted header
+                    self.decoder = None;
+                    self.error = Some(e.clone());
+                    return Err(e);
+                }
+                Ok(consumed)
+            },
+            None => Ok(0),
+        }
+    }
+
+    /// Returns `true` when the instruction is written and the status is complete, or not expected, or when feeding failed
+    pub fn is_finished(&self) -> bool {
+        self.error.is_some() || (self.unwritten().is_empty() && self.wanted() == 0)
+    }
+
+    /// The status, `None` if no status was expected
+    pub fn finish(&mut self) -> Result<Option<D::Output>, D::Error> {
+        if let Some(ref e) = self.error {
+            return Err(e.clone());
+        }
+        match self.decoder.take() {
+            Some(decoder) => decoder.finish().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The result when the status did not arrive in time
+    pub fn timed_out(&mut self) -> Result<Option<D::Output>, D::Error> {
+        if let Some(ref e) = self.error {
+            return Err(e.clone());
+        }
+        self.decoder = None;
+        match self.reply {
+            Reply::Maybe => Ok(None),
+            _ => Err(D::Error::from(CommunicationError::TimedOut)),
+        }
+    }
+}
+
+/// Transactions with protocol 1 servos
+pub mod protocol1 {
+    use super::{Decode, Transaction};
+    use StatusReturnLevel;
+    use Reply;
+    use protocol1::{instruction, packet, Error, PacketID, ReadRegister, ServoID, StatusDecoder, WriteRegister};
+
+    /// Decoding the status to a ping
+    pub struct Ping(StatusDecoder<instruction::Pong>);
+
+    impl Decode for Ping {
+        type Output = ();
+        type Error = Error;
+
+        fn wanted(&self) -> usize {
+            self.0.wanted()
+        }
+
+        fn feed(&mut self, data: &[u8]) -> Result<(), Error> {
+            self.0.feed(data)
+        }
+
+        fn finish(self) -> Result<(), Error> {
+            self.0.finish().map(|_| ())
+        }
+    }
+
+    /// Decoding the status to a read of the register `R`
+    pub struct Read<R: ReadRegister>(StatusDecoder<instruction::ReadDataResponse<R>>);
+
+    impl<R: ReadRegister> Decode for Read<R> {
+        type Output = R;
+        type Error = Error;
+
+        fn wanted(&self) -> usize {
+            self.0.wanted()
+        }
+
+        fn feed(&mut self, data: &[u8]) -> Result<(), Error> {
+            self.0.feed(data)
+        }
+
+        fn finish(self) -> Result<R, Error> {
+            self.0.finish().map(|response| response.data)
+        }
+    }
+
+    /// Decoding the status to a write
+    pub struct Write(StatusDecoder<instruction::WriteDataResponse>);
+
+    impl Decode for Write {
+        type Output = ();
+        type Error = Error;
+
+        fn wanted(&self) -> usize {
+            self.0.wanted()
+        }
+
+        fn feed(&mut self, data: &[u8]) -> Result<(), Error> {
+            self.0.feed(data)
+        }
+
+        fn finish(self) -> Result<(), Error> {
+            self.0.finish().map(|_| ())
+        }
+    }
+
+    /// Ping the servo with the given `id`
+    pub fn ping(id: ServoID) -> Transaction<Ping> {
+        let ping = instruction::Ping::new(PacketID::from(id));
+        Transaction::new(packet(&ping), Ping(StatusDecoder::new(PacketID::from(id))), Reply::Always)
+    }
+
+    /// Read the register `R` from the servo with the given `id`
+    pub fn read<R: ReadRegister>(id: ServoID) -> Transaction<Read<R>> {
+        let read = instruction::ReadData::<R>::new(PacketID::from(id));
+        Transaction::new(packet(&read), Read(StatusDecoder::new(PacketID::from(id))), Reply::Always)
+    }
+
+    /// Write `register` to the servo with the given `id`, which answers as configured by `status_return_level`
+    pub fn write<W: WriteRegister>(id: ServoID, register: W, status_return_level: StatusReturnLevel) -> Transaction<Write> {
+        let write = instruction::WriteData::new(PacketID::from(id), register);
+        Transaction::new(packet(&write), Write(StatusDecoder::new(PacketID::from(id))), status_return_level.write_reply())
+    }
+}
+
+/// Transactions with protocol 2 servos
+pub mod protocol2 {
+    use super::{Decode, Transaction};
+    use BaudRate;
+    use StatusReturnLevel;
+    use Reply;
+    use protocol2::{instruction, packet, Error, PacketID, ReadRegister, ServoID, ServoInfo, StatusDecoder, WriteRegister};
+
+    /// A decoded status, with the alert flag of the servo
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub struct Status<T> {
+        pub alert: bool,
+        pub value: T,
+    }
+
+    /// Decoding the status to a ping
+    pub struct Ping(StatusDecoder<instruction::Pong>, BaudRate);
+
+    impl Decode for Ping {
+        type Output = Status<ServoInfo>;
+        type Error = Error;
+
+        fn wanted(&self) -> usize {
+            self.0.wanted()
+        }
+
+        fn feed(&mut self, data: &[u8]) -> Result<(), Error> {
+            self.0.feed(data)
+        }
+
+        fn finish(self) -> Result<Status<ServoInfo>, Error> {
+            let baud_rate = self.1;
+            self.0.finish().map(|response| Status {
+                alert: response.alert,
+                value: ServoInfo {
+                    baud_rate: baud_rate,
+                    model_number: response.status.model_number,
+                    fw_version: response.status.fw_version,
+                    id: response.status.id,
+                },
+            })
+        }
+    }
+
+    /// Decoding the status to a read of the register `R`
+    pub struct Read<R: ReadRegister>(StatusDecoder<instruction::ReadResponse<R>>);
+
+    impl<R: ReadRegister> Decode for Read<R> {
+        type Output = Status<R>;
+        type Error = Error;
+
+        fn wanted(&self) -> usize {
+            self.0.wanted()
+        }
+
+        fn feed(&mut self, data: &[u8]) -> Result<(), Error> {
+            self.0.feed(data)
+        }
+
+        fn finish(self) -> Result<Status<R>, Error> {
+            self.0.finish().map(|response| Status{alert: response.alert, value: response.status.value})
+        }
+    }
+
+    /// Decoding the status to a write
+    pub struct Write(StatusDecoder<instruction::WriteResponse>);
+
+    impl Decode for Write {
+        type Output = Status<()>;
+        type Error = Error;
+
+        fn wanted(&self) -> usize {
+            self.0.wanted()
+        }
+
+        fn feed(&mut self, data: &[u8]) -> Result<(), Error> {
+            self.0.feed(data)
+        }
+
+        fn finish(self) -> Result<Status<()>, Error> {
+            self.0.finish().map(|response| Status{alert: response.alert, value: ()})
+        }
+    }
+
+    /// Ping the servo with the given `id`
+    ///
+    /// `baud_rate` is only recorded in the returned `ServoInfo`.
+    pub fn ping(id: ServoID, baud_rate: BaudRate) -> Transaction<Ping> {
+        let ping = instruction::Ping::new(PacketID::from(id));
+        Transaction::new(packet(&ping), Ping(StatusDecoder::new(), baud_rate), Reply::Always)
+    }
+
+    /// Read the register `R` from the servo with the given `id`
+    pub fn read<R: ReadRegister>(id: ServoID) -> Transaction<Read<R>> {
+        let read = instruction::Read::<R>::new(PacketID::from(id));
+        Transaction::new(packet(&read), Read(StatusDecoder::new()), Reply::Always)
+    }
+
+    /// Write `register` to the servo with the given `id`, which answers as configured by `status_return_level`
+    pub fn write<W: WriteRegister>(id: ServoID, register: W, status_return_level: StatusReturnLevel) -> Transaction<Write> {
+        let write = instruction::Write::new(PacketID::from(id), register);
+        Transaction::new(packet(&write), Write(StatusDecoder::new()), status_return_level.write_reply())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use BaudRate;
+    use StatusReturnLevel;
+
+    #[test]
+    fn test_protocol1_ping() {
+        let mut transaction = protocol1::ping(::protocol1::ServoID::new(3));
+        assert_eq!(transaction.unwritten(), &[0xff, 0xff, 0x03, 0x02, 0x01, 0xf9]);
+        transaction.wrote(4);
+        assert!(!transaction.is_finished());
+        transaction.wrote(2);
+
+        // The bytes arrive one by one, as in a receive interrupt, followed by the start of something else
+        let received = [0xff, 0xff, 0x03, 0x02, 0x00, 0xfa, 0xff];
+        let mut consumed = 0;
+        for b in received.iter() {
+            consumed += transaction.feed(&[*b]).unwrap();
+        }
+        assert_eq!(consumed, 6);
+        assert!(transaction.is_finished());
+        assert_eq!(transaction.finish(), Ok(Some(())));
+
+        let mut transaction = protocol1::ping(::protocol1::ServoID::new(3));
+        transaction.wrote(6);
+        assert_eq!(transaction.timed_out(), Err(::protocol1::Error::Communication(CommunicationError::TimedOut)));
+    }
+
+    #[test]
+    fn test_feed_after_error() {
+        let mut transaction = protocol1::ping(::protocol1::ServoID::new(3));
+        transaction.wrote(6);
+
+        // A corrupted header announcing 255 bytes
+        let error = ::protocol1::Error::Format(::protocol1::FormatError::Length);
+        assert_eq!(transaction.feed(&[0xff, 0xff, 0x03, 0xff]), Err(error.clone()));
+        assert_eq!(transaction.wanted(), 0);
+        assert!(transaction.is_finished());
+        assert_eq!(transaction.feed(&[0u8; 255]), Ok(0));
+        assert_eq!(transaction.finish(), Err(error.clone()));
+        assert_eq!(transaction.timed_out(), Err(error));
+    }
+
+    #[test]
+    fn test_protocol2_malformed_header() {
+        let headers = [
+            ([0xff, 0xff, 0xfd, 0x00, 0xfe, 0x07, 0x00, 0x55, 0x00], ::protocol2::FormatError::ID),
+            ([0xff, 0xff, 0xfd, 0x00, 0x01, 0x01, 0x00, 0x55, 0x00], ::protocol2::FormatError::Length),
+        ];
+        for &(header, error) in headers.iter() {
+            let mut transaction = protocol2::ping(::protocol2::ServoID::new(1), BaudRate::Baud57600);
+            transaction.wrote(10);
+            let error = ::protocol2::Error::Format(error);
+            assert_eq!(transaction.feed(&header), Err(error.clone()));
+            assert!(transaction.is_finished());
+            assert_eq!(transaction.finish().err(), Some(error));
+        }
+    }
+
+    #[test]
+    fn test_protocol2_ping() {
+        let mut transaction = protocol2::ping(::protocol2::ServoID::new(1), BaudRate::Baud57600);
+        assert_eq!(transaction.unwritten(), &[0xff, 0xff, 0xfd, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4e]);
+        transaction.wrote(10);
+
+        assert_eq!(transaction.wanted(), 9);
+        assert_eq!(transaction.feed(&[0xff, 0xff, 0xfd, 0x00, 0x01, 0x07, 0x00, 0x55, 0x00]), Ok(9));
+        assert_eq!(transaction.wanted(), 5);
+        assert_eq!(transaction.feed(&[0x06, 0x04, 0x26, 0x65, 0x5d]), Ok(5));
+        let status = transaction.finish().unwrap().unwrap();
+        assert!(!status.alert);
+        assert_eq!(status.value.model_number, 0x0406);
+        assert_eq!(status.value.baud_rate, BaudRate::Baud57600);
+
+        // Servos that do not answer writes complete the transaction once it is written
+        let mut transaction = protocol2::write(::protocol2::ServoID::new(1), ::pro::control_table::LedRed::new(0), StatusReturnLevel::Read);
+        let written = transaction.unwritten().len();
+        transaction.wrote(written);
+        assert!(transaction.is_finished());
+        assert_eq!(transaction.finish(), Ok(None));
+    }
+}